@@ -0,0 +1,121 @@
+use divan::Bencher;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use std::hint::black_box;
+
+use b3::{
+	likelihood::{
+		CpuLikelihood, LikelihoodTrait, NodeMajorLikelihood, Row,
+		ThreadedLikelihood, Transition,
+	},
+	util::{dna_to_patterns, Patterns},
+};
+use data::seq::DnaSeq;
+
+/// Size of the primate mitochondrial alignment used by `benches/primate.py`.
+const TAXA: usize = 12;
+const LENGTH: usize = 898;
+
+/// Unique site patterns of a synthetic alignment of the primate size, and
+/// their weights.  Every taxon is a copy of a random ancestor with some of
+/// the sites mutated, so that the patterns repeat like in real data.
+fn sites() -> (Vec<Vec<Row<4>>>, Vec<f64>) {
+	let mut rng = SmallRng::seed_from_u64(4);
+	let base = |rng: &mut SmallRng| b"ACGT"[rng.random_range(0..4)] as char;
+
+	let ancestor: String = (0..LENGTH).map(|_| base(&mut rng)).collect();
+	let sequences: Vec<DnaSeq> = (0..TAXA)
+		.map(|_| {
+			let sequence: String = ancestor
+				.chars()
+				.map(|ancestral| {
+					if rng.random::<f64>() < 0.2 {
+						base(&mut rng)
+					} else {
+						ancestral
+					}
+				})
+				.collect();
+			DnaSeq::try_from(sequence.as_str()).unwrap()
+		})
+		.collect();
+	let Patterns { rows, weights, .. } = dna_to_patterns::<4>(&sequences);
+	(rows, weights)
+}
+
+/// A full recalculation of a caterpillar tree, where each internal node joins
+/// the previous one with the next leaf.
+fn tree(leaves: usize) -> (Vec<usize>, Vec<Transition<4>>, Vec<usize>) {
+	let mut rng = SmallRng::seed_from_u64(4);
+
+	let mut nodes = Vec::new();
+	let mut transitions = Vec::new();
+	let mut children = Vec::new();
+
+	let mut previous = 0;
+	for leaf in 1..leaves {
+		let node = leaves + leaf - 1;
+		nodes.push(node);
+		children.extend([previous, leaf]);
+		for _ in 0..2 {
+			let mut transition = Transition::<4>::from_element(0.0);
+			for i in 0..4 {
+				for j in 0..4 {
+					transition[(i, j)] = rng.random();
+				}
+			}
+			transitions.push(transition);
+		}
+		previous = node;
+	}
+
+	(nodes, transitions, children)
+}
+
+fn propose_accept(
+	bencher: Bencher,
+	calculator: impl Fn(
+			Vec<Vec<Row<4>>>,
+			Vec<f64>,
+		) -> Box<dyn LikelihoodTrait<4> + Send>
+		+ Sync,
+) {
+	let (sites, weights) = sites();
+	let (nodes, transitions, children) = tree(sites[0].len());
+	bencher.with_inputs(|| {
+		black_box(calculator(sites.clone(), weights.clone()))
+	})
+	.bench_values(|mut calculator| {
+		calculator.propose(&nodes, &transitions, &children).unwrap();
+		calculator.accept().unwrap();
+	})
+}
+
+#[divan::bench]
+fn pattern_major(bencher: Bencher) {
+	propose_accept(bencher, |sites, weights| {
+		Box::new(CpuLikelihood::new(sites, weights))
+	})
+}
+
+#[divan::bench]
+fn node_major(bencher: Bencher) {
+	propose_accept(bencher, |sites, weights| {
+		Box::new(NodeMajorLikelihood::new(sites, weights))
+	})
+}
+
+#[divan::bench(args = [1, 2, 4, 8])]
+fn threaded(bencher: Bencher, threads: usize) {
+	propose_accept(bencher, |sites, weights| {
+		Box::new(
+			ThreadedLikelihood::new(sites, weights, threads)
+				.unwrap(),
+		)
+	})
+}
+
+fn main() {
+	divan::main();
+}
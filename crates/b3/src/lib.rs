@@ -1,19 +1,26 @@
 pub mod clock;
 pub mod likelihood;
 pub mod log;
+pub mod marginal;
 pub mod mcmc;
 pub mod operator;
 pub mod parameter;
 pub mod prior;
+pub mod state;
 pub mod substitution;
 mod transitions;
 mod tree;
 pub mod util;
+mod version;
+
+#[cfg(test)]
+mod testing;
 
 pub use log::PyLogger;
 pub use prior::PyPrior;
 pub use transitions::Transitions;
 pub use tree::Tree;
+pub use version::Version;
 
 use pyo3::prelude::*;
 
@@ -25,8 +32,10 @@ pub fn pymodule(py: Python) -> PyResult<Bound<PyModule>> {
 	m.add_class::<parameter::PyParameter>()?;
 	m.add_class::<tree::PyTree>()?;
 	m.add_class::<operator::PyProposal>()?;
+	m.add_class::<operator::PyCompound>()?;
 	m.add_class::<likelihood::PyLikelihood>()?;
 	m.add_class::<mcmc::Mcmc>()?;
+	m.add_class::<marginal::MarginalLikelihood>()?;
 
 	Ok(m)
 }
@@ -0,0 +1,256 @@
+//! Ancestral state reconstruction.
+//!
+//! The post-order partials calculated by the likelihood give the probability
+//! of the data below a node.  A pre-order pass adds the probability of the rest
+//! of the tree, and together they give the marginal distribution of states at
+//! each internal node.  Joint samples are drawn top-down, conditioning each
+//! node on the state sampled at its parent.
+//!
+//! The root states are weighted equally, the same way the likelihood does.
+use rand::Rng as _;
+
+use super::{Row, Transition};
+use rng::Rng;
+
+/// Accepted state of a likelihood needed to reconstruct ancestral states.
+pub struct Reconstruction<const N: usize> {
+	/// Internal nodes in post-order, with the root last.
+	pub nodes: Vec<usize>,
+	/// Two children for each of `nodes`.
+	pub children: Vec<usize>,
+	/// Transition matrices of the edges above each of `children`.
+	pub transitions: Vec<Transition<N>>,
+	/// Lengths of the edges above each of `children`.
+	pub distances: Vec<f64>,
+	/// Partials of every node, indexed by node and then by pattern.
+	pub partials: Vec<Vec<Row<N>>>,
+}
+
+/// Scales `row` to sum up to 1.
+fn normalize<const N: usize>(row: Row<N>) -> Row<N> {
+	row / row.sum()
+}
+
+/// Samples an index from the unnormalized weights in `row`.
+fn sample<const N: usize>(row: Row<N>, rng: &mut Rng) -> usize {
+	let mut target = rng.random::<f64>() * row.sum();
+	for i in 0..N {
+		target -= row[i];
+		if target < 0.0 {
+			return i;
+		}
+	}
+	// Rounding errors can leave a bit of the total over, it belongs to
+	// the last state with a non-zero weight.
+	(0..N).rev().find(|i| row[*i] > 0.0).unwrap_or(N - 1)
+}
+
+impl<const N: usize> Reconstruction<N> {
+	fn num_leaves(&self) -> usize {
+		self.nodes.len() + 1
+	}
+
+	fn num_patterns(&self) -> usize {
+		self.partials[0].len()
+	}
+
+	/// Pre-order pass over the tree.
+	///
+	/// Returns the probability of the data outside of the subtree of each
+	/// internal node, indexed by `node - num_leaves`, and the same
+	/// probability as seen from the parent end of each edge, indexed like
+	/// `children`.  Both are normalized per pattern.
+	pub fn pre_order(&self) -> (Vec<Vec<Row<N>>>, Vec<Vec<Row<N>>>) {
+		let num_leaves = self.num_leaves();
+		let num_patterns = self.num_patterns();
+
+		// The root has nothing above it
+		let mut above = vec![Vec::new(); self.nodes.len()];
+		above[self.nodes.len() - 1] =
+			vec![Row::<N>::ones(); num_patterns];
+		let mut outside = vec![Vec::new(); self.children.len()];
+
+		for (i, &node) in self.nodes.iter().enumerate().rev() {
+			let mut from_children = [Vec::new(), Vec::new()];
+			for (side, from_child) in
+				from_children.iter_mut().enumerate()
+			{
+				let child = self.children[i * 2 + side];
+				let mut rows =
+					vec![Row::default(); num_patterns];
+				self.transitions[i * 2 + side].mul_slice(
+					&self.partials[child],
+					&mut rows,
+				);
+				*from_child = rows;
+			}
+
+			for side in 0..2 {
+				let edge = i * 2 + side;
+
+				// Everything except for the subtree of the child,
+				// as seen from the parent.
+				let sibling = &from_children[1 - side];
+				outside[edge] = above[node - num_leaves]
+					.iter()
+					.zip(sibling)
+					.map(|(above, sibling)| {
+						normalize(*above * *sibling)
+					})
+					.collect();
+
+				let child = self.children[edge];
+				if child < num_leaves {
+					continue;
+				}
+
+				// Moved down the edge
+				let mut at_child =
+					vec![Row::default(); num_patterns];
+				self.transitions[edge].transpose().mul_slice(
+					&outside[edge],
+					&mut at_child,
+				);
+				above[child - num_leaves] = at_child
+					.into_iter()
+					.map(normalize)
+					.collect();
+			}
+		}
+
+		(above, outside)
+	}
+
+	/// Marginal state probabilities of the internal nodes for each
+	/// pattern, indexed by `node - num_leaves`.
+	pub fn marginal(&self) -> Vec<Vec<Row<N>>> {
+		let num_leaves = self.num_leaves();
+		let (above, _) = self.pre_order();
+
+		above.into_iter()
+			.enumerate()
+			.map(|(i, above)| {
+				self.partials[num_leaves + i]
+					.iter()
+					.zip(above)
+					.map(|(below, above)| {
+						normalize(*below * above)
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	/// Samples the states of all nodes jointly.  Each site is sampled
+	/// independently, using the partials of its pattern from `sites`.  The
+	/// result is indexed by node and then by site.
+	///
+	/// Leaves are sampled too, which resolves ambiguous observations.
+	pub fn sample(
+		&self,
+		sites: &[usize],
+		rng: &mut Rng,
+	) -> Vec<Vec<usize>> {
+		let num_nodes = self.partials.len();
+		let mut out = vec![vec![0; sites.len()]; num_nodes];
+
+		let root = *self.nodes.last().unwrap();
+		for (site, &pattern) in sites.iter().enumerate() {
+			out[root][site] =
+				sample(self.partials[root][pattern], rng);
+		}
+
+		for (i, &node) in self.nodes.iter().enumerate().rev() {
+			for side in 0..2 {
+				let child = self.children[i * 2 + side];
+				let transition =
+					&self.transitions[i * 2 + side];
+
+				for (site, &pattern) in sites.iter().enumerate()
+				{
+					let parent = out[node][site];
+					let weights = transition[parent]
+						* self.partials[child][pattern];
+					out[child][site] = sample(weights, rng);
+				}
+			}
+		}
+
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::likelihood::fixtures::{reconstruction, MODELS};
+	use rand::SeedableRng;
+
+	/// Marginals by summing over every assignment of the internal states.
+	fn brute_force(r: &Reconstruction<4>) -> [Row<4>; 2] {
+		let leaves = [0, 0, 1];
+		let mut out = [Row::<4>::default(); 2];
+
+		for a in 0..4 {
+			for b in 0..4 {
+				// a is the state of node 3, b of the root
+				let root = r.transitions[2][(b, a)]
+					* r.transitions[3][(b, leaves[2])];
+				let node_3 = r.transitions[0][(a, leaves[0])]
+					* r.transitions[1][(a, leaves[1])];
+				let p = root * node_3;
+				out[0][a] += p;
+				out[1][b] += p;
+			}
+		}
+
+		out.map(normalize)
+	}
+
+	#[test]
+	fn marginal() {
+		for model in MODELS {
+			let r = reconstruction(model);
+			let marginal = r.marginal();
+			let expected = brute_force(&r);
+
+			for node in 0..2 {
+				for state in 0..4 {
+					assert!((marginal[node][0][state]
+						- expected[node][state])
+						.abs() < 1e-12);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn joint_sampling() {
+		let mut rng = Rng::seed_from_u64(4);
+
+		for model in MODELS {
+			let r = reconstruction(model);
+			let expected = brute_force(&r);
+
+			let num_sites = 100_000;
+			let samples = r.sample(&vec![0; num_sites], &mut rng);
+
+			for (samples, expected) in
+				samples[3..].iter().zip(&expected)
+			{
+				for (state, expected) in
+					expected.as_array().iter().enumerate()
+				{
+					let count = samples
+						.iter()
+						.filter(|s| **s == state)
+						.count();
+					let frequency =
+						count as f64 / num_sites as f64;
+					assert!((frequency - expected).abs()
+						< 0.01);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,647 @@
+//! Continuous trait likelihood under multivariate Brownian diffusion.
+//!
+//! The trait of a child is normally distributed around the trait of its parent
+//! with the covariance `length * Sigma`, where `length` is the branch length,
+//! optionally multiplied by a relaxed random walk scalar, and `Sigma` is the
+//! diffusion covariance.  The model is parametrized by the precision matrix
+//! `P = Sigma^-1`.
+//!
+//! The internal states are integrated out by passing Gaussian messages from
+//! the leaves to the root, similar to Felsenstein's independent contrasts.  The
+//! message of a node is a mean and a scalar variance (in units of `Sigma`) of
+//! the trait given the data below it.  Because all covariances are multiples
+//! of `Sigma`, the dimensions never have to be decoupled.
+use anyhow::{bail, Result};
+use pyo3::prelude::*;
+use rand_distr::{Distribution, StandardNormal};
+use tracing::{instrument, trace};
+
+use std::f64::consts::PI;
+
+use crate::{
+	parameter::{Parameter, PyParameter},
+	tree::PyTree,
+};
+use linalg::DMatrix;
+use rng::Rng;
+
+/// Combines two independent Gaussian estimates of the same value, each given
+/// by its mean and variance.  A zero variance is an exact observation.
+fn combine(a: &[f64], a_var: f64, b: &[f64], b_var: f64) -> (Vec<f64>, f64) {
+	let total = a_var + b_var;
+	if total == 0.0 {
+		return (a.to_vec(), 0.0);
+	}
+
+	let mean =
+		a.iter().zip(b)
+			.map(|(a, b)| (a * b_var + b * a_var) / total)
+			.collect();
+	(mean, a_var * b_var / total)
+}
+
+/// Gaussian messages of every node, passed from the leaves to the root.
+struct Messages {
+	means: Vec<Vec<f64>>,
+	variances: Vec<f64>,
+}
+
+/// Brownian diffusion over a fixed set of tip values.
+pub struct Diffusion {
+	/// Trait values of each leaf.
+	tips: Vec<Vec<f64>>,
+	/// Mean and sample size of the conjugate normal prior on the root.  The
+	/// root is integrated over a flat prior without it.
+	root_prior: Option<(Vec<f64>, f64)>,
+}
+
+/// Structure of a tree needed by [`Diffusion`].
+pub struct Branches {
+	/// Internal nodes in post-order, with the root last.
+	pub nodes: Vec<usize>,
+	/// Two children for each of `nodes`.
+	pub children: Vec<usize>,
+	/// Scaled length of the branch above each node, indexed by node.
+	pub lengths: Vec<f64>,
+}
+
+impl Diffusion {
+	pub fn new(
+		tips: Vec<Vec<f64>>,
+		root_prior: Option<(Vec<f64>, f64)>,
+	) -> Result<Self> {
+		let Some(dimensions) = tips.first().map(Vec::len) else {
+			bail!("Expected at least one tip");
+		};
+		if dimensions == 0 {
+			bail!("The trait must have at least one dimension");
+		}
+		if tips.iter().any(|tip| tip.len() != dimensions) {
+			bail!("All tips must have {dimensions} dimensions");
+		}
+		if let Some((mean, sample_size)) = &root_prior {
+			if mean.len() != dimensions {
+				bail!("The root prior mean must have {dimensions} dimensions");
+			}
+			if *sample_size <= 0.0 {
+				bail!("The root prior sample size must be positive");
+			}
+		}
+
+		Ok(Self { tips, root_prior })
+	}
+
+	pub fn dimensions(&self) -> usize {
+		self.tips[0].len()
+	}
+
+	pub fn num_leaves(&self) -> usize {
+		self.tips.len()
+	}
+
+	/// Lower Cholesky factor of the row-major `precision`, or `None` if it
+	/// isn't positive definite.
+	fn factor(&self, precision: &[f64]) -> Option<DMatrix<f64>> {
+		let n = self.dimensions();
+		DMatrix::from_vec(n, n, precision.to_vec()).cholesky()
+	}
+
+	/// Log density of `difference` under a normal distribution with the
+	/// covariance `variance * Sigma`, where `factor` is the Cholesky factor of
+	/// the precision.
+	fn log_normal(
+		&self,
+		difference: &[f64],
+		variance: f64,
+		factor: &DMatrix<f64>,
+	) -> f64 {
+		let n = self.dimensions();
+
+		// `c^T P c` is the squared norm of `L^T c`
+		let mut quadratic = 0.0;
+		let mut log_det = 0.0;
+		for j in 0..n {
+			let projected: f64 = (j..n)
+				.map(|i| factor[(i, j)] * difference[i])
+				.sum();
+			quadratic += projected * projected;
+			log_det += 2.0 * factor[(j, j)].ln();
+		}
+
+		-0.5 * (n as f64 * (2.0 * PI * variance).ln() - log_det
+			+ quadratic / variance)
+	}
+
+	fn messages(&self, branches: &Branches) -> Messages {
+		let num_nodes = branches.lengths.len();
+		let mut means = vec![Vec::new(); num_nodes];
+		let mut variances = vec![0.0; num_nodes];
+		means[..self.num_leaves()].clone_from_slice(&self.tips);
+
+		for (i, &node) in branches.nodes.iter().enumerate() {
+			let [left, right] =
+				[0, 1].map(|s| branches.children[i * 2 + s]);
+			(means[node], variances[node]) = combine(
+				&means[left],
+				variances[left] + branches.lengths[left],
+				&means[right],
+				variances[right] + branches.lengths[right],
+			);
+		}
+
+		Messages { means, variances }
+	}
+
+	/// Log likelihood of the tips with the internal states integrated out.
+	/// It's negative infinity if `precision` isn't positive definite.
+	pub fn log_likelihood(
+		&self,
+		branches: &Branches,
+		precision: &[f64],
+	) -> f64 {
+		let Some(factor) = self.factor(precision) else {
+			return f64::NEG_INFINITY;
+		};
+
+		let messages = self.messages(branches);
+		let mut out = 0.0;
+		for pair in branches.children.chunks(2) {
+			let [left, right] = [pair[0], pair[1]];
+
+			// The difference of the children's estimates is independent
+			// of the estimate of the parent.
+			let variance = messages.variances[left]
+				+ branches.lengths[left] + messages
+				.variances[right] + branches.lengths
+				[right];
+			if variance <= 0.0 {
+				return f64::NEG_INFINITY;
+			}
+			let contrast: Vec<f64> = messages.means[left]
+				.iter()
+				.zip(&messages.means[right])
+				.map(|(l, r)| l - r)
+				.collect();
+
+			out += self.log_normal(&contrast, variance, &factor);
+		}
+
+		if let Some((mean, sample_size)) = &self.root_prior {
+			let root = *branches.nodes.last().unwrap();
+			let difference: Vec<f64> = messages.means[root]
+				.iter()
+				.zip(mean)
+				.map(|(m, prior)| m - prior)
+				.collect();
+			let variance =
+				messages.variances[root] + 1.0 / sample_size;
+			out += self.log_normal(&difference, variance, &factor);
+		}
+
+		out
+	}
+
+	/// Jointly samples the traits of all nodes given the tips, indexed by
+	/// node.  The tips are returned as they are.
+	pub fn sample(
+		&self,
+		branches: &Branches,
+		precision: &[f64],
+		rng: &mut Rng,
+	) -> Result<Vec<Vec<f64>>> {
+		let n = self.dimensions();
+		let Some(factor) = self.factor(precision) else {
+			bail!("The precision matrix must be positive definite");
+		};
+
+		// Draws `mean + sqrt(variance) * x`, where `x ~ N(0, Sigma)` solves
+		// `L^T x = z` for a standard normal `z`.
+		let mut draw = |mean: &[f64], variance: f64| -> Vec<f64> {
+			let z: Vec<f64> = (0..n)
+				.map(|_| StandardNormal.sample(&mut *rng))
+				.collect();
+			let mut x = vec![0.0; n];
+			for i in (0..n).rev() {
+				let mut sum = z[i];
+				for k in (i + 1)..n {
+					sum -= factor[(k, i)] * x[k];
+				}
+				x[i] = sum / factor[(i, i)];
+			}
+
+			let scale = variance.sqrt();
+			mean.iter().zip(x).map(|(m, x)| m + scale * x).collect()
+		};
+
+		let messages = self.messages(branches);
+		let mut out = messages.means.clone();
+
+		let root = *branches.nodes.last().unwrap();
+		let (mean, variance) = match &self.root_prior {
+			Some((prior, sample_size)) => combine(
+				&messages.means[root],
+				messages.variances[root],
+				prior,
+				1.0 / sample_size,
+			),
+			None => (
+				messages.means[root].clone(),
+				messages.variances[root],
+			),
+		};
+		out[root] = draw(&mean, variance);
+
+		for (i, &node) in branches.nodes.iter().enumerate().rev() {
+			for side in 0..2 {
+				let child = branches.children[i * 2 + side];
+				if child < self.num_leaves() {
+					continue;
+				}
+
+				let (mean, variance) = combine(
+					&out[node],
+					branches.lengths[child],
+					&messages.means[child],
+					messages.variances[child],
+				);
+				out[child] = draw(&mean, variance);
+			}
+		}
+
+		Ok(out)
+	}
+}
+
+/// Values of a real parameter.
+fn real_values(param: &PyParameter) -> Result<Vec<f64>> {
+	match &*param.inner() {
+		Parameter::Real(values) => Ok(values.clone()),
+		_ => bail!("Expected a real parameter"),
+	}
+}
+
+pub struct ContinuousLikelihood {
+	diffusion: Diffusion,
+	tree: Py<PyTree>,
+	/// Precision matrix of the diffusion in row-major order.
+	precision: PyParameter,
+	/// Relaxed random walk scalars, one per edge.
+	rates: Option<PyParameter>,
+
+	/// Last accepted likelihood
+	pub(super) cache: f64,
+	/// Last calculated likelihood
+	last: f64,
+}
+
+impl ContinuousLikelihood {
+	pub fn new(
+		diffusion: Diffusion,
+		tree: Py<PyTree>,
+		precision: PyParameter,
+		rates: Option<PyParameter>,
+	) -> Result<Self> {
+		let n = diffusion.dimensions();
+		if real_values(&precision)?.len() != n * n {
+			bail!(
+				"The precision matrix must have {} dimensions",
+				n * n
+			);
+		}
+
+		let tree_ref = tree.get().inner();
+		if tree_ref.num_leaves() != diffusion.num_leaves() {
+			bail!(
+				"The tree has {} leaves, but got {} tips",
+				tree_ref.num_leaves(),
+				diffusion.num_leaves(),
+			);
+		}
+		if let Some(rates) = &rates {
+			let num_edges = tree_ref.num_internals() * 2;
+			if real_values(rates)?.len() != num_edges {
+				bail!("Expected a rate for each of the {num_edges} edges");
+			}
+		}
+		drop(tree_ref);
+
+		let mut out = Self {
+			diffusion,
+			tree,
+			precision,
+			rates,
+			cache: f64::NAN,
+			last: f64::NAN,
+		};
+		out.last = out.calculate()?;
+		out.accept()?;
+		Ok(out)
+	}
+
+	fn branches(&self) -> Result<Branches> {
+		let rates = self.rates.as_ref().map(real_values).transpose()?;
+
+		let tree = self.tree.get().inner();
+		let (nodes, edges, children) =
+			tree.to_lists(&tree.full_update());
+		let mut lengths = vec![0.0; tree.num_nodes()];
+		for (edge, child) in edges.into_iter().zip(&children) {
+			let rate = rates.as_ref().map_or(1.0, |r| r[edge]);
+			lengths[*child] = tree.edge_distance(edge) * rate;
+		}
+
+		Ok(Branches {
+			nodes,
+			children,
+			lengths,
+		})
+	}
+
+	fn calculate(&self) -> Result<f64> {
+		let branches = self.branches()?;
+		let precision = real_values(&self.precision)?;
+		Ok(self.diffusion.log_likelihood(&branches, &precision))
+	}
+
+	#[instrument(skip_all)]
+	pub fn propose(&mut self, _py: Python) -> Result<f64> {
+		let is_dirty = self.tree.get().inner().is_dirty()
+			|| self.precision.is_dirty()
+			|| self.rates.as_ref().is_some_and(|r| r.is_dirty());
+		if !is_dirty {
+			self.last = self.cache;
+			return Ok(self.cache);
+		}
+
+		let likelihood = self.calculate()?;
+		trace!(likelihood);
+		self.last = likelihood;
+		Ok(likelihood)
+	}
+
+	pub fn accept(&mut self) -> Result<()> {
+		self.cache = self.last;
+		Ok(())
+	}
+
+	pub fn reject(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	/// Jointly samples the traits of every node in the accepted state.
+	pub fn positions(&self, rng: &mut Rng) -> Result<Vec<Vec<f64>>> {
+		let branches = self.branches()?;
+		let precision = real_values(&self.precision)?;
+		self.diffusion.sample(&branches, &precision, rng)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::with_python;
+	use rand::SeedableRng;
+
+	/// A non-diagonal precision matrix.
+	const PRECISION: [f64; 4] = [1.0, 0.3, 0.3, 2.0];
+
+	/// Log density of the contrast `x` under a normal with the covariance
+	/// `variance * P^-1` for the 2 by 2 precision `P`.
+	fn log_contrast(x: [f64; 2], variance: f64, p: [f64; 4]) -> f64 {
+		let det = p[0] * p[3] - p[1] * p[2];
+		let quadratic = p[0] * x[0] * x[0]
+			+ (p[1] + p[2]) * x[0] * x[1]
+			+ p[3] * x[1] * x[1];
+		-0.5 * (2.0 * (2.0 * PI * variance).ln() - det.ln()
+			+ quadratic / variance)
+	}
+
+	/// The tree `((0, 1)3, 2)4` with two-dimensional tips.
+	fn diffusion(
+		root_prior: Option<(Vec<f64>, f64)>,
+	) -> (Diffusion, Branches) {
+		let tips =
+			vec![vec![0.0, 1.0], vec![0.5, 0.5], vec![2.0, -1.0]];
+		let branches = Branches {
+			nodes: vec![3, 4],
+			children: vec![0, 1, 3, 2],
+			lengths: vec![0.3, 0.2, 0.9, 0.6, 0.0],
+		};
+		(Diffusion::new(tips, root_prior).unwrap(), branches)
+	}
+
+	/// Log density of a multivariate normal with the covariance `variance *
+	/// Sigma` for a diagonal `Sigma`.
+	fn log_normal(
+		x: &[f64],
+		mean: &[f64],
+		variance: f64,
+		sigma: &[f64],
+	) -> f64 {
+		x.iter().zip(mean)
+			.zip(sigma)
+			.map(|((x, m), s)| {
+				let v = variance * s;
+				-0.5 * ((2.0 * PI * v).ln()
+					+ (x - m).powi(2) / v)
+			})
+			.sum()
+	}
+
+	#[test]
+	fn matches_root_prior_integral() {
+		// With a diagonal covariance the dimensions are independent, and
+		// with a root prior the tips are jointly normal.  The density of
+		// the tips factors into the density of leaf 2 and of leaves 0 and 1
+		// given leaf 2, which can be calculated directly.
+		let sigma = [0.5, 2.0];
+		let precision = [2.0, 0.0, 0.0, 0.5];
+		let prior = (vec![1.0, 0.0], 4.0);
+		let (diffusion, branches) = diffusion(Some(prior.clone()));
+		let tips = &diffusion.tips;
+
+		let root_var = 1.0 / prior.1;
+		// Variances from the root to each leaf, and their shared parts
+		let v2 = root_var + 0.9;
+		let v0 = root_var + 0.6 + 0.3;
+		let v1 = root_var + 0.6 + 0.2;
+		let shared = root_var + 0.6;
+
+		let mut expected = log_normal(&tips[2], &prior.0, v2, &sigma);
+		for d in 0..2 {
+			// Leaves 0 and 1 given leaf 2, which shares `root_var`
+			let k = root_var / v2;
+			let mean = prior.0[d] + k * (tips[2][d] - prior.0[d]);
+			let c00 = v0 - k * root_var;
+			let c11 = v1 - k * root_var;
+			let c01 = shared - k * root_var;
+
+			let det = (c00 * c11 - c01 * c01) * sigma[d].powi(2);
+			let [a, b] = [tips[0][d] - mean, tips[1][d] - mean];
+			let quadratic = (c11 * a * a - 2.0 * c01 * a * b
+				+ c00 * b * b) / (c00 * c11
+				- c01 * c01) / sigma[d];
+			expected += -0.5
+				* (2.0 * (2.0 * PI).ln()
+					+ det.ln() + quadratic);
+		}
+
+		let actual = diffusion.log_likelihood(&branches, &precision);
+		assert!((actual - expected).abs() < 1e-9);
+	}
+
+	#[test]
+	fn rejects_indefinite_precision() {
+		let (diffusion, branches) = diffusion(None);
+		let precision = [1.0, 2.0, 2.0, 1.0];
+		assert_eq!(
+			diffusion.log_likelihood(&branches, &precision),
+			f64::NEG_INFINITY
+		);
+	}
+
+	#[test]
+	fn samples_keep_tips() {
+		let (diffusion, branches) = diffusion(None);
+		let mut rng = Rng::seed_from_u64(4);
+
+		let samples = diffusion
+			.sample(&branches, &PRECISION, &mut rng)
+			.unwrap();
+		assert_eq!(samples.len(), 5);
+		assert_eq!(&samples[..3], &diffusion.tips[..]);
+		assert!(samples[3..].iter().all(|s| s.len() == 2));
+	}
+
+	#[test]
+	fn cherry_flat_prior() {
+		// With a flat prior on the root only the contrast between the two
+		// tips is informative.
+		let tips = vec![vec![0.3, -1.0], vec![1.5, 0.5]];
+		let branches = Branches {
+			nodes: vec![2],
+			children: vec![0, 1],
+			lengths: vec![0.4, 1.1, 0.0],
+		};
+		let diffusion = Diffusion::new(tips, None).unwrap();
+
+		let expected = log_contrast([-1.2, -1.5], 1.5, PRECISION);
+		let actual = diffusion.log_likelihood(&branches, &PRECISION);
+		assert!((actual - expected).abs() < 1e-12);
+	}
+
+	#[test]
+	fn rates_scale_branches() {
+		with_python(|env| {
+			let tree = env.tree(2, 1);
+			let parameter = |values: &[f64]| -> PyParameter {
+				env.b3.getattr("Parameter")
+					.unwrap()
+					.call_method1(
+						"Real",
+						pyo3::types::PyTuple::new(
+							env.py, values,
+						)
+						.unwrap(),
+					)
+					.unwrap()
+					.extract()
+					.unwrap()
+			};
+			let rates = [0.5, 3.0];
+
+			let diffusion = Diffusion::new(
+				vec![vec![0.3, -1.0], vec![1.5, 0.5]],
+				None,
+			)
+			.unwrap();
+			let likelihood = ContinuousLikelihood::new(
+				diffusion,
+				tree.clone_ref(env.py),
+				parameter(&PRECISION),
+				Some(parameter(&rates)),
+			)
+			.unwrap();
+
+			// Each edge is stretched by its own rate
+			let variance: f64 = {
+				let tree = tree.get().inner();
+				(0..2).map(|e| rates[e] * tree.edge_distance(e))
+					.sum()
+			};
+			let expected =
+				log_contrast([-1.2, -1.5], variance, PRECISION);
+			assert!((likelihood.cache - expected).abs() < 1e-12);
+		})
+	}
+
+	#[test]
+	fn samples_match_conditional() {
+		let (diffusion, branches) = diffusion(None);
+		let tips = &diffusion.tips;
+		let [l0, l1, l2, l3] = [0.3, 0.2, 0.9, 0.6];
+
+		// With a flat root prior the internal nodes 3 and 4 given the tips
+		// are normal with the precision `A` (in units of `P`) and the mean
+		// `A^-1 b`, which follow from the branch terms of the density.
+		let a = [
+			1.0 / l0 + 1.0 / l1 + 1.0 / l3,
+			-1.0 / l3,
+			1.0 / l3 + 1.0 / l2,
+		];
+		let det = a[0] * a[2] - a[1] * a[1];
+		let cov = [a[2] / det, -a[1] / det, a[0] / det];
+		let sigma = {
+			let p = PRECISION;
+			let det = p[0] * p[3] - p[1] * p[2];
+			[p[3] / det, -p[1] / det, p[0] / det]
+		};
+
+		let mut rng = Rng::seed_from_u64(4);
+		let num_samples = 100_000;
+		let mut sums = [[0.0; 2]; 2];
+		let mut squares = [[0.0; 3]; 2];
+		for _ in 0..num_samples {
+			let samples = diffusion
+				.sample(&branches, &PRECISION, &mut rng)
+				.unwrap();
+			assert_eq!(&samples[..3], &tips[..]);
+			for (node, sample) in samples[3..].iter().enumerate() {
+				sums[node][0] += sample[0];
+				sums[node][1] += sample[1];
+				squares[node][0] += sample[0] * sample[0];
+				squares[node][1] += sample[0] * sample[1];
+				squares[node][2] += sample[1] * sample[1];
+			}
+		}
+
+		let n = num_samples as f64;
+		for d in 0..2 {
+			let b = [
+				tips[0][d] / l0 + tips[1][d] / l1,
+				tips[2][d] / l2,
+			];
+			let means = [
+				cov[0] * b[0] + cov[1] * b[1],
+				cov[1] * b[0] + cov[2] * b[1],
+			];
+			for node in 0..2 {
+				let actual = sums[node][d] / n;
+				assert!((actual - means[node]).abs() < 0.01);
+			}
+		}
+		for node in 0..2 {
+			let variance = cov[node * 2];
+			let mean = sums[node].map(|s| s / n);
+			let actual = [
+				squares[node][0] / n - mean[0] * mean[0],
+				squares[node][1] / n - mean[0] * mean[1],
+				squares[node][2] / n - mean[1] * mean[1],
+			];
+			for (actual, sigma) in actual.iter().zip(sigma) {
+				let expected = variance * sigma;
+				assert!((actual - expected).abs() < 0.01);
+			}
+		}
+	}
+}
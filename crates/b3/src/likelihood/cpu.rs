@@ -5,6 +5,8 @@ use skvec::SkVec;
 
 pub struct CpuLikelihood<const N: usize> {
 	probabilities: Vec<SkVec<Row<N>>>,
+	/// Number of sites with each pattern.
+	weights: Vec<f64>,
 
 	updated_nodes: Vec<usize>,
 }
@@ -35,7 +37,8 @@ impl<const N: usize> LikelihoodTrait<N> for CpuLikelihood<N> {
 		let out = self
 			.probabilities
 			.iter()
-			.map(|p| p[root].sum().ln())
+			.zip(&self.weights)
+			.map(|(p, weight)| weight * p[root].sum().ln())
 			.sum();
 		Ok(out)
 	}
@@ -60,10 +63,23 @@ impl<const N: usize> LikelihoodTrait<N> for CpuLikelihood<N> {
 
 		Ok(())
 	}
+
+	fn likelihoods(&self, root: usize) -> Result<Vec<f64>> {
+		Ok(self.probabilities
+			.iter()
+			.map(|p| p[root].sum().ln())
+			.collect())
+	}
+
+	fn partials(&self, node: usize) -> Result<Vec<Row<N>>> {
+		Ok(self.probabilities.iter().map(|p| p[node]).collect())
+	}
 }
 
 impl<const N: usize> CpuLikelihood<N> {
-	pub fn new(sites: Vec<Vec<Row<N>>>) -> Self {
+	pub fn new(sites: Vec<Vec<Row<N>>>, weights: Vec<f64>) -> Self {
+		assert_eq!(sites.len(), weights.len());
+
 		let mut probabilities = vec![
 			SkVec::repeat(
 				Row::<N>::default(),
@@ -88,7 +104,75 @@ impl<const N: usize> CpuLikelihood<N> {
 
 		Self {
 			probabilities,
+			weights,
 			updated_nodes: Vec::new(),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{likelihood::fixtures::jc, util::tip_partial};
+	use data::DnaNucleotide::{self, *};
+
+	/// Likelihood of a single site on the tree `((0, 1)3, 2)4`.
+	fn likelihood(site: [DnaNucleotide; 3]) -> f64 {
+		let rows = site.iter().map(|b| tip_partial(*b)).collect();
+		let mut calculator = CpuLikelihood::new(vec![rows], vec![1.0]);
+
+		let transitions = [jc(0.1), jc(0.2), jc(0.15), jc(0.4)];
+		calculator
+			.propose(&[3, 4], &transitions, &[0, 1, 3, 2])
+			.unwrap()
+	}
+
+	#[test]
+	fn ambiguity_sums_states() {
+		let purine = likelihood([Purine, Cytosine, Thymine]).exp();
+		let adenine = likelihood([Adenine, Cytosine, Thymine]).exp();
+		let guanine = likelihood([Guanine, Cytosine, Thymine]).exp();
+
+		assert!((purine - (adenine + guanine)).abs() < 1e-12);
+	}
+
+	#[test]
+	fn gaps_are_missing() {
+		let gap = likelihood([Gap, Cytosine, Thymine]);
+		let any = likelihood([Any, Cytosine, Thymine]);
+
+		let mut sum = 0.0;
+		for base in [Adenine, Cytosine, Guanine, Thymine] {
+			sum += likelihood([base, Cytosine, Thymine]).exp();
+		}
+
+		assert_eq!(gap, any);
+		assert!((any.exp() - sum).abs() < 1e-12);
+	}
+
+	#[test]
+	fn weighted_patterns() {
+		let rows = |site: [DnaNucleotide; 3]| {
+			site.iter().map(|b| tip_partial(*b)).collect()
+		};
+		let sites = vec![
+			rows([Adenine, Adenine, Guanine]),
+			rows([Cytosine, Thymine, Thymine]),
+		];
+		let mut calculator = CpuLikelihood::new(sites, vec![3.0, 1.0]);
+
+		let transitions = [jc(0.1), jc(0.2), jc(0.15), jc(0.4)];
+		let total = calculator
+			.propose(&[3, 4], &transitions, &[0, 1, 3, 2])
+			.unwrap();
+		calculator.accept().unwrap();
+
+		let patterns = calculator.likelihoods(4).unwrap();
+		assert_eq!(patterns.len(), 2);
+		assert!((patterns[0]
+			- likelihood([Adenine, Adenine, Guanine]))
+		.abs() < 1e-12);
+		assert!((total - (3.0 * patterns[0] + patterns[1])).abs()
+			< 1e-12);
+	}
+}
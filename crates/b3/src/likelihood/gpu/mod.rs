@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use vulkano::{
 	buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
 	command_buffer::{
@@ -64,6 +64,15 @@ pub struct GpuLikelihood {
 	/// which nodes were updated in the on-GPU buffer.  As such, it acts as
 	/// the `edited` field in `SkVec`.
 	updated_nodes_cache: Vec<usize>,
+
+	/// Number of sites with each pattern.
+	weights: Vec<f64>,
+	/// Per-pattern log likelihoods of the last proposal.
+	likelihoods: Vec<f64>,
+	/// Per-pattern log likelihoods of the accepted state.  The partials
+	/// live on the GPU, so unlike in the CPU likelihood they are kept
+	/// around instead of being recalculated.
+	accepted_likelihoods: Vec<f64>,
 }
 
 mod propose {
@@ -130,10 +139,26 @@ impl LikelihoodTrait<4> for GpuLikelihood {
 		future.wait(None)?;
 
 		let output = self.propose_likelihoods.read()?;
-		Ok(output.iter().map(|v| v.ln()).sum())
+		self.likelihoods.clear();
+		self.likelihoods.extend(output.iter().map(|v| v.ln()));
+
+		let likelihood = self
+			.likelihoods
+			.iter()
+			.zip(&self.weights)
+			.map(|(likelihood, weight)| likelihood * weight)
+			.sum();
+		Ok(likelihood)
 	}
 
 	fn accept(&mut self) -> Result<()> {
+		if !self.updated_nodes_cache.is_empty() {
+			std::mem::swap(
+				&mut self.likelihoods,
+				&mut self.accepted_likelihoods,
+			);
+		}
+
 		// `propose` changes the state to how it should be after the
 		// update, so this is all what's needed to accept.
 		self.updated_nodes_cache.clear();
@@ -161,10 +186,18 @@ impl LikelihoodTrait<4> for GpuLikelihood {
 
 		Ok(())
 	}
+
+	fn likelihoods(&self, _root: usize) -> Result<Vec<f64>> {
+		Ok(self.accepted_likelihoods.clone())
+	}
+
+	fn partials(&self, _node: usize) -> Result<Vec<Row<4>>> {
+		bail!("The GPU likelihood doesn't support reading partials")
+	}
 }
 
 impl GpuLikelihood {
-	pub fn new(sites: Vec<Vec<Row<4>>>) -> Result<Self> {
+	pub fn new(sites: Vec<Vec<Row<4>>>, weights: Vec<f64>) -> Result<Self> {
 		let num_sites = sites.len();
 		let num_leaves = sites[0].len();
 
@@ -619,6 +652,10 @@ impl GpuLikelihood {
 			propose_likelihoods,
 
 			updated_nodes_cache: Vec::new(),
+
+			weights,
+			likelihoods: Vec::new(),
+			accepted_likelihoods: Vec::new(),
 		})
 	}
 }
@@ -0,0 +1,442 @@
+//! Stochastic character mapping and Markov jump counts.
+//!
+//! Substitution histories are sampled along the branches with uniformization
+//! (Rodrigue et al., 2008): the process is turned into a Poisson process with
+//! the rate `mu`, the largest exit rate of the substitution matrix, whose jumps
+//! follow the discrete chain `R = I + Q / mu`.  Some of the jumps don't change
+//! the state, and are dropped from the histories.
+//!
+//! Jumps are counted with a *register*, a matrix of weights for each pair of
+//! states.  For example, a register with ones for purine-pyrimidine pairs
+//! counts transversions.  The diagonal of the register is ignored.
+use rand::Rng as _;
+
+use super::{ancestral::Reconstruction, Row, Transition};
+use rng::Rng;
+
+/// Cumulative Poisson probability after which the uniformization sums are
+/// truncated.
+const TOLERANCE: f64 = 1e-12;
+/// Limit on the number of uniformized jumps on a single branch.  Sampling a
+/// history keeps a column of `N` values for each jump.
+const MAX_JUMPS: usize = 10_000;
+
+struct Uniformized<const N: usize> {
+	/// Rate of the Poisson process.
+	rate: f64,
+	/// Transition matrix of the jumps.
+	jump: Transition<N>,
+}
+
+impl<const N: usize> Uniformized<N> {
+	fn new(substitution: &Transition<N>) -> Self {
+		let rate = (0..N)
+			.map(|i| -substitution[(i, i)])
+			.fold(0.0, f64::max);
+
+		let mut jump = Transition::<N>::identity();
+		if rate > 0.0 {
+			for i in 0..N {
+				for j in 0..N {
+					jump[(i, j)] +=
+						substitution[(i, j)] / rate;
+				}
+			}
+		}
+
+		Self { rate, jump }
+	}
+}
+
+/// Substitution history along a single branch: the times at which the state
+/// changed and the new states.
+pub type History = Vec<(f64, usize)>;
+
+/// Samples a substitution history of the length `distance` which starts at
+/// `start` and ends at `end`.  `transition` must be the transition matrix for
+/// `distance`.
+pub fn sample_history<const N: usize>(
+	substitution: &Transition<N>,
+	transition: &Transition<N>,
+	distance: f64,
+	start: usize,
+	end: usize,
+	rng: &mut Rng,
+) -> History {
+	let uniformized = Uniformized::new(substitution);
+	let mean = uniformized.rate * distance;
+
+	// Only the column `end` of the powers of the jump matrix is needed,
+	// so `columns[k]` holds that column of `R^k`.  They're kept around to
+	// sample the states after the number of jumps is known.
+	let mut end_column = Row::<N>::default();
+	end_column[end] = 1.0;
+	let mut columns = vec![end_column];
+
+	// Number of jumps, conditioned on the end points
+	let target = rng.random::<f64>() * transition[(start, end)];
+	let mut poisson = (-mean).exp();
+	let mut cumulative = 0.0;
+	let mut num_jumps = 0;
+	loop {
+		cumulative += poisson * columns[num_jumps][start];
+		if target < cumulative || num_jumps == MAX_JUMPS {
+			break;
+		}
+
+		num_jumps += 1;
+		poisson *= mean / num_jumps as f64;
+		columns.push(uniformized.jump * columns[num_jumps - 1]);
+	}
+
+	let mut times: Vec<f64> = (0..num_jumps)
+		.map(|_| rng.random::<f64>() * distance)
+		.collect();
+	times.sort_by(f64::total_cmp);
+
+	// States after each jump, conditioned on reaching `end`
+	let mut out = Vec::new();
+	let mut state = start;
+	for (i, time) in times.into_iter().enumerate() {
+		let remaining = &columns[num_jumps - i - 1];
+		let mut weights = [0.0; N];
+		for (next, weight) in weights.iter_mut().enumerate() {
+			*weight = uniformized.jump[(state, next)]
+				* remaining[next];
+		}
+
+		let mut target =
+			rng.random::<f64>() * weights.iter().sum::<f64>();
+		let mut next = state;
+		for (candidate, weight) in weights.iter().enumerate() {
+			if *weight > 0.0 {
+				next = candidate;
+			}
+			target -= weight;
+			if target < 0.0 {
+				break;
+			}
+		}
+
+		if next != state {
+			out.push((time, next));
+			state = next;
+		}
+	}
+
+	out
+}
+
+/// Weighted number of jumps in `history`, which starts at `start`.
+pub fn count_jumps<const N: usize>(
+	history: &History,
+	start: usize,
+	register: &Transition<N>,
+) -> f64 {
+	let mut state = start;
+	let mut out = 0.0;
+	for &(_, next) in history {
+		out += register[(state, next)];
+		state = next;
+	}
+	out
+}
+
+/// Expected register count over a branch of the length `distance`, jointly
+/// with the end state.
+///
+/// The element `(a, b)` is `E[count * 1(end = b) | start = a]`, so dividing
+/// it by the transition probability from `a` to `b` gives the expected count
+/// conditioned on both ends.
+fn expected_jumps<const N: usize>(
+	substitution: &Transition<N>,
+	distance: f64,
+	register: &Transition<N>,
+) -> Transition<N> {
+	let uniformized = Uniformized::new(substitution);
+	let mean = uniformized.rate * distance;
+
+	// Jump matrix restricted to the registered jumps
+	let mut registered = uniformized.jump;
+	for i in 0..N {
+		for j in 0..N {
+			registered[(i, j)] *=
+				if i == j { 0.0 } else { register[(i, j)] };
+		}
+	}
+
+	// `sums` is the sum of `R^k R_L R^(n - k - 1)` over `k` for `n`
+	// jumps, where `R_L` is the registered part of `R`.
+	let mut power = Transition::<N>::identity();
+	let mut sums = Transition::<N>::from_element(0.0);
+	let mut poisson = (-mean).exp();
+	let mut cumulative = poisson;
+	let mut out = Transition::<N>::from_element(0.0);
+	for n in 1..=MAX_JUMPS {
+		sums = sums * uniformized.jump + power * registered;
+		power = power * uniformized.jump;
+
+		poisson *= mean / n as f64;
+		cumulative += poisson;
+		out += sums * poisson;
+
+		if cumulative > 1.0 - TOLERANCE {
+			break;
+		}
+	}
+
+	out
+}
+
+/// Jump counts on every branch, indexed like `reconstruction.children`.
+pub struct Jumps {
+	/// Counts from one sampled history of each site.
+	pub realized: Vec<f64>,
+	/// Counts expected under the posterior distribution of the histories.
+	pub expected: Vec<f64>,
+}
+
+/// Counts the registered jumps on every branch, summed over all sites.
+///
+/// `sites` maps the alignment sites to patterns, and `weights` has the number
+/// of sites of each pattern.
+pub fn jumps<const N: usize>(
+	reconstruction: &Reconstruction<N>,
+	substitution: &Transition<N>,
+	register: &Transition<N>,
+	sites: &[usize],
+	weights: &[f64],
+	rng: &mut Rng,
+) -> Jumps {
+	let states = reconstruction.sample(sites, rng);
+	let (_, outside) = reconstruction.pre_order();
+
+	let num_edges = reconstruction.children.len();
+	let mut realized = vec![0.0; num_edges];
+	let mut expected = vec![0.0; num_edges];
+
+	for (i, &node) in reconstruction.nodes.iter().enumerate() {
+		for side in 0..2 {
+			let edge = i * 2 + side;
+			let child = reconstruction.children[edge];
+			let transition = &reconstruction.transitions[edge];
+			let distance = reconstruction.distances[edge];
+
+			let ends = states[node].iter().zip(&states[child]);
+			for (&start, &end) in ends {
+				let history = sample_history(
+					substitution,
+					transition,
+					distance,
+					start,
+					end,
+					rng,
+				);
+				realized[edge] +=
+					count_jumps(&history, start, register);
+			}
+
+			let joint = expected_jumps(
+				substitution,
+				distance,
+				register,
+			);
+			for (pattern, weight) in weights.iter().enumerate() {
+				let above = outside[edge][pattern];
+				let below =
+					reconstruction.partials[child][pattern];
+
+				let mut numerator = 0.0;
+				let mut denominator = 0.0;
+				for a in 0..N {
+					for b in 0..N {
+						let ends = above[a] * below[b];
+						numerator +=
+							ends * joint[(a, b)];
+						denominator += ends
+							* transition[(a, b)];
+					}
+				}
+				expected[edge] +=
+					weight * numerator / denominator;
+			}
+		}
+	}
+
+	Jumps { realized, expected }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::likelihood::fixtures::{
+		asymmetric_generator, jc_generator, reconstruction, MODELS,
+	};
+	use rand::SeedableRng;
+
+	/// Rate matrices of [`MODELS`].
+	const GENERATORS: [fn() -> Transition<4>; 2] =
+		[jc_generator, asymmetric_generator];
+
+	fn all_jumps() -> Transition<4> {
+		Transition::<4>::from_element(1.0)
+	}
+
+	/// Only counts the jumps from 0 to 1, but not back.
+	fn zero_to_one() -> Transition<4> {
+		let mut out = Transition::<4>::from_element(0.0);
+		out[(0, 1)] = 1.0;
+		out
+	}
+
+	/// The integral of `P(s) Q_L P(t - s)` over `s` from 0 to `t`, where
+	/// `Q_L` is the registered part of `substitution`, by Simpson's rule.
+	fn integral(
+		substitution: &Transition<4>,
+		t: f64,
+		register: &Transition<4>,
+	) -> Transition<4> {
+		let mut registered = *substitution;
+		for i in 0..4 {
+			for j in 0..4 {
+				registered[(i, j)] *= if i == j {
+					0.0
+				} else {
+					register[(i, j)]
+				};
+			}
+		}
+
+		let steps = 200;
+		let h = t / steps as f64;
+		let mut out = Transition::<4>::from_element(0.0);
+		for k in 0..=steps {
+			let s = k as f64 * h;
+			let weight = match k {
+				0 => 1.0,
+				_ if k == steps => 1.0,
+				_ if k % 2 == 1 => 4.0,
+				_ => 2.0,
+			};
+			out += (*substitution * s).expm()
+				* registered * (*substitution * (t - s)).expm()
+				* (weight * h / 3.0);
+		}
+		out
+	}
+
+	#[test]
+	fn expected_unconditional() {
+		// Without conditioning on the end, the expected number of
+		// substitutions is the rate times the distance.
+		let expected =
+			expected_jumps(&jc_generator(), 0.7, &all_jumps());
+		for a in 0..4 {
+			let total: f64 = (0..4).map(|b| expected[(a, b)]).sum();
+			assert!((total - 0.7).abs() < 1e-9);
+		}
+	}
+
+	#[test]
+	fn expected_matches_integral() {
+		for generator in GENERATORS {
+			for register in [all_jumps(), zero_to_one()] {
+				let actual = expected_jumps(
+					&generator(),
+					0.7,
+					&register,
+				);
+				let expected =
+					integral(&generator(), 0.7, &register);
+				for a in 0..4 {
+					for b in 0..4 {
+						assert!((actual[(a, b)]
+							- expected[(a, b)])
+							.abs() < 1e-9);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn histories_match_expectation() {
+		let distance = 0.5;
+		let mut rng = Rng::seed_from_u64(4);
+
+		for (generator, model) in GENERATORS.into_iter().zip(MODELS) {
+			let substitution = generator();
+			let transition = model(distance);
+
+			for (start, end) in [(0, 0), (0, 2), (2, 0), (1, 2)] {
+				let registers = [all_jumps(), zero_to_one()];
+				let num_samples = 100_000;
+				let mut totals = [0.0; 2];
+				for _ in 0..num_samples {
+					let history = sample_history(
+						&substitution,
+						&transition,
+						distance,
+						start,
+						end,
+						&mut rng,
+					);
+					assert_eq!(
+						history.last()
+							.map_or(start, |h| h.1),
+						end
+					);
+					for (total, register) in totals
+						.iter_mut()
+						.zip(&registers)
+					{
+						*total += count_jumps(
+							&history, start,
+							register,
+						);
+					}
+				}
+
+				for (total, register) in
+					totals.iter().zip(&registers)
+				{
+					let mean = total / num_samples as f64;
+					let expected = expected_jumps(
+						&substitution,
+						distance,
+						register,
+					);
+					let conditional = expected
+						[(start, end)]
+						/ transition[(start, end)];
+					assert!((mean - conditional).abs()
+						< 0.01);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn mapped_jumps_match_expectation() {
+		let mut rng = Rng::seed_from_u64(4);
+		let num_sites = 20_000;
+
+		for (generator, model) in GENERATORS.into_iter().zip(MODELS) {
+			let counts = jumps(
+				&reconstruction(model),
+				&generator(),
+				&all_jumps(),
+				&vec![0; num_sites],
+				&[num_sites as f64],
+				&mut rng,
+			);
+
+			for (realized, expected) in
+				counts.realized.iter().zip(&counts.expected)
+			{
+				let difference = (realized - expected).abs();
+				assert!(difference / (num_sites as f64) < 0.01);
+			}
+		}
+	}
+}
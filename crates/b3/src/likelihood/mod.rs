@@ -1,28 +1,44 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use parking_lot::{Mutex, MutexGuard};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use tracing::{info, instrument, trace};
+use tracing::{info, instrument, trace, warn};
 
 use crate::{
-	substitution::PySubstitution, tree::PyTree, util::dna_to_rows,
+	parameter::PyParameter,
+	substitution::PySubstitution,
+	tree::PyTree,
+	util::{dna_to_patterns, traits_to_patterns, Patterns},
 	Transitions,
 };
-use data::seq::{python::PyDnaSeq, DnaSeq};
+use ancestral::Reconstruction;
+use continuous::Diffusion;
+use data::{
+	seq::{python::PyDnaSeq, DnaSeq},
+	DnaNucleotide,
+};
 use linalg::{RowMatrix, Vector};
+use rng::{PyRng, Rng};
+use util::py_bail;
 
+pub mod ancestral;
+pub mod continuous;
 mod cpu;
 mod gpu;
-// mod thread;
-
-use cpu::CpuLikelihood;
-use gpu::GpuLikelihood;
+pub mod mapping;
+mod node_major;
+mod thread;
 
-// pub use thread::ThreadedLikelihood;
+pub use continuous::ContinuousLikelihood;
+pub use cpu::CpuLikelihood;
+pub use gpu::GpuLikelihood;
+pub use node_major::NodeMajorLikelihood;
+pub use thread::ThreadedLikelihood;
 
 pub type Row<const N: usize> = Vector<f64, N>;
-type Transition<const N: usize> = RowMatrix<f64, N, N>;
+pub type Transition<const N: usize> = RowMatrix<f64, N, N>;
 
-trait LikelihoodTrait<const N: usize> {
+pub trait LikelihoodTrait<const N: usize> {
 	fn propose(
 		&mut self,
 		nodes: &[usize],
@@ -33,11 +49,80 @@ trait LikelihoodTrait<const N: usize> {
 	fn accept(&mut self) -> Result<()>;
 
 	fn reject(&mut self) -> Result<()>;
+
+	/// Log likelihoods of every site pattern in the accepted state, with
+	/// the tree rooted at `root`.  They aren't weighted by the number of
+	/// sites with each pattern.
+	///
+	/// Must not be called while a proposal is pending.
+	fn likelihoods(&self, root: usize) -> Result<Vec<f64>>;
+
+	/// Partials of `node` for every site pattern in the accepted state.
+	///
+	/// Must not be called while a proposal is pending.
+	fn partials(&self, node: usize) -> Result<Vec<Row<N>>>;
 }
 
 type DynCalculator<const N: usize> =
 	Box<dyn LikelihoodTrait<N> + Send + Sync + 'static>;
 
+/// Memory layout and parallelism of the CPU calculators.
+#[derive(Debug, Clone, Copy)]
+enum CpuLayout {
+	/// Pattern-major partials, split between `threads` threads.
+	Pattern { threads: usize },
+	/// Node-major partials on a single thread.
+	Node,
+}
+
+impl CpuLayout {
+	/// Validates the `layout` and `threads` arguments of the Python
+	/// constructors.
+	fn new(layout: &str, threads: usize) -> Result<Self> {
+		if threads == 0 {
+			py_bail!(PyValueError, "`threads` must be at least 1");
+		}
+
+		Ok(match layout {
+			"pattern" => Self::Pattern { threads },
+			"node" if threads == 1 => Self::Node,
+			"node" => py_bail!(
+				PyValueError,
+				"The node-major layout only runs on one thread",
+			),
+			_ => py_bail!(
+				PyValueError,
+				"`layout` must be either 'pattern' or 'node', got '{layout}'",
+			),
+		})
+	}
+}
+
+/// Calculator for `layout`: a single-threaded one for one thread, and a
+/// threaded one otherwise.
+fn cpu_calculator<const N: usize>(
+	sites: Vec<Vec<Row<N>>>,
+	weights: Vec<f64>,
+	layout: CpuLayout,
+) -> Result<DynCalculator<N>> {
+	Ok(match layout {
+		CpuLayout::Pattern { threads: 1 } => {
+			info!("using CpuLikelihood");
+			Box::new(CpuLikelihood::new(sites, weights))
+		}
+		CpuLayout::Pattern { threads } => {
+			info!(threads, "using ThreadedLikelihood");
+			Box::new(ThreadedLikelihood::new(
+				sites, weights, threads,
+			)?)
+		}
+		CpuLayout::Node => {
+			info!("using NodeMajorLikelihood");
+			Box::new(NodeMajorLikelihood::new(sites, weights))
+		}
+	})
+}
+
 pub struct GenericLikelihood<const N: usize> {
 	substitution: PySubstitution<N>,
 	transitions: Transitions<N>,
@@ -48,34 +133,111 @@ pub struct GenericLikelihood<const N: usize> {
 	/// it might get rejected.
 	last: f64,
 	tree: Py<PyTree>,
+	/// Number of states of the data.  States from it up to `N` are padding
+	/// for discrete traits.
+	states: usize,
+
+	/// Number of sites with each pattern.
+	weights: Vec<f64>,
+	/// Index of the pattern of each site in the alignment.
+	sites: Vec<usize>,
+	/// Root of the tree in the accepted state.
+	root: usize,
+	/// Root of the tree in the last calculation.
+	last_root: usize,
+	/// Parameter versions the accepted transition matrices were calculated
+	/// at, see [`PySubstitution::versions`].
+	versions: Option<Vec<u64>>,
+	/// Parameter versions of the last calculation.
+	last_versions: Option<Vec<u64>>,
 }
 
 impl GenericLikelihood<4> {
 	#[instrument(skip_all, fields(use_gpu))]
 	fn new(
 		substitution: PySubstitution<4>,
-		sites: Vec<Vec<Vector<f64, 4>>>,
+		patterns: Patterns<4>,
 		tree: Py<PyTree>,
 		use_gpu: bool,
+		layout: CpuLayout,
 	) -> Result<Self> {
-		let num_internals = sites[0].len() - 1;
-		let transitions = Transitions::<4>::new(num_internals * 2);
+		let Patterns {
+			rows,
+			weights,
+			sites,
+		} = patterns;
+		let num_leaves = rows[0].len();
 
 		let calculator: DynCalculator<4> = if use_gpu {
 			info!("using GpuLikelihood");
-			Box::new(GpuLikelihood::new(sites)?)
+			Box::new(GpuLikelihood::new(rows, weights.clone())?)
 		} else {
-			info!("using CpuLikelihood");
-			Box::new(CpuLikelihood::new(sites))
+			cpu_calculator(rows, weights.clone(), layout)?
 		};
 
+		Self::with_calculator(
+			substitution,
+			calculator,
+			num_leaves,
+			weights,
+			sites,
+			tree,
+		)
+	}
+}
+
+impl<const N: usize> GenericLikelihood<N> {
+	/// Creates a likelihood calculated on the CPU.
+	#[instrument(skip_all)]
+	fn new_cpu(
+		substitution: PySubstitution<N>,
+		patterns: Patterns<N>,
+		tree: Py<PyTree>,
+		layout: CpuLayout,
+	) -> Result<Self> {
+		let Patterns {
+			rows,
+			weights,
+			sites,
+		} = patterns;
+		let num_leaves = rows[0].len();
+		let calculator = cpu_calculator(rows, weights.clone(), layout)?;
+
+		Self::with_calculator(
+			substitution,
+			calculator,
+			num_leaves,
+			weights,
+			sites,
+			tree,
+		)
+	}
+
+	fn with_calculator(
+		substitution: PySubstitution<N>,
+		calculator: DynCalculator<N>,
+		num_leaves: usize,
+		weights: Vec<f64>,
+		sites: Vec<usize>,
+		tree: Py<PyTree>,
+	) -> Result<Self> {
+		let num_internals = num_leaves - 1;
+		let transitions = Transitions::<N>::new(num_internals * 2);
+
 		let mut out = Self {
+			states: substitution.dimensions(),
 			substitution,
 			transitions,
 			calculator,
 			tree,
 			cache: f64::NAN,
 			last: f64::NAN,
+			weights,
+			sites,
+			root: 0,
+			last_root: 0,
+			versions: None,
+			last_versions: None,
 		};
 		Python::with_gil(|py| out.propose(py))?;
 		// propose sets `last` and accept updates the cache, so neither
@@ -83,15 +245,37 @@ impl GenericLikelihood<4> {
 		out.accept()?;
 		Ok(out)
 	}
-}
 
-impl<const N: usize> GenericLikelihood<N> {
 	#[instrument(skip_all)]
 	fn propose(&mut self, py: Python) -> Result<f64> {
-		let tree = &self.tree.get().inner();
-		let substitution_matrix = self.substitution.get_matrix(py)?;
-		let full_update =
-			self.transitions.update(substitution_matrix, tree);
+		let tree = self.tree.get().inner();
+		// The versions are compared with the accepted ones instead of
+		// checking whether the parameters are dirty, since they could
+		// have been accepted without this likelihood seeing them.  The
+		// cache is only NaN before the first proposal, when the
+		// transitions have no matrix to reuse.
+		let versions = self.substitution.versions();
+		let stale = versions.is_none() || versions != self.versions;
+		self.last_versions = versions;
+		let rates = if stale || self.cache.is_nan() {
+			Some(self.substitution.get_rates(py)?)
+		} else {
+			None
+		};
+		let full_update = match self.transitions.update(rates, &tree) {
+			Ok(full_update) => full_update,
+			// A broken rate matrix (e.g. from an extreme parameter
+			// value) makes the state impossible, so the proposal is
+			// rejected instead of crashing the whole run.
+			Err(error) => {
+				warn!(%error, "failed to calculate transition matrices");
+				// Nothing of the failed update may be accepted
+				self.transitions.reject();
+				self.last_versions.clone_from(&self.versions);
+				self.last = f64::NEG_INFINITY;
+				return Ok(self.last);
+			}
+		};
 		let nodes = if full_update {
 			tree.full_update()
 		} else {
@@ -106,14 +290,17 @@ impl<const N: usize> GenericLikelihood<N> {
 		}
 
 		let (nodes, edges, children) = tree.to_lists(&nodes);
+		drop(tree);
+		self.last_root = *nodes.last().unwrap();
 
 		let transitions = self.transitions.matrices(&edges);
 
-		let likelihood = self.calculator.propose(
-			&nodes,
-			&transitions,
-			&children,
-		)?;
+		// The calculation doesn't touch any Python objects, so the GIL
+		// is released to let other chains run in the meantime.
+		let calculator = &mut self.calculator;
+		let likelihood = py.allow_threads(|| {
+			calculator.propose(&nodes, &transitions, &children)
+		})?;
 		trace!(likelihood);
 		self.last = likelihood;
 		Ok(likelihood)
@@ -121,16 +308,122 @@ impl<const N: usize> GenericLikelihood<N> {
 
 	fn accept(&mut self) -> Result<()> {
 		self.cache = self.last;
+		self.root = self.last_root;
+		self.versions.clone_from(&self.last_versions);
 		self.calculator.accept()?;
 		self.transitions.accept();
 		Ok(())
 	}
 
 	fn reject(&mut self) -> Result<()> {
+		// Accepting without a new proposal keeps the accepted state.
+		self.last = self.cache;
+		self.last_versions.clone_from(&self.versions);
 		self.calculator.reject()?;
-		self.transitions.accept();
+		self.transitions.reject();
 		Ok(())
 	}
+
+	/// Log likelihoods of the unique site patterns in the accepted state.
+	///
+	/// They are calculated on request, so there's no overhead unless
+	/// they are used.
+	fn pattern_likelihoods(&self) -> Result<Vec<f64>> {
+		self.calculator.likelihoods(self.root)
+	}
+
+	/// Log likelihoods of every site in the alignment in the accepted
+	/// state.
+	fn site_likelihoods(&self) -> Result<Vec<f64>> {
+		let patterns = self.pattern_likelihoods()?;
+		Ok(self.sites.iter().map(|p| patterns[*p]).collect())
+	}
+
+	fn reconstruction(&self) -> Result<Reconstruction<N>> {
+		let tree = self.tree.get().inner();
+		let (nodes, edges, children) =
+			tree.to_lists(&tree.full_update());
+		let num_nodes = tree.num_nodes();
+		let distances =
+			edges.iter().map(|e| tree.edge_distance(*e)).collect();
+		drop(tree);
+
+		let partials = (0..num_nodes)
+			.map(|node| self.calculator.partials(node))
+			.collect::<Result<_>>()?;
+
+		Ok(Reconstruction {
+			nodes,
+			children,
+			transitions: self.transitions.matrices(&edges),
+			distances,
+			partials,
+		})
+	}
+
+	/// Marginal state probabilities of every internal node at every site,
+	/// in the accepted state.
+	fn ancestral_marginal(&self) -> Result<Vec<Vec<Row<N>>>> {
+		let marginal = self.reconstruction()?.marginal();
+
+		Ok(marginal
+			.into_iter()
+			.map(|node| {
+				self.sites.iter().map(|p| node[*p]).collect()
+			})
+			.collect())
+	}
+
+	/// A joint sample of the states of every internal node at every site,
+	/// in the accepted state.
+	fn ancestral_sample(&self, rng: &mut Rng) -> Result<Vec<Vec<usize>>> {
+		let mut states =
+			self.reconstruction()?.sample(&self.sites, rng);
+		let num_leaves = states.len().div_ceil(2);
+		Ok(states.split_off(num_leaves))
+	}
+
+	/// Registered jump counts on the edge above every node, realized in a
+	/// sampled history and expected.  The root has no edge above it, so
+	/// its counts are 0.
+	fn markov_jumps(
+		&self,
+		register: &[Vec<f64>],
+		rng: &mut Rng,
+	) -> Result<(Vec<f64>, Vec<f64>)> {
+		let k = self.states;
+		if register.len() != k || register.iter().any(|r| r.len() != k)
+		{
+			bail!("The register must be a {k}x{k} matrix");
+		}
+		let mut matrix = Transition::<N>::from_element(0.0);
+		for (i, row) in register.iter().enumerate() {
+			for (j, value) in row.iter().enumerate() {
+				matrix[(i, j)] = *value;
+			}
+		}
+
+		let reconstruction = self.reconstruction()?;
+		let jumps = mapping::jumps(
+			&reconstruction,
+			&self.transitions.substitution(),
+			&matrix,
+			&self.sites,
+			&self.weights,
+			rng,
+		);
+
+		let num_nodes = reconstruction.partials.len();
+		let mut realized = vec![0.0; num_nodes];
+		let mut expected = vec![0.0; num_nodes];
+		for (edge, &child) in reconstruction.children.iter().enumerate()
+		{
+			realized[child] = jumps.realized[edge];
+			expected[child] = jumps.expected[edge];
+		}
+
+		Ok((realized, expected))
+	}
 }
 
 pub enum ErasedLikelihood {
@@ -138,41 +431,258 @@ pub enum ErasedLikelihood {
 	Nucleotide5(GenericLikelihood<5>),
 	// TODO: amino: 20 standard, 2 special, stop codon
 	Codon(GenericLikelihood<64>),
+	/// Discrete traits, padded to the next supported number of states.
+	Discrete4(GenericLikelihood<4>),
+	Discrete8(GenericLikelihood<8>),
+	Discrete16(GenericLikelihood<16>),
+	Discrete32(GenericLikelihood<32>),
+	Discrete64(GenericLikelihood<64>),
+	Continuous(ContinuousLikelihood),
+}
+
+/// Calls the same code on the inner likelihood of every variant.  The second
+/// form handles the continuous likelihood separately, for the methods which
+/// only make sense for sequences and discrete traits.
+macro_rules! dispatch {
+	($self:expr, $inner:ident => $body:expr) => {
+		dispatch!(
+			$self,
+			$inner => $body,
+			ErasedLikelihood::Continuous($inner) => $body
+		)
+	};
+	($self:expr, $inner:ident => $body:expr, $continuous:pat => $other:expr) => {
+		match $self {
+			ErasedLikelihood::Nucleotide4($inner) => $body,
+			ErasedLikelihood::Nucleotide5($inner) => $body,
+			ErasedLikelihood::Codon($inner) => $body,
+			ErasedLikelihood::Discrete4($inner) => $body,
+			ErasedLikelihood::Discrete8($inner) => $body,
+			ErasedLikelihood::Discrete16($inner) => $body,
+			ErasedLikelihood::Discrete32($inner) => $body,
+			ErasedLikelihood::Discrete64($inner) => $body,
+			$continuous => $other,
+		}
+	};
+}
+
+fn not_discrete() -> anyhow::Error {
+	anyhow::anyhow!("Not supported by the continuous trait likelihood")
 }
 
 impl ErasedLikelihood {
-	pub fn propose(&mut self, py: Python) -> Result<f64> {
-		match self {
-			ErasedLikelihood::Nucleotide4(inner) => {
-				inner.propose(py)
+	/// Discrete trait likelihood with the smallest padding which fits
+	/// `states`.
+	fn discrete(
+		traits: &[String],
+		states: &[String],
+		substitution: &Bound<PyAny>,
+		tree: Py<PyTree>,
+		layout: CpuLayout,
+	) -> Result<Self> {
+		fn new<const N: usize>(
+			traits: &[String],
+			states: &[String],
+			substitution: &Bound<PyAny>,
+			tree: Py<PyTree>,
+			layout: CpuLayout,
+		) -> Result<GenericLikelihood<N>> {
+			let substitution =
+				PySubstitution::<N>::padded(substitution)?;
+			if substitution.dimensions() != states.len() {
+				bail!(
+					"Expected the substitution model to have {} dimensions, got {}",
+					states.len(),
+					substitution.dimensions(),
+				);
 			}
-			_ => todo!(),
+			let patterns = traits_to_patterns::<N>(traits, states)?;
+			GenericLikelihood::new_cpu(
+				substitution,
+				patterns,
+				tree,
+				layout,
+			)
 		}
+
+		Ok(match states.len() {
+			0..=1 => bail!("A discrete trait must have at least 2 states"),
+			2..=4 => Self::Discrete4(new(
+				traits,
+				states,
+				substitution,
+				tree,
+				layout,
+			)?),
+			5..=8 => Self::Discrete8(new(
+				traits,
+				states,
+				substitution,
+				tree,
+				layout,
+			)?),
+			9..=16 => Self::Discrete16(new(
+				traits,
+				states,
+				substitution,
+				tree,
+				layout,
+			)?),
+			17..=32 => Self::Discrete32(new(
+				traits,
+				states,
+				substitution,
+				tree,
+				layout,
+			)?),
+			33..=64 => Self::Discrete64(new(
+				traits,
+				states,
+				substitution,
+				tree,
+				layout,
+			)?),
+			n => bail!("At most 64 trait states are supported, got {n}"),
+		})
+	}
+
+	pub fn propose(&mut self, py: Python) -> Result<f64> {
+		dispatch!(self, inner => inner.propose(py))
 	}
 
 	pub fn accept(&mut self) -> Result<()> {
-		match self {
-			ErasedLikelihood::Nucleotide4(inner) => inner.accept(),
-			ErasedLikelihood::Nucleotide5(inner) => inner.accept(),
-			ErasedLikelihood::Codon(inner) => inner.accept(),
-		}
+		dispatch!(self, inner => inner.accept())
 	}
 
 	pub fn reject(&mut self) -> Result<()> {
-		match self {
-			ErasedLikelihood::Nucleotide4(inner) => inner.reject(),
-			ErasedLikelihood::Nucleotide5(inner) => inner.reject(),
-			ErasedLikelihood::Codon(inner) => inner.reject(),
-		}
+		dispatch!(self, inner => inner.reject())
 	}
 
 	pub fn cached_likelihood(&self) -> f64 {
+		dispatch!(self, inner => inner.cache)
+	}
+
+	pub fn pattern_likelihoods(&self) -> Result<Vec<f64>> {
+		dispatch!(
+			self,
+			inner => inner.pattern_likelihoods(),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	pub fn site_likelihoods(&self) -> Result<Vec<f64>> {
+		dispatch!(
+			self,
+			inner => inner.site_likelihoods(),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	pub fn ancestral_marginal(&self) -> Result<Vec<Vec<Vec<f64>>>> {
+		fn to_vecs<const N: usize>(
+			marginal: Vec<Vec<Row<N>>>,
+			states: usize,
+		) -> Vec<Vec<Vec<f64>>> {
+			marginal.into_iter()
+				.map(|node| {
+					node.into_iter()
+						.map(|row| {
+							row.to_array()[..states]
+								.to_vec()
+						})
+						.collect()
+				})
+				.collect()
+		}
+
+		dispatch!(
+			self,
+			inner => Ok(to_vecs(
+				inner.ancestral_marginal()?,
+				inner.states,
+			)),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	/// Jointly samples the states of the internal nodes, indexed by node
+	/// and then by site.
+	pub fn ancestral_states(
+		&self,
+		rng: &mut Rng,
+	) -> Result<Vec<Vec<usize>>> {
+		dispatch!(
+			self,
+			inner => inner.ancestral_sample(rng),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	/// Jointly samples the sequences of the internal nodes.
+	pub fn ancestral_sequences(
+		&self,
+		rng: &mut Rng,
+	) -> Result<Vec<DnaSeq>> {
+		if !matches!(
+			self,
+			ErasedLikelihood::Nucleotide4(_)
+				| ErasedLikelihood::Nucleotide5(_)
+		) {
+			bail!("Ancestral sequences are only supported for nucleotides")
+		}
+		let states = self.ancestral_states(rng)?;
+
+		use DnaNucleotide::*;
+		let bases = [Adenine, Cytosine, Guanine, Thymine, Gap];
+		Ok(states
+			.into_iter()
+			.map(|node| {
+				let bases: Vec<_> = node
+					.into_iter()
+					.map(|s| bases[s])
+					.collect();
+				DnaSeq::from(bases.as_slice())
+			})
+			.collect())
+	}
+
+	pub fn markov_jumps(
+		&self,
+		register: &[Vec<f64>],
+		rng: &mut Rng,
+	) -> Result<(Vec<f64>, Vec<f64>)> {
+		dispatch!(
+			self,
+			inner => inner.markov_jumps(register, rng),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	/// Jointly samples the continuous traits of every node.
+	pub fn positions(&self, rng: &mut Rng) -> Result<Vec<Vec<f64>>> {
 		match self {
-			ErasedLikelihood::Nucleotide4(inner) => inner.cache,
-			ErasedLikelihood::Nucleotide5(inner) => inner.cache,
-			ErasedLikelihood::Codon(inner) => inner.cache,
+			ErasedLikelihood::Continuous(inner) => inner.positions(rng),
+			_ => bail!("Only supported by the continuous trait likelihood"),
 		}
 	}
+
+	/// Number of states of the substitution model, without the padding.
+	pub fn states(&self) -> Result<usize> {
+		dispatch!(
+			self,
+			inner => Ok(inner.states),
+			ErasedLikelihood::Continuous(_) => Err(not_discrete())
+		)
+	}
+
+	/// Number of sites with each pattern, empty for continuous traits.
+	pub fn pattern_weights(&self) -> &[f64] {
+		dispatch!(
+			self,
+			inner => &inner.weights,
+			ErasedLikelihood::Continuous(_) => &[]
+		)
+	}
 }
 
 #[pyclass(name = "Likelihood", module = "aspartik.b3", frozen)]
@@ -189,31 +699,399 @@ impl PyLikelihood {
 #[pymethods]
 impl PyLikelihood {
 	#[new]
-	#[pyo3(signature = (sequences, substitution, tree, use_gpu = false))]
-	fn new4(
+	#[pyo3(signature = (
+		sequences, substitution, tree, use_gpu = false, gaps = "missing",
+		threads = 1, layout = "pattern",
+	))]
+	fn new(
 		sequences: Vec<PyDnaSeq>,
-		substitution: PySubstitution<4>,
+		substitution: Bound<PyAny>,
 		tree: Py<PyTree>,
 		use_gpu: bool,
+		gaps: &str,
+		threads: usize,
+		layout: &str,
 	) -> Result<Self> {
+		let cpu_layout = CpuLayout::new(layout, threads)?;
+		if use_gpu && (threads != 1 || layout != "pattern") {
+			py_bail!(
+				PyValueError,
+				"`threads` and `layout` can't be set for the GPU likelihood",
+			);
+		}
+
 		let sequences: Vec<DnaSeq> = sequences
 			.iter()
 			.map(|seq| seq.clone().into())
 			.collect();
-		let sites = dna_to_rows(&sequences);
 
-		let generic_likelihood = GenericLikelihood::new(
-			substitution,
-			sites,
+		let erased_likelihood = match gaps {
+			"missing" => {
+				let substitution = substitution.extract()?;
+				let patterns = dna_to_patterns::<4>(&sequences);
+				ErasedLikelihood::Nucleotide4(
+					GenericLikelihood::<4>::new(
+						substitution,
+						patterns,
+						tree,
+						use_gpu,
+						cpu_layout,
+					)?,
+				)
+			}
+			"state" => {
+				if use_gpu {
+					py_bail!(
+						PyValueError,
+						"The GPU likelihood doesn't support gaps as a separate state",
+					);
+				}
+				let substitution = substitution.extract()?;
+				let patterns = dna_to_patterns::<5>(&sequences);
+				ErasedLikelihood::Nucleotide5(
+					GenericLikelihood::<5>::new_cpu(
+						substitution,
+						patterns,
+						tree,
+						cpu_layout,
+					)?,
+				)
+			}
+			_ => py_bail!(
+				PyValueError,
+				"`gaps` must be either 'missing' or 'state', got '{gaps}'",
+			),
+		};
+
+		Ok(PyLikelihood {
+			inner: Mutex::new(erased_likelihood),
+		})
+	}
+
+	/// Likelihood of a discrete trait with one value per leaf.
+	#[staticmethod]
+	#[pyo3(signature = (
+		traits, states, substitution, tree, threads = 1,
+		layout = "pattern",
+	))]
+	fn discrete(
+		traits: Vec<String>,
+		states: Vec<String>,
+		substitution: Bound<PyAny>,
+		tree: Py<PyTree>,
+		threads: usize,
+		layout: &str,
+	) -> Result<Self> {
+		let layout = CpuLayout::new(layout, threads)?;
+
+		let erased_likelihood = ErasedLikelihood::discrete(
+			&traits,
+			&states,
+			&substitution,
 			tree,
-			use_gpu,
+			layout,
 		)?;
 
-		let erased_likelihood =
-			ErasedLikelihood::Nucleotide4(generic_likelihood);
-
 		Ok(PyLikelihood {
 			inner: Mutex::new(erased_likelihood),
 		})
 	}
+
+	/// Likelihood of a continuous trait under Brownian diffusion.
+	#[staticmethod]
+	#[pyo3(signature = (
+		traits, precision, tree, rates = None, root_mean = None,
+		root_sample_size = 1.0,
+	))]
+	fn continuous(
+		traits: Vec<Vec<f64>>,
+		precision: PyParameter,
+		tree: Py<PyTree>,
+		rates: Option<PyParameter>,
+		root_mean: Option<Vec<f64>>,
+		root_sample_size: f64,
+	) -> Result<Self> {
+		let root_prior = root_mean.map(|mean| (mean, root_sample_size));
+		let diffusion = Diffusion::new(traits, root_prior)?;
+		let likelihood = ContinuousLikelihood::new(
+			diffusion, tree, precision, rates,
+		)?;
+
+		Ok(PyLikelihood {
+			inner: Mutex::new(ErasedLikelihood::Continuous(
+				likelihood,
+			)),
+		})
+	}
+
+	/// Log likelihoods of the unique site patterns in the accepted state.
+	fn pattern_likelihoods(&self) -> Result<Vec<f64>> {
+		self.inner().pattern_likelihoods()
+	}
+
+	/// Log likelihoods of every alignment site in the accepted state.
+	fn site_likelihoods(&self) -> Result<Vec<f64>> {
+		self.inner().site_likelihoods()
+	}
+
+	/// Marginal probabilities of the states of each internal node at every
+	/// site, in the accepted state.
+	fn ancestral_marginal(&self) -> Result<Vec<Vec<Vec<f64>>>> {
+		self.inner().ancestral_marginal()
+	}
+
+	/// Jointly samples the sequences of the internal nodes in the accepted
+	/// state.
+	fn sample_ancestral(&self, rng: &PyRng) -> Result<Vec<PyDnaSeq>> {
+		let sequences =
+			self.inner().ancestral_sequences(&mut rng.inner())?;
+		Ok(sequences.into_iter().map(PyDnaSeq::from).collect())
+	}
+
+	/// Jointly samples the state indices of the internal nodes in the
+	/// accepted state.
+	fn sample_ancestral_states(
+		&self,
+		rng: &PyRng,
+	) -> Result<Vec<Vec<usize>>> {
+		self.inner().ancestral_states(&mut rng.inner())
+	}
+
+	/// Jointly samples the continuous traits of every node in the accepted
+	/// state, indexed by node.
+	fn sample_positions(&self, rng: &PyRng) -> Result<Vec<Vec<f64>>> {
+		self.inner().positions(&mut rng.inner())
+	}
+
+	/// Samples substitution histories in the accepted state and counts the
+	/// jumps weighted by `register`, summed over all sites.  Returns the
+	/// realized and the expected counts on the edge above each node.
+	fn markov_jumps(
+		&self,
+		register: Vec<Vec<f64>>,
+		rng: &PyRng,
+	) -> Result<(Vec<f64>, Vec<f64>)> {
+		self.inner().markov_jumps(&register, &mut rng.inner())
+	}
+
+	/// Number of states of the substitution model.
+	#[getter]
+	fn states(&self) -> Result<usize> {
+		self.inner().states()
+	}
+
+	/// Number of sites with each pattern.
+	#[getter]
+	fn pattern_weights(&self) -> Vec<f64> {
+		self.inner().pattern_weights().to_vec()
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+	//! Models and alignments shared by the likelihood tests.
+	use super::{Reconstruction, Row, Transition};
+
+	/// Jukes-Cantor rate matrix with one expected substitution per unit of
+	/// distance.
+	pub fn jc_generator() -> Transition<4> {
+		let mut out = Transition::<4>::from_element(1.0 / 3.0);
+		for i in 0..4 {
+			out[(i, i)] = -1.0;
+		}
+		out
+	}
+
+	/// Jukes-Cantor transition matrix for the distance `t`.
+	pub fn jc(t: f64) -> Transition<4> {
+		let same = 0.25 + 0.75 * (-4.0 * t / 3.0).exp();
+		let diff = 0.25 - 0.25 * (-4.0 * t / 3.0).exp();
+
+		let mut out = Transition::<4>::from_element(diff);
+		for i in 0..4 {
+			out[(i, i)] = same;
+		}
+		out
+	}
+
+	/// An asymmetric three-state rate matrix padded to four states.  Unlike
+	/// with Jukes-Cantor, its transition matrices aren't symmetric, so
+	/// transposing one by mistake changes the result.
+	pub fn asymmetric_generator() -> Transition<4> {
+		let q = [[-1.0, 0.4, 0.6], [0.2, -0.5, 0.3], [0.9, 0.1, -1.0]];
+		let mut out = Transition::<4>::default();
+		for i in 0..3 {
+			for j in 0..3 {
+				out[(i, j)] = q[i][j];
+			}
+		}
+		out
+	}
+
+	/// Transition matrix of [`asymmetric_generator`] for the distance `t`.
+	pub fn asymmetric(t: f64) -> Transition<4> {
+		(asymmetric_generator() * t).expm()
+	}
+
+	/// Tip partial of an observed `state`.
+	pub fn leaf(state: usize) -> Row<4> {
+		let mut row = Row::default();
+		row[state] = 1.0;
+		row
+	}
+
+	/// Transition matrices for a distance under Jukes-Cantor and under an
+	/// asymmetric model.
+	pub const MODELS: [fn(f64) -> Transition<4>; 2] = [jc, asymmetric];
+
+	/// The tree `((0, 1)3, 2)4` with a single pattern `AAC`, with the
+	/// transition matrices of `model`.
+	pub fn reconstruction(
+		model: fn(f64) -> Transition<4>,
+	) -> Reconstruction<4> {
+		let distances = vec![0.1, 0.1, 0.3, 0.4];
+		let transitions: Vec<_> =
+			distances.iter().map(|d| model(*d)).collect();
+		let leaves = [leaf(0), leaf(0), leaf(1)];
+		let node_3 = (transitions[0] * leaves[0])
+			* (transitions[1] * leaves[1]);
+		let node_4 = (transitions[2] * node_3)
+			* (transitions[3] * leaves[2]);
+
+		Reconstruction {
+			nodes: vec![3, 4],
+			children: vec![0, 1, 3, 2],
+			transitions,
+			distances,
+			partials: [
+				leaves[0], leaves[1], leaves[2], node_3, node_4,
+			]
+			.map(|row| vec![row])
+			.to_vec(),
+		}
+	}
+
+	/// `count` patterns on three leaves, with different bases on most of
+	/// them.
+	pub fn sites(count: usize) -> Vec<Vec<Row<4>>> {
+		(0..count)
+			.map(|i| {
+				(0..3).map(|j| leaf((i * (j + 1)) % 4))
+					.collect()
+			})
+			.collect()
+	}
+
+	/// Uneven weights for [`sites`].
+	pub fn weights(count: usize) -> Vec<f64> {
+		(0..count).map(|i| (i % 3 + 1) as f64).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::fixtures::jc;
+	use super::PyLikelihood;
+	use crate::testing::with_python;
+	use data::seq::{python::PyDnaSeq, DnaSeq};
+	use pyo3::{prelude::*, types::IntoPyDict};
+
+	use std::f64::consts::LN_2;
+
+	/// Checks the total log likelihood of a two taxon alignment with
+	/// ambiguous characters and gaps against the closed form of
+	/// Jukes-Cantor.
+	#[test]
+	fn matches_jukes_cantor() {
+		with_python(|env| {
+			// Normalized Jukes-Cantor, where branch lengths are the
+			// expected numbers of substitutions
+			let rate = env.real(1.0 / 3.0);
+			let tree = env.tree(2, 1);
+			{
+				let mut tree = tree.get().inner();
+				let root = tree.root().into();
+				tree.update_weight(root, 0.5);
+				tree.accept();
+			}
+			let likelihood = env.likelihood(
+				&["ACGTRNA-", "ACGAAYTC"],
+				&rate,
+				&tree,
+			);
+
+			// Transition probabilities over the whole distance of 1
+			let same = jc(1.0)[(0, 0)];
+			let diff = jc(1.0)[(0, 1)];
+			// Site likelihoods with the stationary frequencies at
+			// the root, as calculated by BEAST
+			let reference = [
+				same / 4.0,
+				same / 4.0,
+				same / 4.0,
+				diff / 4.0,
+				// R (A or G) against A
+				(same + diff) / 4.0,
+				// N against Y sums over C and T
+				0.5,
+				diff / 4.0,
+				// The gap is missing data
+				0.25,
+			]
+			.map(f64::ln);
+			// The root states are weighted equally instead, which adds
+			// ln 4 to every site
+			let sites = reference.map(|l| l + 2.0 * LN_2);
+			let total: f64 = sites.iter().sum();
+
+			let inner = likelihood.get().inner();
+			assert!((inner.cached_likelihood() - total).abs()
+				< 1e-10);
+			let actual = inner.site_likelihoods().unwrap();
+			assert_eq!(actual.len(), sites.len());
+			for (actual, expected) in actual.iter().zip(sites) {
+				assert!((actual - expected).abs() < 1e-10);
+			}
+		})
+	}
+
+	#[test]
+	fn node_layout_matches_pattern_layout() {
+		with_python(|env| {
+			let sequences = ["ACGTRNA-", "ACGAAYTC", "AGGTACTC"];
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let expected = env.likelihood(&sequences, &rate, &tree);
+
+			let sequences: Vec<PyDnaSeq> = sequences
+				.iter()
+				.map(|s| DnaSeq::try_from(*s).unwrap().into())
+				.collect();
+			let kwargs = [("layout", "node")]
+				.into_py_dict(env.py)
+				.unwrap();
+			let actual: Py<PyLikelihood> =
+				env.b3.getattr("Likelihood")
+					.unwrap()
+					.call(
+						(
+							sequences,
+							env.helper(
+								"JC",
+								(rate.clone(),),
+							),
+							tree.clone_ref(env.py),
+						),
+						Some(&kwargs),
+					)
+					.unwrap()
+					.extract()
+					.unwrap();
+
+			let expected =
+				expected.get().inner().cached_likelihood();
+			let actual = actual.get().inner().cached_likelihood();
+			assert!((actual - expected).abs() < 1e-10);
+		})
+	}
 }
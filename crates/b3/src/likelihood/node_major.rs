@@ -0,0 +1,185 @@
+use anyhow::Result;
+
+use super::{LikelihoodTrait, Row, Transition};
+
+/// CPU likelihood which stores the partials of each node in one contiguous
+/// block of patterns.
+///
+/// Like in [`SkVec`][skvec::SkVec], each node has two blocks: the accepted one
+/// and the one being edited.  Updating a node writes the whole inactive block
+/// and flips the mask, so a proposal touches one allocation per node instead
+/// of one allocation per pattern.
+pub struct NodeMajorLikelihood<const N: usize> {
+	/// Two blocks of partials for every node, each one holding all of the
+	/// patterns.
+	blocks: Vec<[Vec<Row<N>>; 2]>,
+	/// Index of the active block of each node.
+	mask: Vec<u8>,
+	/// True if the active block has been written during this proposal.
+	edited: Vec<bool>,
+
+	/// Number of sites with each pattern.
+	weights: Vec<f64>,
+
+	/// Scratch space for the right child of a node.
+	right: Vec<Row<N>>,
+}
+
+impl<const N: usize> LikelihoodTrait<N> for NodeMajorLikelihood<N> {
+	fn propose(
+		&mut self,
+		nodes: &[usize],
+		transitions: &[Transition<N>],
+		children: &[usize],
+	) -> Result<f64> {
+		assert_eq!(nodes.len() * 2, transitions.len());
+		assert_eq!(nodes.len() * 2, children.len());
+
+		let mut scratch = std::mem::take(&mut self.right);
+		for (i, &node) in nodes.iter().enumerate() {
+			if !self.edited[node] {
+				self.mask[node] ^= 1;
+				self.edited[node] = true;
+			}
+
+			// Take the block out, so that the children can be
+			// borrowed while it's being written to.
+			let active = self.mask[node] as usize;
+			let mut out =
+				std::mem::take(&mut self.blocks[node][active]);
+
+			let left = self.active(children[i * 2]);
+			transitions[i * 2].mul_slice(left, &mut out);
+
+			let right = self.active(children[i * 2 + 1]);
+			transitions[i * 2 + 1].mul_slice(right, &mut scratch);
+
+			for (out, right) in out.iter_mut().zip(&scratch) {
+				*out *= *right;
+			}
+
+			self.blocks[node][active] = out;
+		}
+		self.right = scratch;
+
+		let root = *nodes.last().unwrap();
+		let out = self
+			.active(root)
+			.iter()
+			.zip(&self.weights)
+			.map(|(p, weight)| weight * p.sum().ln())
+			.sum();
+		Ok(out)
+	}
+
+	fn accept(&mut self) -> Result<()> {
+		self.edited.iter_mut().for_each(|e| *e = false);
+		Ok(())
+	}
+
+	fn reject(&mut self) -> Result<()> {
+		for (mask, edited) in self.mask.iter_mut().zip(&mut self.edited)
+		{
+			if *edited {
+				*mask ^= 1;
+				*edited = false;
+			}
+		}
+		Ok(())
+	}
+
+	fn likelihoods(&self, root: usize) -> Result<Vec<f64>> {
+		Ok(self.active(root).iter().map(|p| p.sum().ln()).collect())
+	}
+
+	fn partials(&self, node: usize) -> Result<Vec<Row<N>>> {
+		Ok(self.active(node).to_vec())
+	}
+}
+
+impl<const N: usize> NodeMajorLikelihood<N> {
+	pub fn new(sites: Vec<Vec<Row<N>>>, weights: Vec<f64>) -> Self {
+		assert_eq!(sites.len(), weights.len());
+
+		let num_patterns = sites.len();
+		let num_leaves = sites[0].len();
+		let num_nodes = num_leaves * 2 - 1;
+
+		let empty = vec![Row::<N>::default(); num_patterns];
+		let mut blocks =
+			vec![[empty.clone(), empty.clone()]; num_nodes];
+
+		for (i, site) in sites.iter().enumerate() {
+			for (leaf, row) in site.iter().enumerate() {
+				blocks[leaf][0][i] = *row;
+			}
+		}
+
+		Self {
+			blocks,
+			mask: vec![0; num_nodes],
+			edited: vec![false; num_nodes],
+			weights,
+			right: empty,
+		}
+	}
+
+	fn active(&self, node: usize) -> &[Row<N>] {
+		&self.blocks[node][self.mask[node] as usize]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::likelihood::{
+		fixtures::{jc, sites, weights},
+		CpuLikelihood,
+	};
+
+	#[test]
+	fn matches_pattern_major() {
+		let short = [jc(0.1); 4];
+		let long = [jc(0.5); 4];
+		let nodes = [3, 4];
+		let children = [0, 1, 3, 2];
+
+		let mut expected = CpuLikelihood::new(sites(11), weights(11));
+		let mut actual =
+			NodeMajorLikelihood::new(sites(11), weights(11));
+
+		for (transitions, accept) in [
+			(short, true),
+			(long, false),
+			(long, true),
+			(short, false),
+		] {
+			let a = expected
+				.propose(&nodes, &transitions, &children)
+				.unwrap();
+			let b = actual
+				.propose(&nodes, &transitions, &children)
+				.unwrap();
+			assert!((a - b).abs() < 1e-9);
+
+			if accept {
+				expected.accept().unwrap();
+				actual.accept().unwrap();
+			} else {
+				expected.reject().unwrap();
+				actual.reject().unwrap();
+			}
+
+			// Only recalculate the root to check the stored partials
+			let a = expected
+				.propose(&[4], &short[2..], &[3, 2])
+				.unwrap();
+			let b = actual
+				.propose(&[4], &short[2..], &[3, 2])
+				.unwrap();
+			assert!((a - b).abs() < 1e-9);
+			expected.reject().unwrap();
+			actual.reject().unwrap();
+		}
+	}
+}
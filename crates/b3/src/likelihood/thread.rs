@@ -1,84 +1,150 @@
-use crossbeam_channel::{bounded, select, Receiver, Sender};
+use anyhow::Result;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 
-use std::thread;
-
-use super::{CpuLikelihood, Likelihood};
-use base::substitution::{Row, Substitution};
-
-type Update<const N: usize> =
-	(Vec<Substitution<N>>, Vec<usize>, Vec<(usize, usize)>);
+use super::{CpuLikelihood, LikelihoodTrait, Row, Transition};
 
+/// CPU likelihood which splits the site patterns into chunks and processes
+/// them in parallel.
+///
+/// Each chunk is a separate [`CpuLikelihood`], so accepting and rejecting
+/// works exactly the same way as in the single-threaded calculator.
 pub struct ThreadedLikelihood<const N: usize> {
-	propose: Sender<Update<N>>,
-	likelihood: Receiver<f64>,
-	accept: Sender<()>,
-	reject: Sender<()>,
+	chunks: Vec<CpuLikelihood<N>>,
+	pool: ThreadPool,
 }
 
-impl<const N: usize> Likelihood for ThreadedLikelihood<N> {
-	type Row = Row<N>;
-	type Substitution = Substitution<N>;
-
+impl<const N: usize> LikelihoodTrait<N> for ThreadedLikelihood<N> {
 	fn propose(
 		&mut self,
-		substitutions: &[Self::Substitution],
 		nodes: &[usize],
-		children: &[(usize, usize)],
-	) {
-		self.propose
-			.send((
-				substitutions.to_vec(),
-				nodes.to_vec(),
-				children.to_vec(),
-			))
-			.unwrap();
+		transitions: &[Transition<N>],
+		children: &[usize],
+	) -> Result<f64> {
+		let chunks = &mut self.chunks;
+		self.pool.install(|| {
+			chunks.par_iter_mut()
+				.map(|chunk| {
+					chunk.propose(
+						nodes,
+						transitions,
+						children,
+					)
+				})
+				.sum()
+		})
+	}
+
+	fn accept(&mut self) -> Result<()> {
+		let chunks = &mut self.chunks;
+		self.pool.install(|| {
+			chunks.par_iter_mut()
+				.try_for_each(|chunk| chunk.accept())
+		})
 	}
 
-	fn likelihood(&self) -> f64 {
-		self.likelihood.recv().unwrap()
+	fn reject(&mut self) -> Result<()> {
+		let chunks = &mut self.chunks;
+		self.pool.install(|| {
+			chunks.par_iter_mut()
+				.try_for_each(|chunk| chunk.reject())
+		})
 	}
 
-	fn accept(&mut self) {
-		self.accept.send(()).unwrap()
+	fn likelihoods(&self, root: usize) -> Result<Vec<f64>> {
+		let mut out = Vec::new();
+		for chunk in &self.chunks {
+			out.extend(chunk.likelihoods(root)?);
+		}
+		Ok(out)
 	}
 
-	fn reject(&mut self) {
-		self.reject.send(()).unwrap()
+	fn partials(&self, node: usize) -> Result<Vec<Row<N>>> {
+		let mut out = Vec::new();
+		for chunk in &self.chunks {
+			out.extend(chunk.partials(node)?);
+		}
+		Ok(out)
 	}
 }
 
 impl<const N: usize> ThreadedLikelihood<N> {
-	#[allow(dead_code)]
-	pub fn new(mut likelihood: CpuLikelihood<N>) -> Self {
-		let (propose_sender, propose_reciever) =
-			bounded::<Update<N>>(1);
-		let (likelihood_sender, likelihood_reciever) = bounded(1);
-		let (accept_sender, accept_reciever) = bounded(1);
-		let (reject_sender, reject_reciever) = bounded(1);
-
-		thread::spawn(move || loop {
-			select! {
-				recv(propose_reciever) -> update => {
-					let Ok(update) = update else {
-						break;
-					};
-					likelihood.propose(&update.0, &update.1, &update.2);
-					let _ = likelihood_sender.send(likelihood.likelihood());
-				}
-				recv(accept_reciever) -> _ => {
-					likelihood.accept();
-				}
-				recv(reject_reciever) -> _ => {
-					likelihood.accept();
-				}
-			}
-		});
-
-		Self {
-			propose: propose_sender,
-			likelihood: likelihood_reciever,
-			accept: accept_sender,
-			reject: reject_sender,
+	/// Creates a calculator running on `threads` threads.  The patterns are
+	/// split into one chunk per thread.
+	pub fn new(
+		sites: Vec<Vec<Row<N>>>,
+		weights: Vec<f64>,
+		threads: usize,
+	) -> Result<Self> {
+		let pool = ThreadPoolBuilder::new()
+			.num_threads(threads)
+			.build()?;
+
+		let chunk_size = sites.len().div_ceil(threads);
+		let chunks = sites
+			.chunks(chunk_size)
+			.zip(weights.chunks(chunk_size))
+			.map(|(sites, weights)| {
+				CpuLikelihood::new(
+					sites.to_vec(),
+					weights.to_vec(),
+				)
+			})
+			.collect();
+
+		Ok(Self { chunks, pool })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::likelihood::fixtures::{jc, sites, weights};
+
+	#[test]
+	fn matches_single_threaded() {
+		let transitions = [jc(0.1), jc(0.2), jc(0.3), jc(0.4)];
+		let nodes = [3, 4];
+		let children = [0, 1, 3, 2];
+
+		let mut single = CpuLikelihood::new(sites(37), weights(37));
+		let expected = single
+			.propose(&nodes, &transitions, &children)
+			.unwrap();
+
+		for threads in [1, 2, 3, 8] {
+			let mut threaded = ThreadedLikelihood::new(
+				sites(37),
+				weights(37),
+				threads,
+			)
+			.unwrap();
+			let actual = threaded
+				.propose(&nodes, &transitions, &children)
+				.unwrap();
+			assert!((actual - expected).abs() < 1e-9);
 		}
 	}
+
+	#[test]
+	fn reject_restores() {
+		let short = [jc(0.1); 4];
+		let long = [jc(0.5); 4];
+		let nodes = [3, 4];
+		let children = [0, 1, 3, 2];
+
+		let mut threaded =
+			ThreadedLikelihood::new(sites(37), weights(37), 4)
+				.unwrap();
+		let before =
+			threaded.propose(&nodes, &short, &children).unwrap();
+		threaded.accept().unwrap();
+
+		threaded.propose(&nodes, &long, &children).unwrap();
+		threaded.reject().unwrap();
+
+		// Only the root is recalculated, from the restored partials
+		let after =
+			threaded.propose(&[4], &short[2..], &[3, 2]).unwrap();
+		assert!((after - before).abs() < 1e-9);
+	}
 }
@@ -0,0 +1,297 @@
+//! Marginal likelihood estimation with power posteriors.
+//!
+//! The chain is run on a schedule of powers `beta` from 0 to 1, with the
+//! target distribution `prior + beta * likelihood`.  At each power the
+//! log likelihood of the sampled states is collected, and then the samples are
+//! combined using either path sampling (thermodynamic integration) or
+//! stepping-stone sampling.
+//!
+//! The generalized stepping-stone method (Fan et al., 2011) replaces the prior
+//! at `beta = 0` with a working distribution, which should approximate the
+//! posterior.  The target becomes `beta * (likelihood + prior) + (1 - beta) *
+//! working`, and the collected values are `likelihood + prior - working`.  The
+//! estimators themselves stay the same.
+use anyhow::Result;
+use pyo3::prelude::*;
+
+use crate::PyPrior;
+
+/// Power applied to the posterior of a chain.
+pub(crate) struct Power {
+	pub beta: f64,
+	/// Priors which make up the working distribution of the generalized
+	/// stepping-stone.  Empty for the regular power posterior.
+	pub working: Vec<PyPrior>,
+}
+
+impl Default for Power {
+	fn default() -> Self {
+		Self {
+			beta: 1.0,
+			working: Vec::new(),
+		}
+	}
+}
+
+/// `beta * value`, which is 0 for `beta = 0` even if `value` is infinite.
+fn scale(beta: f64, value: f64) -> f64 {
+	if beta == 0.0 {
+		0.0
+	} else {
+		beta * value
+	}
+}
+
+impl Power {
+	fn working(&self, py: Python) -> Result<f64> {
+		let mut out = 0.0;
+		for prior in &self.working {
+			out += prior.probability(py)?;
+		}
+		Ok(out)
+	}
+
+	/// Log density of the tempered posterior.
+	pub fn target(
+		&self,
+		py: Python,
+		likelihood: f64,
+		prior: f64,
+	) -> Result<f64> {
+		if self.working.is_empty() {
+			return Ok(prior + scale(self.beta, likelihood));
+		}
+
+		let working = self.working(py)?;
+		Ok(scale(self.beta, likelihood + prior)
+			+ scale(1.0 - self.beta, working))
+	}
+
+	/// The value integrated over the powers: the log likelihood for the
+	/// regular power posterior and the log ratio of the posterior to the
+	/// working distribution for the generalized one.
+	pub fn energy(
+		&self,
+		py: Python,
+		likelihood: f64,
+		prior: impl FnOnce() -> Result<f64>,
+	) -> Result<f64> {
+		if self.working.is_empty() {
+			Ok(likelihood)
+		} else {
+			Ok(likelihood + prior()? - self.working(py)?)
+		}
+	}
+}
+
+/// Powers distributed according to the quantiles of `Beta(alpha, 1)`, as done
+/// by Xie et al. (2011).  Small values of `alpha` put more steps near 0,
+/// where the power posterior changes the fastest.
+pub(crate) fn schedule(steps: usize, alpha: f64) -> Vec<f64> {
+	(0..=steps)
+		.map(|k| (k as f64 / steps as f64).powf(1.0 / alpha))
+		.collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+	values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn variance(values: &[f64]) -> f64 {
+	let mean = mean(values);
+	let sum: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+	sum / (values.len() as f64 - 1.0)
+}
+
+/// Trapezoidal integration of the mean energy over the powers.
+///
+/// The standard error treats the samples as independent, so it's an
+/// underestimate for autocorrelated chains.
+fn path_sampling(betas: &[f64], samples: &[Vec<f64>]) -> (f64, f64) {
+	let n = betas.len();
+	let mut estimate = 0.0;
+	let mut error = 0.0;
+
+	for k in 0..n {
+		// Weight of the k-th mean in the trapezoid sum
+		let left = if k > 0 { betas[k] - betas[k - 1] } else { 0.0 };
+		let right = if k + 1 < n {
+			betas[k + 1] - betas[k]
+		} else {
+			0.0
+		};
+		let weight = (left + right) / 2.0;
+
+		estimate += weight * mean(&samples[k]);
+		error += weight.powi(2) * variance(&samples[k])
+			/ samples[k].len() as f64;
+	}
+
+	(estimate, error.sqrt())
+}
+
+/// Product of the importance sampling ratios between neighbouring powers,
+/// each estimated from the samples of the lower power.
+///
+/// The error of each ratio is estimated with the delta method, assuming
+/// independent samples.
+fn stepping_stone(betas: &[f64], samples: &[Vec<f64>]) -> (f64, f64) {
+	let mut estimate = 0.0;
+	let mut error = 0.0;
+
+	for k in 0..(betas.len() - 1) {
+		let delta = betas[k + 1] - betas[k];
+		let samples = &samples[k];
+
+		// Factor out the largest term to avoid overflow
+		let max = samples
+			.iter()
+			.copied()
+			.fold(f64::NEG_INFINITY, f64::max);
+		let weights: Vec<f64> = samples
+			.iter()
+			.map(|v| (delta * (v - max)).exp())
+			.collect();
+		let weights_mean = mean(&weights);
+
+		estimate += delta * max + weights_mean.ln();
+		error += variance(&weights)
+			/ (weights.len() as f64 * weights_mean.powi(2));
+	}
+
+	(estimate, error.sqrt())
+}
+
+/// Log marginal likelihood estimates from a power posterior run.
+#[derive(Debug, Clone)]
+#[pyclass(name = "MarginalLikelihood", module = "aspartik.b3", frozen)]
+pub struct MarginalLikelihood {
+	/// Powers at which the chain was sampled, from 0 to 1.
+	#[pyo3(get)]
+	powers: Vec<f64>,
+	/// Mean of the collected values at each power.
+	#[pyo3(get)]
+	means: Vec<f64>,
+	/// `True` if the estimates use a working distribution.
+	#[pyo3(get)]
+	generalized: bool,
+
+	/// Path sampling estimate of the log marginal likelihood.
+	#[pyo3(get)]
+	path_sampling: f64,
+	/// Standard error of the path sampling estimate.
+	#[pyo3(get)]
+	path_sampling_error: f64,
+	/// Stepping-stone estimate of the log marginal likelihood.
+	#[pyo3(get)]
+	stepping_stone: f64,
+	/// Standard error of the stepping-stone estimate.
+	#[pyo3(get)]
+	stepping_stone_error: f64,
+}
+
+impl MarginalLikelihood {
+	/// `samples` holds the values collected at each of `powers`.
+	pub(crate) fn new(
+		powers: Vec<f64>,
+		samples: Vec<Vec<f64>>,
+		generalized: bool,
+	) -> Self {
+		let means = samples.iter().map(|s| mean(s)).collect();
+		let (path_sampling, path_sampling_error) =
+			path_sampling(&powers, &samples);
+		let (stepping_stone, stepping_stone_error) =
+			stepping_stone(&powers, &samples);
+
+		Self {
+			powers,
+			means,
+			generalized,
+			path_sampling,
+			path_sampling_error,
+			stepping_stone,
+			stepping_stone_error,
+		}
+	}
+}
+
+#[pymethods]
+impl MarginalLikelihood {
+	fn __repr__(&self) -> String {
+		format!(
+			"MarginalLikelihood(path_sampling={} ± {}, stepping_stone={} ± {}, generalized={})",
+			self.path_sampling,
+			self.path_sampling_error,
+			self.stepping_stone,
+			self.stepping_stone_error,
+			if self.generalized { "True" } else { "False" },
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::{rngs::SmallRng, SeedableRng};
+	use rand_distr::{Distribution, Normal};
+
+	use std::f64::consts::PI;
+
+	#[test]
+	fn schedule_spans_unit_interval() {
+		for alpha in [0.3, 1.0, 2.0] {
+			let powers = schedule(10, alpha);
+			assert_eq!(powers.len(), 11);
+			assert_eq!(powers[0], 0.0);
+			assert_eq!(powers[10], 1.0);
+			assert!(powers.windows(2).all(|w| w[0] < w[1]));
+		}
+	}
+
+	/// A normal mean with the prior `N(0, 1)` and one observation `y` with
+	/// the likelihood `N(y | theta, 1)`.  The power posteriors are normal,
+	/// so they are sampled exactly, and the marginal likelihood is
+	/// `N(y | 0, 2)`.
+	#[test]
+	fn conjugate_normal() {
+		let y: f64 = 1.5;
+		let log_likelihood = |theta: f64| {
+			-0.5 * (2.0 * PI).ln() - (y - theta).powi(2) / 2.0
+		};
+		let exact = -0.5 * (4.0 * PI).ln() - y.powi(2) / 4.0;
+
+		let mut rng = SmallRng::seed_from_u64(4);
+		let powers = schedule(40, 0.3);
+		let samples: Vec<Vec<f64>> =
+			powers.iter()
+				.map(|beta| {
+					let precision = 1.0 + beta;
+					let normal = Normal::new(
+						beta * y / precision,
+						precision.powf(-0.5),
+					)
+					.unwrap();
+					(0..5000)
+						.map(|_| {
+							log_likelihood(normal.sample(&mut rng))
+						})
+						.collect()
+				})
+				.collect();
+
+		let estimate = MarginalLikelihood::new(powers, samples, false);
+		for (value, error) in [
+			(estimate.path_sampling, estimate.path_sampling_error),
+			(
+				estimate.stepping_stone,
+				estimate.stepping_stone_error,
+			),
+		] {
+			assert!(error > 0.0 && error < 0.05, "{error}");
+			assert!(
+				(value - exact).abs() < 0.02,
+				"{value} vs {exact}"
+			);
+		}
+	}
+}
@@ -1,13 +1,16 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use parking_lot::Mutex;
 use pyo3::prelude::*;
 use rand::Rng as _;
+use rayon::prelude::*;
 use tracing::{instrument, trace};
 
 use crate::{
 	likelihood::PyLikelihood,
+	marginal::{schedule, MarginalLikelihood, Power},
 	operator::{Proposal, PyOperator, WeightedScheduler},
-	parameter::{Parameter, PyParameter},
+	parameter::PyParameter,
+	state::{PyState, Transaction},
 	tree::PyTree,
 	PyLogger, PyPrior,
 };
@@ -17,22 +20,24 @@ use rng::PyRng;
 pub struct Mcmc {
 	posterior: Mutex<f64>,
 
-	#[allow(unused)]
 	burnin: usize,
 	length: usize,
 
 	trees: Vec<Py<PyTree>>,
 
-	/// TODO: parameter serialization
-	backup_params: Mutex<Vec<Parameter>>,
-	/// Current set of parameters by name.
-	params: Vec<PyParameter>,
-
 	priors: Vec<PyPrior>,
 	scheduler: WeightedScheduler,
 	likelihoods: Vec<Py<PyLikelihood>>,
 	loggers: Vec<PyLogger>,
 	rng: Py<PyRng>,
+
+	/// Trees, parameters, likelihoods, and extra states, which are accepted
+	/// or rejected together at the end of each step.
+	transaction: Transaction,
+
+	/// Ignore the data: likelihoods are never calculated and count as zero,
+	/// so the chain samples from the prior.
+	sample_prior: bool,
 }
 
 #[pymethods]
@@ -42,6 +47,10 @@ impl Mcmc {
 	// but I'll have to benchmark that.
 	#[expect(clippy::too_many_arguments)]
 	#[new]
+	#[pyo3(signature = (
+		burnin, length, trees, params, priors, operators, likelihoods,
+		loggers, rng, states = Vec::new(), sample_prior = false,
+	))]
 	fn new(
 		py: Python,
 
@@ -55,26 +64,43 @@ impl Mcmc {
 		likelihoods: Vec<Py<PyLikelihood>>,
 		loggers: Vec<PyLogger>,
 		rng: Py<PyRng>,
+		states: Vec<PyState>,
+		sample_prior: bool,
 	) -> Result<Mcmc> {
-		let mut backup_params = Vec::with_capacity(params.len());
-		for param in &params {
-			backup_params.push(param.inner().clone());
-		}
-		let backup_params = Mutex::new(backup_params);
 		let scheduler = WeightedScheduler::new(py, operators)?;
 
+		let mut transaction = Transaction::new();
+		for tree in &trees {
+			transaction.push(tree.clone_ref(py));
+		}
+		for param in params {
+			// Values set before the chain was built are the starting
+			// point, so rejections must not roll them back.
+			param.accept();
+			transaction.push(param);
+		}
+		// Likelihoods don't change when sampling from the prior
+		if !sample_prior {
+			for likelihood in &likelihoods {
+				transaction.push(likelihood.clone_ref(py));
+			}
+		}
+		for state in states {
+			transaction.push(state);
+		}
+
 		Ok(Mcmc {
 			posterior: Mutex::new(f64::NEG_INFINITY),
 			burnin,
 			length,
 			trees,
-			params,
-			backup_params,
 			priors,
 			scheduler,
 			likelihoods,
 			loggers,
 			rng,
+			transaction,
+			sample_prior,
 		})
 	}
 
@@ -83,9 +109,9 @@ impl Mcmc {
 		let self_ = this.get();
 		for index in 0..self_.length {
 			trace!(step = index);
-			self_.step(py).with_context(|| {
-				anyhow!("Failed on step {index}")
-			})?;
+			self_.step(py, &Power::default()).with_context(
+				|| anyhow!("Failed on step {index}"),
+			)?;
 
 			for logger in &self_.loggers {
 				logger.log(py, this.clone_ref(py), index)
@@ -98,6 +124,138 @@ impl Mcmc {
 		Ok(())
 	}
 
+	/// Runs `chains` independent chains in parallel.
+	///
+	/// `factory` is called with the chain index and a random generator for
+	/// that chain, and must return a new `MCMC` which doesn't share any
+	/// state with the other chains.  The generators use distinct streams
+	/// derived from `seed`, so the whole run is reproducible.  Each chain
+	/// should get its own log files, for example by including the index in
+	/// the file names.
+	///
+	/// The chains run on a pool of `threads` threads, one per chain by
+	/// default.  Python code, such as operators and priors, still runs one
+	/// chain at a time, but the likelihood calculations are done in
+	/// parallel.
+	///
+	/// Returns the chains in the order of their indices.
+	#[staticmethod]
+	#[pyo3(signature = (factory, chains, seed, threads = None))]
+	#[instrument(skip_all)]
+	fn run_chains(
+		py: Python,
+		factory: Bound<PyAny>,
+		chains: usize,
+		seed: u64,
+		threads: Option<usize>,
+	) -> Result<Vec<Py<Self>>> {
+		let mut mcmcs = Vec::with_capacity(chains);
+		for index in 0..chains {
+			let rng = PyRng::from_stream(seed, index as u64);
+			let mcmc = factory
+				.call1((index, rng))?
+				.extract::<Py<Self>>()
+				.with_context(|| {
+					anyhow!("Factory failed to create chain {index}")
+				})?;
+			mcmcs.push(mcmc);
+		}
+
+		let pool = rayon::ThreadPoolBuilder::new()
+			.num_threads(threads.unwrap_or(chains))
+			.build()?;
+
+		py.allow_threads(|| {
+			pool.install(|| {
+				mcmcs.par_iter().enumerate().try_for_each(
+					|(index, mcmc)| {
+						Python::with_gil(|py| {
+							Self::run(
+								mcmc.clone_ref(
+									py,
+								),
+								py,
+							)
+						})
+						.with_context(|| {
+							anyhow!("Chain {index} failed")
+						})
+					},
+				)
+			})
+		})?;
+
+		Ok(mcmcs)
+	}
+
+	/// Estimates the log marginal likelihood of the model.
+	///
+	/// The chain is run at `steps + 1` powers of the likelihood spaced
+	/// according to `alpha`, for `length` steps each, of which the first
+	/// `burnin` are discarded.  If `working` priors are passed, they are used
+	/// as the working distribution of the generalized stepping-stone.
+	/// Loggers aren't called during the estimation.
+	#[pyo3(signature = (steps = 100, alpha = 0.3, working = Vec::new()))]
+	#[instrument(skip_all)]
+	fn marginal_likelihood(
+		&self,
+		py: Python,
+		steps: usize,
+		alpha: f64,
+		working: Vec<PyPrior>,
+	) -> Result<MarginalLikelihood> {
+		ensure!(
+			!self.sample_prior,
+			"The marginal likelihood can't be estimated when sampling from the prior"
+		);
+		ensure!(steps > 0, "At least one step is needed");
+		ensure!(alpha > 0.0, "`alpha` must be positive, got {alpha}");
+		ensure!(
+			self.length >= self.burnin + 2,
+			"At least two samples are needed after burnin at each power"
+		);
+
+		let powers = schedule(steps, alpha);
+		let generalized = !working.is_empty();
+		let mut power = Power { beta: 0.0, working };
+		let mut samples = Vec::with_capacity(powers.len());
+
+		for beta in &powers {
+			trace!(beta);
+			power.beta = *beta;
+			// The current state has to be evaluated under the new
+			// power for the acceptance ratios to be valid.
+			*self.posterior.lock() = power.target(
+				py,
+				self.likelihood(),
+				self.prior(py)?,
+			)?;
+
+			let mut values =
+				Vec::with_capacity(self.length - self.burnin);
+			for index in 0..self.length {
+				self.step(py, &power).with_context(|| {
+					anyhow!("Failed on step {index} with power {beta}")
+				})?;
+
+				if index >= self.burnin {
+					let value = power.energy(
+						py,
+						self.likelihood(),
+						|| self.prior(py),
+					)?;
+					values.push(value);
+				}
+			}
+			samples.push(values);
+		}
+
+		// Return to the untempered posterior
+		*self.posterior.lock() = self.likelihood() + self.prior(py)?;
+
+		Ok(MarginalLikelihood::new(powers, samples, generalized))
+	}
+
 	#[getter]
 	fn posterior(&self) -> f64 {
 		*self.posterior.lock()
@@ -105,6 +263,10 @@ impl Mcmc {
 
 	#[getter]
 	fn likelihood(&self) -> f64 {
+		if self.sample_prior {
+			return 0.0;
+		}
+
 		let mut out = 0.0;
 		for likelihood in &self.likelihoods {
 			out += likelihood.get().inner().cached_likelihood();
@@ -129,7 +291,7 @@ impl Mcmc {
 
 impl Mcmc {
 	#[instrument(skip_all)]
-	fn step(&self, py: Python) -> Result<()> {
+	fn step(&self, py: Python, power: &Power) -> Result<()> {
 		let rng = self.rng.get();
 		let operator = self.scheduler.select_operator(&mut rng.inner());
 
@@ -141,6 +303,22 @@ impl Mcmc {
 		)
 			})? {
 				Proposal::Accept() => {
+					// The likelihoods still have to see the new
+					// state, or their caches would go stale.
+					let likelihood =
+						self.propose_likelihoods(py)?;
+					let prior = self.prior(py)?;
+					// Even an unconditional move can't make
+					// the state impossible
+					if likelihood == f64::NEG_INFINITY
+						|| prior == f64::NEG_INFINITY
+					{
+						self.reject()?;
+						return Ok(());
+					}
+					*self.posterior.lock() = power.target(
+						py, likelihood, prior,
+					)?;
 					self.accept()?;
 					return Ok(());
 				}
@@ -162,12 +340,8 @@ impl Mcmc {
 			return Ok(());
 		}
 
-		let mut likelihood = 0.0;
-		for py_likelihood in &self.likelihoods {
-			likelihood +=
-				py_likelihood.get().inner().propose(py)?;
-		}
-		let new_posterior = likelihood + prior;
+		let likelihood = self.propose_likelihoods(py)?;
+		let new_posterior = power.target(py, likelihood, prior)?;
 
 		let old_posterior = *self.posterior.lock();
 
@@ -194,41 +368,359 @@ impl Mcmc {
 		Ok(())
 	}
 
+	/// Total log likelihood of the proposed state, which is zero when
+	/// sampling from the prior.
+	fn propose_likelihoods(&self, py: Python) -> Result<f64> {
+		let mut likelihood = 0.0;
+		if !self.sample_prior {
+			for py_likelihood in &self.likelihoods {
+				likelihood += py_likelihood
+					.get()
+					.inner()
+					.propose(py)?;
+			}
+		}
+		Ok(likelihood)
+	}
+
 	fn accept(&self) -> Result<()> {
-		trace!("accept proposal");
+		self.transaction.accept()
+	}
 
-		for tree in &self.trees {
-			tree.get().inner().accept();
-		}
+	fn reject(&self) -> Result<()> {
+		self.transaction.reject()
+	}
+}
 
-		for likelihood in &self.likelihoods {
-			likelihood.get().inner().accept()?;
-		}
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		parameter::Parameter,
+		testing::{with_python, Env},
+	};
+	use pyo3::types::IntoPyDict;
 
-		let mut backup_params = self.backup_params.lock();
-		for i in 0..self.params.len() {
-			backup_params[i] = self.params[i].inner().clone();
-		}
+	const SEQUENCES: [&str; 3] = ["ACGTACGTAA", "ACGTTCGTAA", "AGGTTCGAAC"];
 
-		Ok(())
+	/// A chain with a flat prior which uses `operators`.
+	fn mcmc(
+		env: &Env,
+		length: usize,
+		tree: &Py<PyTree>,
+		params: Vec<PyParameter>,
+		operators: Vec<Bound<PyAny>>,
+		likelihood: &Py<PyLikelihood>,
+		sample_prior: bool,
+	) -> Py<Mcmc> {
+		let py = env.py;
+		let kwargs = [("sample_prior", sample_prior)]
+			.into_py_dict(py)
+			.unwrap();
+		env.b3.getattr("MCMC")
+			.unwrap()
+			.call(
+				(
+					0,
+					length,
+					vec![tree.clone_ref(py)],
+					params,
+					vec![env.helper("Flat", ())],
+					operators,
+					vec![likelihood.clone_ref(py)],
+					Vec::<PyObject>::new(),
+					env.rng(4),
+				),
+				Some(&kwargs),
+			)
+			.unwrap()
+			.extract()
+			.unwrap()
 	}
 
-	fn reject(&self) -> Result<()> {
-		trace!("reject proposal");
+	fn cached(likelihood: &Py<PyLikelihood>) -> f64 {
+		likelihood.get().inner().cached_likelihood()
+	}
 
-		for tree in &self.trees {
-			tree.get().inner().reject();
-		}
+	#[test]
+	fn accept_proposal_updates_likelihood() {
+		with_python(|env| {
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let likelihood =
+				env.likelihood(&SEQUENCES, &rate, &tree);
+			let before = cached(&likelihood);
+
+			let operator = env.helper(
+				"SetParameter",
+				(
+					rate.clone(),
+					vec![0.1],
+					env.proposal("Accept"),
+				),
+			);
+			let chain = mcmc(
+				env,
+				1,
+				&tree,
+				vec![rate.clone()],
+				vec![operator],
+				&likelihood,
+				false,
+			);
+			Mcmc::run(chain.clone_ref(env.py), env.py).unwrap();
+
+			let after = cached(&likelihood);
+			assert_ne!(before, after);
+			// A fresh likelihood sees the accepted rate
+			let fresh = env.likelihood(&SEQUENCES, &rate, &tree);
+			assert!((cached(&fresh) - after).abs() < 1e-10);
+			assert_eq!(chain.get().posterior(), after);
+		})
+	}
 
-		for likelihood in &self.likelihoods {
-			likelihood.get().inner().reject()?;
-		}
+	#[test]
+	fn reject_keeps_values_set_before_the_chain() {
+		with_python(|env| {
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let likelihood =
+				env.likelihood(&SEQUENCES, &rate, &tree);
+			*rate.inner_mut() = Parameter::Real(vec![0.5]);
+
+			let operator =
+				env.helper("Fixed", (env.proposal("Reject"),));
+			let chain = mcmc(
+				env,
+				1,
+				&tree,
+				vec![rate.clone()],
+				vec![operator],
+				&likelihood,
+				false,
+			);
+			Mcmc::run(chain.clone_ref(env.py), env.py).unwrap();
+
+			assert_eq!(*rate.inner(), Parameter::Real(vec![0.5]));
+		})
+	}
+
+	#[test]
+	fn accept_proposal_of_broken_rates_is_rejected() {
+		with_python(|env| {
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let likelihood =
+				env.likelihood(&SEQUENCES, &rate, &tree);
+			let before = cached(&likelihood);
+
+			// An infinite rate can't be exponentiated
+			let set = env.helper(
+				"SetParameter",
+				(
+					rate.clone(),
+					vec![f64::INFINITY],
+					env.proposal("Accept"),
+				),
+			);
+			let chain = mcmc(
+				env,
+				1,
+				&tree,
+				vec![rate.clone()],
+				vec![set],
+				&likelihood,
+				false,
+			);
+			Mcmc::run(chain.clone_ref(env.py), env.py).unwrap();
+
+			assert_eq!(*rate.inner(), Parameter::Real(vec![1.0]));
+			assert_eq!(cached(&likelihood), before);
+
+			// A move which touches neither the tree nor the rates
+			// reuses the accepted likelihood
+			let fixed =
+				env.helper("Fixed", (env.proposal("Accept"),));
+			let chain = mcmc(
+				env,
+				1,
+				&tree,
+				vec![rate.clone()],
+				vec![fixed],
+				&likelihood,
+				false,
+			);
+			Mcmc::run(chain.clone_ref(env.py), env.py).unwrap();
+			assert_eq!(chain.get().posterior(), before);
+		})
+	}
 
-		let backup_params = self.backup_params.lock();
-		for i in 0..self.params.len() {
-			*self.params[i].inner() = backup_params[i].clone();
+	#[test]
+	fn sample_prior_ignores_likelihood() {
+		let sequences = ["AAAAAAAAAAAAAAAAAAAA"; 3];
+		for sample_prior in [false, true] {
+			with_python(|env| {
+				let rate = env.real(1.0);
+				let tree = env.tree(3, 1);
+				let likelihood = env
+					.likelihood(&sequences, &rate, &tree);
+				// Identical sequences strongly favour the low rate,
+				// so the second proposal is only accepted if the
+				// likelihood is ignored.
+				let operator = env.helper(
+					"SetParameter",
+					(
+						rate.clone(),
+						vec![1e-3, 1e3],
+						env.proposal("Hastings"),
+					),
+				);
+				let chain = mcmc(
+					env,
+					2,
+					&tree,
+					vec![rate.clone()],
+					vec![operator],
+					&likelihood,
+					sample_prior,
+				);
+				Mcmc::run(chain.clone_ref(env.py), env.py)
+					.unwrap();
+
+				let value =
+					if sample_prior { 1e3 } else { 1e-3 };
+				assert_eq!(
+					*rate.inner(),
+					Parameter::Real(vec![value])
+				);
+				if sample_prior {
+					assert_eq!(
+						chain.get().likelihood(),
+						0.0
+					);
+					assert_eq!(
+						chain.get().posterior(),
+						0.0
+					);
+				} else {
+					assert!(chain.get().likelihood() < 0.0);
+				}
+			})
 		}
+	}
 
-		Ok(())
+	#[test]
+	fn marginal_likelihood_of_fixed_state() {
+		with_python(|env| {
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let likelihood =
+				env.likelihood(&SEQUENCES, &rate, &tree);
+			// The state never changes, so the likelihood is the same
+			// at every power and both estimates must equal it.
+			let operator = env
+				.helper("Fixed", (env.proposal("Hastings"),));
+			let chain = mcmc(
+				env,
+				5,
+				&tree,
+				vec![rate.clone()],
+				vec![operator],
+				&likelihood,
+				false,
+			);
+			let estimate = Py::new(
+				env.py,
+				chain.get()
+					.marginal_likelihood(
+						env.py,
+						4,
+						0.3,
+						vec![],
+					)
+					.unwrap(),
+			)
+			.unwrap();
+
+			let expected = cached(&likelihood);
+			for name in ["path_sampling", "stepping_stone"] {
+				let value: f64 = estimate
+					.getattr(env.py, name)
+					.unwrap()
+					.extract(env.py)
+					.unwrap();
+				assert!(
+					(value - expected).abs() < 1e-8,
+					"{name}: {value} != {expected}"
+				);
+			}
+		})
+	}
+
+	#[test]
+	fn marginal_likelihood_rejects_sample_prior() {
+		with_python(|env| {
+			let rate = env.real(1.0);
+			let tree = env.tree(3, 1);
+			let likelihood =
+				env.likelihood(&SEQUENCES, &rate, &tree);
+			let operator = env
+				.helper("Fixed", (env.proposal("Hastings"),));
+			let chain = mcmc(
+				env,
+				5,
+				&tree,
+				vec![rate.clone()],
+				vec![operator],
+				&likelihood,
+				true,
+			);
+			assert!(chain
+				.get()
+				.marginal_likelihood(env.py, 4, 0.3, vec![])
+				.is_err());
+		})
+	}
+
+	#[test]
+	fn chains_are_reproducible() {
+		with_python(|env| {
+			let run = |seed: u64| -> Vec<Vec<f64>> {
+				let (factory, walks): (
+					Bound<PyAny>,
+					Bound<PyAny>,
+				) = env.helper(
+					"random_walks",
+					(env.b3.clone(), 20),
+				)
+				.extract()
+				.unwrap();
+				let chains = Mcmc::run_chains(
+					env.py,
+					factory,
+					3,
+					seed,
+					Some(2),
+				)
+				.unwrap();
+				assert_eq!(chains.len(), 3);
+				(0..3).map(|i| {
+					walks.get_item(i)
+						.unwrap()
+						.getattr("values")
+						.unwrap()
+						.extract()
+						.unwrap()
+				})
+				.collect()
+			};
+
+			let walks = run(7);
+			assert!(walks.iter().all(|w| w.len() == 20));
+			assert_eq!(walks, run(7));
+			assert_ne!(walks[0], walks[1]);
+			assert_ne!(walks[1], walks[2]);
+			assert_ne!(walks, run(8));
+		})
 	}
 }
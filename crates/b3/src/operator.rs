@@ -17,6 +17,29 @@ pub enum Proposal {
 	Accept(),
 }
 
+impl Proposal {
+	/// Combines the outcomes of two moves applied one after another.
+	///
+	/// A rejection of either move rejects both of them, and the Hastings
+	/// ratios add up.  `Accept` only survives if both moves are accepted
+	/// unconditionally, otherwise it contributes nothing to the ratio.
+	pub fn then(self, other: Proposal) -> Proposal {
+		match (self, other) {
+			(Proposal::Reject(), _) | (_, Proposal::Reject()) => {
+				Proposal::Reject()
+			}
+			(Proposal::Accept(), Proposal::Accept()) => {
+				Proposal::Accept()
+			}
+			(Proposal::Hastings(a), Proposal::Hastings(b)) => {
+				Proposal::Hastings(a + b)
+			}
+			(Proposal::Hastings(r), Proposal::Accept())
+			| (Proposal::Accept(), Proposal::Hastings(r)) => Proposal::Hastings(r),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 #[pyclass(module = "aspartik.b3", name = "Proposal", frozen)]
 pub struct PyProposal(Proposal);
@@ -105,6 +128,64 @@ impl PyOperator {
 	}
 }
 
+/// An operator which applies several operators in a row as a single move.
+///
+/// The proposals of the inner operators are combined: if any of them rejects
+/// the whole move is rejected, and otherwise the Hastings ratios are summed.
+/// The posterior is only evaluated once, after all of the operators have
+/// been applied.  Compound operators can be nested.
+#[derive(Debug)]
+#[pyclass(module = "aspartik.b3", name = "Compound", frozen)]
+pub struct PyCompound {
+	operators: Vec<PyOperator>,
+	#[pyo3(get)]
+	weight: f64,
+}
+
+#[pymethods]
+impl PyCompound {
+	#[new]
+	#[pyo3(signature = (operators, weight = 1.0))]
+	fn new(operators: Vec<PyOperator>, weight: f64) -> Result<Self> {
+		if operators.is_empty() {
+			py_bail!(
+				PyValueError,
+				"Operator list must not be empty",
+			);
+		}
+
+		Ok(Self { operators, weight })
+	}
+
+	fn propose(&self, py: Python) -> Result<PyProposal> {
+		let mut out = Proposal::Accept();
+
+		for operator in &self.operators {
+			out = out.then(operator.propose(py)?);
+
+			// the state will be rolled back regardless
+			if out == Proposal::Reject() {
+				break;
+			}
+		}
+
+		Ok(PyProposal(out))
+	}
+
+	fn __repr__(&self, py: Python) -> Result<String> {
+		let mut reprs = Vec::with_capacity(self.operators.len());
+		for operator in &self.operators {
+			reprs.push(operator.repr(py)?.to_string());
+		}
+
+		Ok(format!(
+			"Compound([{}], weight={})",
+			reprs.join(", "),
+			self.weight
+		))
+	}
+}
+
 #[derive(Debug)]
 pub struct WeightedScheduler {
 	operators: Vec<PyOperator>,
@@ -145,3 +226,59 @@ impl WeightedScheduler {
 		&self.operators[index]
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::testing::with_python;
+
+	#[test]
+	fn compound_stops_at_reject() {
+		with_python(|env| {
+			let hastings = |ratio: f64| {
+				env.b3.getattr("Proposal")
+					.unwrap()
+					.call_method1("Hastings", (ratio,))
+					.unwrap()
+			};
+			let first = env.helper("Fixed", (hastings(0.5),));
+			let accept =
+				env.helper("Fixed", (env.proposal("Accept"),));
+			let reject =
+				env.helper("Fixed", (env.proposal("Reject"),));
+			let last = env.helper("Fixed", (hastings(0.25),));
+			let calls = |operator: &Bound<PyAny>| -> usize {
+				operator.getattr("calls")
+					.unwrap()
+					.extract()
+					.unwrap()
+			};
+			let compound = |operators: Vec<&Bound<PyAny>>| {
+				let operators = operators
+					.into_iter()
+					.map(|o| o.extract().unwrap())
+					.collect();
+				PyCompound::new(operators, 1.0).unwrap()
+			};
+
+			let proposal = compound(vec![&first, &accept, &last])
+				.propose(env.py)
+				.unwrap();
+			assert_eq!(proposal.0, Proposal::Hastings(0.75));
+
+			let proposal = compound(vec![&first, &reject, &last])
+				.propose(env.py)
+				.unwrap();
+			assert_eq!(proposal.0, Proposal::Reject());
+			assert_eq!(calls(&first), 2);
+			assert_eq!(calls(&reject), 1);
+			// Operators after the rejection aren't applied
+			assert_eq!(calls(&last), 1);
+
+			let proposal = compound(vec![&accept])
+				.propose(env.py)
+				.unwrap();
+			assert_eq!(proposal.0, Proposal::Accept());
+		})
+	}
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 use pyo3::prelude::*;
 use pyo3::{
 	class::basic::CompareOp,
@@ -10,9 +10,12 @@ use pyo3::{
 
 use std::{
 	fmt::{self, Display},
+	ops::Deref,
 	sync::Arc,
 };
 
+use crate::version::Version;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Parameter {
 	Real(Vec<f64>),
@@ -106,21 +109,91 @@ impl Display for Parameter {
 /// The parameter values can be accessed using indexing.  Dimensions are
 /// zero-indexed, so `param[0]` is the first value, `param[1]` is the second,
 /// and so on.
+///
+/// Each parameter carries a version, which changes on every write.  It can be
+/// used to cache values derived from the parameter, see `version`.
 pub struct PyParameter {
-	inner: Arc<Mutex<Parameter>>,
+	inner: Arc<Mutex<Versioned>>,
+}
+
+#[derive(Debug)]
+struct Versioned {
+	parameter: Parameter,
+	/// Value of the parameter at the last accepted step.  It's only stored
+	/// once the parameter is written to, so proposals which don't touch the
+	/// parameter don't pay for the copy.
+	backup: Option<Parameter>,
+	version: Version,
 }
 
 impl PyParameter {
-	pub fn inner(&self) -> MutexGuard<Parameter> {
-		self.inner.lock()
+	fn new(parameter: Parameter) -> Self {
+		let versioned = Versioned {
+			parameter,
+			backup: None,
+			version: Version::default(),
+		};
+
+		Self {
+			inner: Arc::new(Mutex::new(versioned)),
+		}
 	}
 
-	pub fn deep_copy(&self) -> PyParameter {
-		let inner = &*self.inner();
+	/// Read access to the parameter values.  Writes must go through
+	/// [`inner_mut`][Self::inner_mut], so that the parameter can be rolled
+	/// back.
+	pub fn inner(&self) -> ParameterRef<'_> {
+		ParameterRef(self.inner.lock())
+	}
 
-		Self {
-			inner: Arc::new(Mutex::new(inner.clone())),
+	/// Write access to the parameter values, which marks it as dirty.
+	pub fn inner_mut(&self) -> MappedMutexGuard<'_, Parameter> {
+		let mut guard = self.inner.lock();
+		if guard.backup.is_none() {
+			guard.backup = Some(guard.parameter.clone());
 		}
+		guard.version.touch();
+		MutexGuard::map(guard, |v| &mut v.parameter)
+	}
+
+	pub fn version(&self) -> u64 {
+		self.inner.lock().version.get()
+	}
+
+	/// Returns `true` if the parameter was written to since the last
+	/// accepted or rejected step.
+	pub fn is_dirty(&self) -> bool {
+		self.inner.lock().version.is_dirty()
+	}
+
+	pub(crate) fn accept(&self) {
+		let mut guard = self.inner.lock();
+		guard.backup = None;
+		guard.version.accept();
+	}
+
+	/// Restores the value the parameter had at the last accepted step.
+	pub(crate) fn reject(&self) {
+		let mut guard = self.inner.lock();
+		if let Some(backup) = guard.backup.take() {
+			guard.parameter = backup;
+		}
+		guard.version.reject();
+	}
+
+	pub fn deep_copy(&self) -> PyParameter {
+		Self::new(self.inner().clone())
+	}
+}
+
+/// Read-only guard returned by [`PyParameter::inner`].
+pub struct ParameterRef<'a>(MutexGuard<'a, Versioned>);
+
+impl Deref for ParameterRef<'_> {
+	type Target = Parameter;
+
+	fn deref(&self) -> &Parameter {
+		&self.0.parameter
 	}
 }
 
@@ -151,9 +224,7 @@ impl PyParameter {
 
 		let values: Vec<f64> = extract(values)?;
 		let parameter = Parameter::Real(values);
-		Ok(Self {
-			inner: Arc::new(Mutex::new(parameter)),
-		})
+		Ok(Self::new(parameter))
 	}
 
 	/// Create a new integer parameter.
@@ -168,9 +239,7 @@ impl PyParameter {
 
 		let values: Vec<i64> = extract(values)?;
 		let parameter = Parameter::Integer(values);
-		Ok(Self {
-			inner: Arc::new(Mutex::new(parameter)),
-		})
+		Ok(Self::new(parameter))
 	}
 
 	/// Create a new boolean parameter.
@@ -181,9 +250,7 @@ impl PyParameter {
 
 		let values: Vec<bool> = extract(values)?;
 		let parameter = Parameter::Boolean(values);
-		Ok(Self {
-			inner: Arc::new(Mutex::new(parameter)),
-		})
+		Ok(Self::new(parameter))
 	}
 
 	fn __len__(&self) -> Result<usize> {
@@ -204,8 +271,8 @@ impl PyParameter {
 	}
 
 	fn __setitem__(&self, i: usize, value: Bound<PyAny>) -> Result<()> {
-		let inner = &mut *self.inner();
-		inner.check_index(i)?;
+		self.inner().check_index(i)?;
+		let inner = &mut *self.inner_mut();
 
 		match inner {
 			Parameter::Real(p) => {
@@ -238,7 +305,7 @@ impl PyParameter {
 	}
 
 	fn __str__(&self) -> Result<String> {
-		Ok(format!("[{}]", self.inner()))
+		Ok(format!("[{}]", *self.inner()))
 	}
 
 	fn __richcmp__(
@@ -275,6 +342,22 @@ impl PyParameter {
 	fn is_boolean(&self) -> Result<bool> {
 		Ok(matches!(&*self.inner(), Parameter::Boolean(_)))
 	}
+
+	/// A number which changes every time the parameter is written to.
+	///
+	/// If a proposal is rejected the version rolls back together with the
+	/// values, so a result computed from the parameter can be cached
+	/// alongside its version and reused for as long as they match.
+	#[getter(version)]
+	fn py_version(&self) -> u64 {
+		self.version()
+	}
+
+	/// `True` if the parameter was changed by the current proposal.
+	#[getter(is_dirty)]
+	fn py_is_dirty(&self) -> bool {
+		self.is_dirty()
+	}
 }
 
 fn extract<T: for<'a> FromPyObjectBound<'a, 'a>>(
@@ -284,3 +367,39 @@ fn extract<T: for<'a> FromPyObjectBound<'a, 'a>>(
 		.map(|v| v.extract::<T>())
 		.collect::<PyResult<Vec<T>>>()?)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn reject_restores_backup() {
+		let param = PyParameter::new(Parameter::Real(vec![1.0, 2.0]));
+		let initial = param.version();
+
+		// Reading doesn't make the parameter dirty
+		assert_eq!(*param.inner(), Parameter::Real(vec![1.0, 2.0]));
+		assert!(!param.is_dirty());
+
+		if let Parameter::Real(values) = &mut *param.inner_mut() {
+			values[0] = 5.0;
+		}
+		assert!(param.is_dirty());
+		assert_ne!(param.version(), initial);
+		param.reject();
+		assert!(!param.is_dirty());
+		assert_eq!(param.version(), initial);
+		assert_eq!(*param.inner(), Parameter::Real(vec![1.0, 2.0]));
+
+		if let Parameter::Real(values) = &mut *param.inner_mut() {
+			values[1] = 3.0;
+		}
+		param.accept();
+		let accepted = param.version();
+		assert!(!param.is_dirty());
+		// Rejecting without a write keeps the accepted value
+		param.reject();
+		assert_eq!(param.version(), accepted);
+		assert_eq!(*param.inner(), Parameter::Real(vec![1.0, 3.0]));
+	}
+}
@@ -0,0 +1,202 @@
+//! Transactional model state.
+//!
+//! Every MCMC step is a transaction: operators edit trees and parameters, the
+//! likelihoods recalculate their caches, and then all of the edits are either
+//! accepted or rejected at once.  Anything which holds state which must follow
+//! the proposal outcome implements [`State`] and is registered in a
+//! [`Transaction`].
+use anyhow::Result;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use tracing::{instrument, trace};
+
+use crate::{likelihood::PyLikelihood, parameter::PyParameter, tree::PyTree};
+use util::{py_bail, py_call_method};
+
+pub trait State {
+	/// Keeps all of the edits made since the last `accept` or `reject`.
+	fn accept(&self) -> Result<()>;
+
+	/// Rolls back all of the edits made since the last `accept` or
+	/// `reject`.
+	fn reject(&self) -> Result<()>;
+}
+
+impl State for Py<PyTree> {
+	fn accept(&self) -> Result<()> {
+		self.get().inner().accept();
+		Ok(())
+	}
+
+	fn reject(&self) -> Result<()> {
+		self.get().inner().reject();
+		Ok(())
+	}
+}
+
+impl State for PyParameter {
+	fn accept(&self) -> Result<()> {
+		PyParameter::accept(self);
+		Ok(())
+	}
+
+	fn reject(&self) -> Result<()> {
+		PyParameter::reject(self);
+		Ok(())
+	}
+}
+
+impl State for Py<PyLikelihood> {
+	fn accept(&self) -> Result<()> {
+		self.get().inner().accept()
+	}
+
+	fn reject(&self) -> Result<()> {
+		self.get().inner().reject()
+	}
+}
+
+/// State defined in Python, such as caches of clock rates or substitution
+/// matrices.
+pub struct PyState {
+	/// INVARIANT: the type has `accept` and `reject` methods
+	inner: PyObject,
+}
+
+impl PyState {
+	fn id(&self) -> usize {
+		self.inner.as_ptr() as usize
+	}
+}
+
+impl<'py> FromPyObject<'py> for PyState {
+	fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+		let repr = obj.repr()?;
+		for method in ["accept", "reject"] {
+			if !obj.getattr(method).is_ok_and(|a| a.is_callable()) {
+				py_bail!(
+					PyTypeError,
+					"State objects must have `accept` and `reject` methods, which take no arguments.  Got {repr}",
+				);
+			}
+		}
+
+		let out = Self {
+			inner: obj.clone().unbind(),
+		};
+		trace!(%repr, id = out.id(), "new PyState");
+		Ok(out)
+	}
+}
+
+impl State for PyState {
+	#[instrument(level = "trace", skip_all, fields(id = self.id()))]
+	fn accept(&self) -> Result<()> {
+		Python::with_gil(|py| {
+			py_call_method!(py, self.inner, "accept")
+		})?;
+		Ok(())
+	}
+
+	#[instrument(level = "trace", skip_all, fields(id = self.id()))]
+	fn reject(&self) -> Result<()> {
+		Python::with_gil(|py| {
+			py_call_method!(py, self.inner, "reject")
+		})?;
+		Ok(())
+	}
+}
+
+/// A set of states which are accepted or rejected together.
+#[derive(Default)]
+pub struct Transaction {
+	states: Vec<Box<dyn State + Send + Sync>>,
+}
+
+impl Transaction {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn push<S: State + Send + Sync + 'static>(&mut self, state: S) {
+		self.states.push(Box::new(state));
+	}
+
+	pub fn accept(&self) -> Result<()> {
+		trace!("accept proposal");
+
+		for state in &self.states {
+			state.accept()?;
+		}
+		Ok(())
+	}
+
+	pub fn reject(&self) -> Result<()> {
+		trace!("reject proposal");
+
+		for state in &self.states {
+			state.reject()?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{parameter::Parameter, testing::with_python};
+
+	#[test]
+	fn transaction_covers_every_state() {
+		with_python(|env| {
+			let tree = env.tree(4, 1);
+			let param = env.real(1.0);
+			let recorders = [
+				env.helper("Recorder", ()),
+				env.helper("Recorder", ()),
+			];
+
+			let mut transaction = Transaction::new();
+			transaction.push(tree.clone_ref(env.py));
+			transaction.push(param.clone());
+			for recorder in &recorders {
+				transaction.push(recorder
+					.extract::<PyState>()
+					.unwrap());
+			}
+
+			let root = tree.get().inner().root().into();
+			let height = tree.get().inner().weight_of(root);
+			let edit = || {
+				tree.get()
+					.inner()
+					.update_weight(root, height + 1.0);
+				*param.inner_mut() = Parameter::Real(vec![2.0]);
+			};
+
+			edit();
+			transaction.reject().unwrap();
+			assert!(!tree.get().inner().is_dirty());
+			assert_eq!(tree.get().inner().weight_of(root), height);
+			assert_eq!(*param.inner(), Parameter::Real(vec![1.0]));
+
+			edit();
+			transaction.accept().unwrap();
+			assert!(!tree.get().inner().is_dirty());
+			assert_eq!(
+				tree.get().inner().weight_of(root),
+				height + 1.0
+			);
+			assert_eq!(*param.inner(), Parameter::Real(vec![2.0]));
+
+			for recorder in &recorders {
+				let calls: Vec<String> = recorder
+					.getattr("calls")
+					.unwrap()
+					.extract()
+					.unwrap();
+				assert_eq!(calls, ["reject", "accept"]);
+			}
+		})
+	}
+}
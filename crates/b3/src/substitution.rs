@@ -1,19 +1,38 @@
-use anyhow::{anyhow, Context, Result};
-use linalg::RowMatrix;
+use anyhow::{anyhow, bail, Context, Result};
+use linalg::{RowMatrix, Vector};
 use pyo3::prelude::*;
 use pyo3::{conversion::FromPyObject, exceptions::PyTypeError};
 use tracing::{instrument, trace};
 
+use crate::{parameter::PyParameter, transitions::Rates};
 use util::{py_bail, py_call_method};
 
 pub struct PySubstitution<const N: usize> {
 	inner: PyObject,
+	/// Number of states of the model.  It's smaller than `N` for discrete
+	/// traits, in which case the matrix is padded with zeros.
+	dimensions: usize,
+	/// Parameters the substitution matrix depends on.  If the model doesn't
+	/// declare them, the matrix is requested on every proposal.
+	parameters: Option<Vec<PyParameter>>,
 }
 
 pub type Substitution<const N: usize> = RowMatrix<f64, N, N>;
 
 impl<'py, const N: usize> FromPyObject<'py> for PySubstitution<N> {
 	fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+		let out = Self::padded(obj)?;
+		if out.dimensions != N {
+			py_bail!(PyTypeError, "Expected the substitution model to have {N} dimensions, got {}", out.dimensions);
+		}
+		Ok(out)
+	}
+}
+
+impl<const N: usize> PySubstitution<N> {
+	/// Extracts a substitution model with at most `N` dimensions.  The
+	/// padding states have no transitions to or from them.
+	pub fn padded(obj: &Bound<PyAny>) -> PyResult<Self> {
 		let repr = obj.repr()?;
 		if !obj.getattr("get_matrix")?.is_callable() {
 			py_bail!(PyTypeError, "Substitution model objects must have an `get_matrix` method which returns a substitution matrix.  Instead got {repr}");
@@ -21,29 +40,88 @@ impl<'py, const N: usize> FromPyObject<'py> for PySubstitution<N> {
 
 		let dimensions =
 			obj.getattr("dimensions")?.extract::<usize>()?;
-		if dimensions != N {
-			py_bail!(PyTypeError, "Expected the substitution model to have {N} dimensions, got {dimensions}");
+		if dimensions == 0 || dimensions > N {
+			py_bail!(PyTypeError, "Expected the substitution model to have at most {N} dimensions, got {dimensions}");
 		}
 
+		let parameters = if obj.hasattr("parameters")? {
+			Some(obj.getattr("parameters")?.extract()?)
+		} else {
+			None
+		};
+
 		let out = Self {
 			inner: obj.clone().unbind(),
+			dimensions,
+			parameters,
 		};
 		trace!(%repr, id = out.id(), "new PySubstitution");
 		Ok(out)
 	}
-}
 
-impl<const N: usize> PySubstitution<N> {
+	/// Number of states of the model, without the padding.
+	pub fn dimensions(&self) -> usize {
+		self.dimensions
+	}
+
 	fn id(&self) -> usize {
 		self.inner.as_ptr() as usize
 	}
 
+	/// Versions of the declared parameters, which identify the matrix
+	/// the model returns.  `None` if the model doesn't declare its
+	/// parameters, in which case the matrix has to be requested anew.
+	pub fn versions(&self) -> Option<Vec<u64>> {
+		self.parameters
+			.as_ref()
+			.map(|p| p.iter().map(|p| p.version()).collect())
+	}
+
+	/// The substitution matrix together with the `frequencies` of the model,
+	/// if it has them.  Frequencies allow a faster decomposition of
+	/// reversible models, so they are ignored if they can't be read.
+	pub fn get_rates(&self, py: Python) -> Result<Rates<N>> {
+		let substitution = self.get_matrix(py)?;
+
+		let frequencies = if self.dimensions == N {
+			self.inner
+				.getattr(py, "frequencies")
+				.and_then(|f| f.extract::<[f64; N]>(py))
+				.ok()
+				.map(Vector::from)
+		} else {
+			None
+		};
+
+		Ok(Rates {
+			substitution,
+			frequencies,
+		})
+	}
+
 	#[instrument(skip_all, fields(id = self.id()))]
 	pub fn get_matrix(&self, py: Python) -> Result<Substitution<N>> {
 		let matrix = py_call_method!(py, self.inner, "get_matrix")?;
 
 		type Matrix<const N: usize> = [[f64; N]; N];
 
+		if self.dimensions < N {
+			let k = self.dimensions;
+			let rows = matrix.extract::<Vec<Vec<f64>>>(py)?;
+			if rows.len() != k || rows.iter().any(|r| r.len() != k)
+			{
+				bail!("Expected the substitution model to return a matrix {0}x{0}.", k);
+			}
+
+			let mut out = Substitution::<N>::from_element(0.0);
+			for (i, row) in rows.iter().enumerate() {
+				for (j, value) in row.iter().enumerate() {
+					out[(i, j)] = *value;
+				}
+			}
+			return Ok(out);
+		}
+
 		let matrix =
 			matrix.extract::<Matrix<N>>(py).with_context(|| {
 				anyhow!("Expected the substitution model to return a matrix {0}x{0}.", N)
@@ -0,0 +1,213 @@
+//! Helpers for tests which need Python objects: models, operators, and the
+//! classes of the extension module.
+use pyo3::{prelude::*, types::PyModule};
+
+use std::ffi::CStr;
+
+use crate::{likelihood::PyLikelihood, parameter::PyParameter, tree::PyTree};
+use data::seq::{python::PyDnaSeq, DnaSeq};
+use rng::PyRng;
+
+/// Python helpers used by the tests.
+const HELPERS: &CStr = cr#"
+class JC:
+    """Jukes-Cantor with the substitution rate in a parameter."""
+
+    dimensions = 4
+
+    def __init__(self, rate):
+        self.rate = rate
+        self.parameters = [rate]
+
+    def get_matrix(self):
+        r = self.rate[0]
+        return [[-3.0 * r if i == j else r for j in range(4)] for i in range(4)]
+
+
+class SetParameter:
+    """Writes the next one of `values` into the first dimension of
+    `parameter` and returns `proposal`."""
+
+    def __init__(self, parameter, values, proposal, weight=1.0):
+        self.parameter = parameter
+        self.values = iter(values)
+        self.proposal = proposal
+        self.weight = weight
+
+    def propose(self):
+        self.parameter[0] = next(self.values)
+        return self.proposal
+
+
+class Fixed:
+    """Returns `proposal` and counts the calls."""
+
+    def __init__(self, proposal, weight=1.0):
+        self.proposal = proposal
+        self.weight = weight
+        self.calls = 0
+
+    def propose(self):
+        self.calls += 1
+        return self.proposal
+
+
+class RandomWalk:
+    """Moves the first dimension of `parameter` by a uniform step drawn from
+    `rng` and records the values."""
+
+    def __init__(self, parameter, rng, proposal, weight=1.0):
+        self.parameter = parameter
+        self.rng = rng
+        self.proposal = proposal
+        self.weight = weight
+        self.values = []
+
+    def propose(self):
+        self.parameter[0] += self.rng.random_float() - 0.5
+        self.values.append(self.parameter[0])
+        return self.proposal
+
+
+def random_walks(b3, length):
+    """Factory for `MCMC.run_chains` which samples random walks.  The walk of
+    each chain is stored in `walks` under its index."""
+    walks = {}
+
+    def factory(index, rng):
+        parameter = b3.Parameter.Real(0.0)
+        walk = RandomWalk(parameter, rng, b3.Proposal.Hastings(0.0))
+        walks[index] = walk
+        return b3.MCMC(0, length, [], [parameter], [Flat()], [walk], [], [], rng)
+
+    return factory, walks
+
+
+class Flat:
+    def probability(self):
+        return 0.0
+
+
+class Recorder:
+    """State which records whether it was accepted or rejected."""
+
+    def __init__(self):
+        self.calls = []
+
+    def accept(self):
+        self.calls.append("accept")
+
+    def reject(self):
+        self.calls.append("reject")
+"#;
+
+/// Python objects shared by a test.
+pub(crate) struct Env<'py> {
+	pub py: Python<'py>,
+	/// The `_b3_rust_impl` module.
+	pub b3: Bound<'py, PyModule>,
+	/// [`HELPERS`].
+	pub helpers: Bound<'py, PyModule>,
+}
+
+/// Stack size of the test threads.  Constructing a likelihood needs more
+/// than the default two megabytes in debug builds, because the frames hold
+/// the matrices of every state count.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Runs `f` with the interpreter initialized.
+pub(crate) fn with_python<T: Send>(f: impl FnOnce(&Env) -> T + Send) -> T {
+	pyo3::prepare_freethreaded_python();
+	std::thread::scope(|scope| {
+		std::thread::Builder::new()
+			.stack_size(STACK_SIZE)
+			.spawn_scoped(scope, || {
+				Python::with_gil(|py| {
+					let env = Env {
+						py,
+						b3: crate::pymodule(py)
+							.unwrap(),
+						helpers: PyModule::from_code(
+							py,
+							HELPERS,
+							c"helpers.py",
+							c"helpers",
+						)
+						.unwrap(),
+					};
+					f(&env)
+				})
+			})
+			.unwrap()
+			.join()
+			.unwrap()
+	})
+}
+
+impl<'py> Env<'py> {
+	/// Calls `name` from [`HELPERS`].
+	pub fn helper(
+		&self,
+		name: &str,
+		args: impl pyo3::call::PyCallArgs<'py>,
+	) -> Bound<'py, PyAny> {
+		self.helpers.getattr(name).unwrap().call1(args).unwrap()
+	}
+
+	pub fn rng(&self, seed: u64) -> Py<PyRng> {
+		Py::new(self.py, PyRng::from_stream(seed, 0)).unwrap()
+	}
+
+	/// A random tree with the leaves `0`, `1`, ...
+	pub fn tree(&self, leaves: usize, seed: u64) -> Py<PyTree> {
+		let names: Vec<String> =
+			(0..leaves).map(|i| i.to_string()).collect();
+		self.b3.getattr("Tree")
+			.unwrap()
+			.call1((names, self.rng(seed)))
+			.unwrap()
+			.extract()
+			.unwrap()
+	}
+
+	pub fn real(&self, value: f64) -> PyParameter {
+		self.b3.getattr("Parameter")
+			.unwrap()
+			.call_method1("Real", (value,))
+			.unwrap()
+			.extract()
+			.unwrap()
+	}
+
+	/// `Proposal.Accept()`, `Proposal.Reject()` or `Proposal.Hastings(0)`
+	/// for `"Accept"`, `"Reject"` and `"Hastings"`.
+	pub fn proposal(&self, kind: &str) -> Bound<'py, PyAny> {
+		let class = self.b3.getattr("Proposal").unwrap();
+		match kind {
+			"Hastings" => class.call_method1(kind, (0.0,)),
+			_ => class.call_method0(kind),
+		}
+		.unwrap()
+	}
+
+	/// A nucleotide likelihood of `sequences` under Jukes-Cantor with the
+	/// rate `rate`.
+	pub fn likelihood(
+		&self,
+		sequences: &[&str],
+		rate: &PyParameter,
+		tree: &Py<PyTree>,
+	) -> Py<PyLikelihood> {
+		let sequences: Vec<PyDnaSeq> = sequences
+			.iter()
+			.map(|s| DnaSeq::try_from(*s).unwrap().into())
+			.collect();
+		let model = self.helper("JC", (rate.clone(),));
+		self.b3.getattr("Likelihood")
+			.unwrap()
+			.call1((sequences, model, tree.clone_ref(self.py)))
+			.unwrap()
+			.extract()
+			.unwrap()
+	}
+}
@@ -1,49 +1,284 @@
 use crate::substitution::Substitution;
-use linalg::RowMatrix;
+use linalg::{ComplexEigen, Error, RowMatrix, Vector};
 use skvec::SkVec;
 
 use crate::tree::Tree;
 
-pub struct Transitions<const N: usize> {
-	current: Substitution<N>,
+/// Number of recently used decompositions which are kept.  Rejected proposals
+/// and operators which toggle a parameter back and forth often return to a
+/// rate matrix which has been decomposed before.
+const CACHE_SIZE: usize = 8;
+
+/// Largest difference between `pi_i * q_ij` and `pi_j * q_ji`, relative to the
+/// largest rate, for which a model is considered reversible.
+#[cfg(feature = "lapack")]
+const REVERSIBLE_TOLERANCE: f64 = 1e-12;
+
+/// Largest difference between the rate matrix and the one rebuilt from its
+/// eigen-decomposition, relative to the largest rate.  Nearly defective
+/// matrices have ill-conditioned eigenvectors, which fail this check, and
+/// their exponentials are calculated directly instead.
+#[cfg_attr(not(feature = "lapack"), allow(dead_code))]
+const RECONSTRUCTION_TOLERANCE: f64 = 1e-9;
 
-	p: RowMatrix<f64, N, N>,
-	diag: RowMatrix<f64, N, N>,
-	inv_p: RowMatrix<f64, N, N>,
+/// A substitution matrix together with its equilibrium frequencies, if the
+/// model declares them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rates<const N: usize> {
+	pub substitution: Substitution<N>,
+	pub frequencies: Option<Vector<f64, N>>,
+}
+
+pub struct Transitions<const N: usize> {
+	current: Decomposition<N>,
+	/// Decomposition of the last accepted substitution matrix, `current` is
+	/// reset to it on rejection.
+	accepted: Decomposition<N>,
+	/// Recently used decompositions, the most recent one first.
+	cache: Vec<Decomposition<N>>,
 
 	transitions: SkVec<RowMatrix<f64, N, N>>,
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+enum Method<const N: usize> {
+	/// `Q = inv_p * diag(values) * p`, so the transition matrix for the
+	/// distance `t` is `inv_p * diag(exp(values * t)) * p`.
+	#[cfg_attr(not(feature = "lapack"), allow(dead_code))]
+	Eigen {
+		values: Vector<f64, N>,
+		p: RowMatrix<f64, N, N>,
+		inv_p: RowMatrix<f64, N, N>,
+	},
+	/// Diagonalization with complex eigenvalues, from non-reversible
+	/// models.
+	#[cfg(feature = "lapack")]
+	Complex(ComplexEigen<N>),
+	/// The matrix isn't diagonalizable, so the exponential is calculated
+	/// for every edge.
+	#[default]
+	Exponential,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Decomposition<const N: usize> {
+	rates: Rates<N>,
+	method: Method<N>,
+}
+
+impl<const N: usize> Decomposition<N> {
+	#[cfg(feature = "lapack")]
+	fn new(rates: Rates<N>) -> Result<Self, Error> {
+		let method = match Self::symmetric(&rates)? {
+			Some(method) => Some(method),
+			None => Self::eigen(&rates.substitution)?,
+		};
+		let method = method.unwrap_or(Method::Exponential);
+
+		Ok(Self { rates, method })
+	}
+
+	#[cfg(not(feature = "lapack"))]
+	fn new(rates: Rates<N>) -> Result<Self, Error> {
+		Ok(Self {
+			rates,
+			method: Method::Exponential,
+		})
+	}
+
+	/// A reversible matrix `Q` with the frequencies `pi` is similar to the
+	/// symmetric matrix `S = D^(1/2) Q D^(-1/2)`, where `D = diag(pi)`.  The
+	/// eigenvectors of `S` are orthogonal, so no inverse is needed.
+	#[cfg(feature = "lapack")]
+	fn symmetric(rates: &Rates<N>) -> Result<Option<Method<N>>, Error> {
+		let Some(frequencies) = rates.frequencies else {
+			return Ok(None);
+		};
+		if frequencies.as_array().iter().any(|f| *f <= 0.0) {
+			return Ok(None);
+		}
+
+		let q = &rates.substitution;
+		let scale = scale(q);
+		for i in 0..N {
+			for j in (i + 1)..N {
+				let forward = frequencies[i] * q[(i, j)];
+				let backward = frequencies[j] * q[(j, i)];
+				if (forward - backward).abs()
+					> REVERSIBLE_TOLERANCE * scale
+				{
+					return Ok(None);
+				}
+			}
+		}
+
+		let roots = frequencies.map(f64::sqrt);
+		let mut s = RowMatrix::<f64, N, N>::default();
+		for i in 0..N {
+			for j in 0..N {
+				s[(i, j)] = roots[i] * q[(i, j)] / roots[j];
+			}
+		}
+
+		// `S = E^T diag(values) E`, where the rows of `E` are the
+		// eigenvectors
+		let (values, e) = s.symmetric_eigen()?;
+		let mut p = RowMatrix::default();
+		let mut inv_p = RowMatrix::default();
+		for i in 0..N {
+			for j in 0..N {
+				p[(i, j)] = e[(i, j)] * roots[j];
+				inv_p[(i, j)] = e[(j, i)] / roots[i];
+			}
+		}
+
+		Ok(Some(Method::Eigen { values, p, inv_p }))
+	}
+
+	/// General eigen-decomposition, if the eigenvectors are independent
+	/// and well-conditioned enough to rebuild the matrix.  Real eigenvalues
+	/// are handled without complex arithmetic.
+	#[cfg(feature = "lapack")]
+	fn eigen(
+		substitution: &Substitution<N>,
+	) -> Result<Option<Method<N>>, Error> {
+		if let Some((values, _, left)) = substitution.real_eigen()? {
+			// The rows of `p` have to be the left eigenvectors for
+			// `p Q = diag(values) p` to hold.
+			let p = left;
+			let inv_p = match p.try_inverse() {
+				Ok(inv_p) => inv_p,
+				Err(Error::Singular) => return Ok(None),
+				Err(e) => return Err(e),
+			};
+			let is_finite = (0..N).all(|i| {
+				inv_p[i].as_array()
+					.iter()
+					.all(|v| v.is_finite())
+			});
+			let rebuilt =
+				inv_p * RowMatrix::from_diagonal(values) * p;
+			let accurate = is_finite
+				&& is_accurate(substitution, &rebuilt);
+			return Ok(accurate.then_some(Method::Eigen {
+				values,
+				p,
+				inv_p,
+			}));
+		}
+
+		match substitution.try_complex_eigen() {
+			Ok(eigen) => Ok(is_usable(substitution, &eigen)
+				.then_some(Method::Complex(eigen))),
+			Err(Error::Singular) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	fn transition(
+		&self,
+		distance: f64,
+	) -> Result<RowMatrix<f64, N, N>, Error> {
+		match &self.method {
+			Method::Eigen { values, p, inv_p } => {
+				let diag = RowMatrix::from_diagonal(
+					values.map(|v| (v * distance).exp()),
+				);
+				Ok(*inv_p * diag * *p)
+			}
+			#[cfg(feature = "lapack")]
+			Method::Complex(eigen) => Ok(eigen.exp(distance)),
+			Method::Exponential => {
+				(self.rates.substitution * distance).try_expm()
+			}
+		}
+	}
+}
+
+/// Largest absolute rate.
+#[cfg_attr(not(feature = "lapack"), allow(dead_code))]
+fn scale<const N: usize>(q: &Substitution<N>) -> f64 {
+	(0..N).flat_map(|i| (0..N).map(move |j| q[(i, j)].abs()))
+		.fold(0.0, f64::max)
+}
+
+/// Whether `rebuilt` matches `q` up to [`RECONSTRUCTION_TOLERANCE`].
+#[cfg_attr(not(feature = "lapack"), allow(dead_code))]
+fn is_accurate<const N: usize>(
+	q: &Substitution<N>,
+	rebuilt: &RowMatrix<f64, N, N>,
+) -> bool {
+	let tolerance = RECONSTRUCTION_TOLERANCE * scale(q);
+	(0..N).all(|i| {
+		(0..N).all(|j| (q[(i, j)] - rebuilt[(i, j)]).abs() <= tolerance)
+	})
+}
+
+/// Whether the complex eigenvectors and their inverse are finite and
+/// rebuild `q` accurately.
+#[cfg_attr(not(feature = "lapack"), allow(dead_code))]
+fn is_usable<const N: usize>(
+	q: &Substitution<N>,
+	eigen: &ComplexEigen<N>,
+) -> bool {
+	let (right, left) = (eigen.right(), eigen.left());
+	let is_finite = (0..N).all(|i| {
+		(0..N).all(|j| {
+			right[(i, j)].is_finite() && left[(i, j)].is_finite()
+		})
+	});
+	is_finite && is_accurate(q, &eigen.reconstruct())
+}
+
 impl<const N: usize> Transitions<N> {
 	pub fn new(length: usize) -> Self {
 		let transitions = SkVec::repeat(RowMatrix::default(), length);
 
 		Self {
 			current: Default::default(),
-
-			p: Default::default(),
-			diag: Default::default(),
-			inv_p: Default::default(),
+			accepted: Default::default(),
+			cache: Vec::with_capacity(CACHE_SIZE),
 
 			transitions,
 		}
 	}
 
+	/// Decomposition of `rates`, reused from the cache if possible.
+	fn decompose(
+		&mut self,
+		rates: Rates<N>,
+	) -> Result<Decomposition<N>, Error> {
+		let decomposition = match self
+			.cache
+			.iter()
+			.position(|d| d.rates == rates)
+		{
+			Some(i) => self.cache.remove(i),
+			None => Decomposition::new(rates)?,
+		};
+
+		self.cache.insert(0, decomposition);
+		self.cache.truncate(CACHE_SIZE);
+		Ok(decomposition)
+	}
+
 	/// Returns `true` if a full update is needed.
+	///
+	/// `rates` is `None` if the substitution model reports that its matrix
+	/// hasn't changed since the last accepted step.
+	///
+	/// Fails if the rate matrix can't be decomposed or exponentiated, for
+	/// example because it has non-finite elements.  The transitions are left
+	/// partially updated, so the proposal has to be rejected.
 	pub fn update(
 		&mut self,
-		substitution: Substitution<N>,
+		rates: Option<Rates<N>>,
 		tree: &Tree,
-	) -> bool {
-		let full_update = substitution != self.current;
-		if full_update {
-			self.current = substitution;
-
-			self.diag = RowMatrix::from_diagonal(
-				substitution.eigenvalues(),
-			);
-			self.p = substitution.eigenvectors();
-			self.inv_p = self.p.inverse();
+	) -> Result<bool, Error> {
+		let full_update =
+			rates.is_some_and(|r| r != self.current.rates);
+		if let Some(rates) = rates.filter(|_| full_update) {
+			self.current = self.decompose(rates)?;
 		}
 
 		let edges: Vec<usize> = if full_update {
@@ -57,31 +292,38 @@ impl<const N: usize> Transitions<N> {
 			.map(|e| tree.edge_distance(e))
 			.collect();
 
-		self.update_edges(&edges, &distances);
+		self.update_edges(&edges, &distances)?;
 
-		full_update
+		Ok(full_update)
 	}
 
-	fn update_edges(&mut self, edges: &[usize], distances: &[f64]) {
+	fn update_edges(
+		&mut self,
+		edges: &[usize],
+		distances: &[f64],
+	) -> Result<(), Error> {
 		for (edge, distance) in edges.iter().zip(distances) {
-			let diag = self
-				.diag
-				.map_diagonal(|v| (v * distance).exp());
-
-			let transition = self.inv_p * diag * self.p;
-
+			let transition = self.current.transition(*distance)?;
 			self.transitions.set(*edge, transition);
 		}
+		Ok(())
 	}
 
 	pub fn accept(&mut self) {
+		self.accepted = self.current;
 		self.transitions.accept();
 	}
 
 	pub fn reject(&mut self) {
+		self.current = self.accepted;
 		self.transitions.reject();
 	}
 
+	/// The substitution matrix the transitions are calculated from.
+	pub fn substitution(&self) -> Substitution<N> {
+		self.current.rates.substitution
+	}
+
 	pub fn matrices(&self, edges: &[usize]) -> Vec<RowMatrix<f64, N, N>> {
 		let mut out = Vec::with_capacity(edges.len());
 
@@ -92,3 +334,203 @@ impl<const N: usize> Transitions<N> {
 		out
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use linalg::Complex64;
+	use std::f64::consts::PI;
+
+	fn rates<const N: usize>(
+		substitution: [[f64; N]; N],
+		frequencies: Option<[f64; N]>,
+	) -> Rates<N> {
+		Rates {
+			substitution: substitution.into(),
+			frequencies: frequencies.map(Into::into),
+		}
+	}
+
+	#[cfg(feature = "lapack")]
+	fn assert_close<const N: usize>(
+		a: RowMatrix<f64, N, N>,
+		b: RowMatrix<f64, N, N>,
+	) {
+		for i in 0..N {
+			for j in 0..N {
+				assert!((a[(i, j)] - b[(i, j)]).abs() < 1e-9);
+			}
+		}
+	}
+
+	#[test]
+	fn padded_states() {
+		// An asymmetric three-state matrix padded to four states
+		let q = [[-1.0, 0.4, 0.6], [0.2, -0.5, 0.3], [0.9, 0.1, -1.0]];
+		let mut padded = [[0.0; 4]; 4];
+		for i in 0..3 {
+			padded[i][..3].copy_from_slice(&q[i]);
+		}
+
+		let expected = Decomposition::new(rates(q, None))
+			.unwrap()
+			.transition(0.7)
+			.unwrap();
+		let actual = Decomposition::new(rates(padded, None))
+			.unwrap()
+			.transition(0.7)
+			.unwrap();
+
+		for i in 0..4 {
+			for j in 0..4 {
+				let value = if i < 3 && j < 3 {
+					expected[(i, j)]
+				} else if i == j {
+					1.0
+				} else {
+					0.0
+				};
+				assert!((actual[(i, j)] - value).abs() < 1e-9);
+			}
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "lapack")]
+	fn methods_agree() {
+		// HKY with kappa = 2, which is reversible but not symmetric
+		let pi = [0.1, 0.2, 0.3, 0.4];
+		let k = 2.0;
+		let mut q = [
+			[0.0, pi[1], k * pi[2], pi[3]],
+			[pi[0], 0.0, pi[2], k * pi[3]],
+			[k * pi[0], pi[1], 0.0, pi[3]],
+			[pi[0], k * pi[1], pi[2], 0.0],
+		];
+		for (i, row) in q.iter_mut().enumerate() {
+			row[i] = -row.iter().sum::<f64>();
+		}
+
+		let symmetric = Decomposition::new(rates(q, Some(pi))).unwrap();
+		assert!(matches!(symmetric.method, Method::Eigen { .. }));
+		let general = Decomposition::new(rates(q, None)).unwrap();
+		assert!(matches!(general.method, Method::Eigen { .. }));
+		let exponential = Decomposition {
+			rates: rates(q, None),
+			method: Method::Exponential,
+		};
+
+		for distance in [0.01, 0.5, 3.0] {
+			let expected =
+				exponential.transition(distance).unwrap();
+			assert_close(
+				symmetric.transition(distance).unwrap(),
+				expected,
+			);
+			assert_close(
+				general.transition(distance).unwrap(),
+				expected,
+			);
+		}
+	}
+
+	#[test]
+	fn complex_eigenvalues() {
+		// A cyclic model, A -> C -> G -> A, has complex eigenvalues
+		let q = [[-1.0, 1.0, 0.0], [0.0, -1.0, 1.0], [1.0, 0.0, -1.0]];
+		let decomposition = Decomposition::new(rates(q, None)).unwrap();
+		#[cfg(feature = "lapack")]
+		assert!(matches!(decomposition.method, Method::Complex(_)));
+		#[cfg(not(feature = "lapack"))]
+		assert!(matches!(decomposition.method, Method::Exponential));
+
+		let transition = decomposition.transition(0.8).unwrap();
+		let expected = (RowMatrix::from(q) * 0.8).expm();
+		for i in 0..3 {
+			for j in 0..3 {
+				let error = (transition[(i, j)]
+					- expected[(i, j)])
+					.abs();
+				assert!(error < 1e-12);
+			}
+		}
+		for i in 0..3 {
+			let sum: f64 = (0..3).map(|j| transition[(i, j)]).sum();
+			assert!((sum - 1.0).abs() < 1e-12);
+		}
+		// Forward along the cycle is more likely than backward
+		assert!(transition[(0, 1)] > transition[(0, 2)]);
+	}
+
+	#[test]
+	fn near_defective_rates() {
+		// The cycle A -> C -> G -> A has complex eigenvalues, and the
+		// chain T -> U -> A has the nearly equal eigenvalues -1 and
+		// -(1 + e), so the eigenvectors are close to parallel.
+		let e = 1e-10;
+		let q = [
+			[-1.0, 1.0, 0.0, 0.0, 0.0],
+			[0.0, -1.0, 1.0, 0.0, 0.0],
+			[1.0, 0.0, -1.0, 0.0, 0.0],
+			[0.0, 0.0, 0.0, -1.0, 1.0],
+			[1.0 + e, 0.0, 0.0, 0.0, -(1.0 + e)],
+		];
+		let decomposition = Decomposition::new(rates(q, None)).unwrap();
+
+		for distance in [0.1, 1.0, 5.0] {
+			let transition =
+				decomposition.transition(distance).unwrap();
+			let expected = (RowMatrix::from(q) * distance).expm();
+			for i in 0..5 {
+				for j in 0..5 {
+					let error = (transition[(i, j)]
+						- expected[(i, j)])
+						.abs();
+					assert!(error < 1e-8);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn inaccurate_complex_eigenvectors() {
+		// The cyclic model has the eigenvalues `w^k - 1` with the right
+		// eigenvectors `(1, w^k, w^2k)`, where `w` is a cube root of one
+		let q = RowMatrix::from([
+			[-1.0, 1.0, 0.0],
+			[0.0, -1.0, 1.0],
+			[1.0, 0.0, -1.0],
+		]);
+		let w = Complex64::from_polar(1.0, 2.0 * PI / 3.0);
+		let values = Vector::from([0, 1, 2].map(|k| w.powi(k) - 1.0));
+		let vector = |k: i32| [0, 1, 2].map(|i| w.powi(k * i));
+
+		let exact = ComplexEigen::new(
+			values,
+			RowMatrix::from([vector(0), vector(1), vector(2)]),
+		)
+		.unwrap();
+		assert!(is_usable(&q, &exact));
+
+		// Nearly parallel vectors, as an ill-conditioned decomposition
+		// would have
+		let mut close = vector(1);
+		for (c, v) in close.iter_mut().zip(vector(2)) {
+			*c += (v - *c) * 1e-12;
+		}
+		let broken = ComplexEigen::new(
+			values,
+			RowMatrix::from([vector(0), vector(1), close]),
+		)
+		.unwrap();
+		assert!(!is_usable(&q, &broken));
+	}
+
+	#[test]
+	fn non_finite_rates() {
+		let q = [[-1.0, 1.0], [f64::NAN, -1.0]];
+		let transition = Decomposition::new(rates(q, None))
+			.and_then(|d| d.transition(0.5));
+		assert_eq!(transition.unwrap_err(), Error::NotFinite);
+	}
+}
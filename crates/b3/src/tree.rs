@@ -16,12 +16,15 @@ use std::{
 };
 
 use io::newick::{
-	Node as NewickNode, NodeIndex as NewickNodeIndex, Tree as NewickTree,
+	Attributes, Node as NewickNode, NodeIndex as NewickNodeIndex,
+	Tree as NewickTree,
 };
 use rng::{PyRng, Rng};
 use skvec::SkVec;
 use util::py_bail;
 
+use crate::version::Version;
+
 const ROOT: usize = usize::MAX;
 
 #[derive(Debug)]
@@ -34,6 +37,8 @@ pub struct Tree {
 
 	updated_edges: Vec<usize>,
 	updated_nodes: Vec<Node>,
+
+	version: Version,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -221,6 +226,8 @@ impl Tree {
 
 			updated_edges: Vec::new(),
 			updated_nodes: Vec::new(),
+
+			version: Version::default(),
 		}
 	}
 
@@ -228,6 +235,7 @@ impl Tree {
 		self.children.accept();
 		self.parents.accept();
 		self.weights.accept();
+		self.version.accept();
 		self.clear_updated();
 	}
 
@@ -235,9 +243,22 @@ impl Tree {
 		self.children.reject();
 		self.parents.reject();
 		self.weights.reject();
+		self.version.reject();
 		self.clear_updated();
 	}
 
+	/// A number which changes on every edit of the tree and rolls back on
+	/// rejection.  See [`Version`].
+	pub fn version(&self) -> u64 {
+		self.version.get()
+	}
+
+	/// Returns `true` if the tree was edited since the last `accept` or
+	/// `reject`.
+	pub fn is_dirty(&self) -> bool {
+		self.version.is_dirty()
+	}
+
 	fn clear_updated(&mut self) {
 		self.updated_edges.clear();
 		self.updated_nodes.clear();
@@ -346,6 +367,7 @@ impl Tree {
 		self.parents.set(new_child.0, parent.0);
 
 		self.updated_edges.push(edge);
+		self.version.touch();
 
 		// `parent` is now the parent of `new_child`, so it'll
 		// be updated.  The operator must handle the old node
@@ -358,6 +380,7 @@ impl Tree {
 	pub fn update_weight(&mut self, node: Node, weight: f64) {
 		self.weights.set(node.0, weight);
 		self.updated_nodes.push(node);
+		self.version.touch();
 
 		if self.parent_of(node).is_some() {
 			self.updated_edges.push(self.edge_index(node));
@@ -372,6 +395,7 @@ impl Tree {
 	/// Doesn't overwrite the old root.
 	pub fn update_root(&mut self, node: Node) {
 		self.parents.set(node.0, ROOT);
+		self.version.touch();
 	}
 
 	/// Replaces `child` with `replacement` in respect to `child`'s parent.
@@ -470,6 +494,11 @@ impl Tree {
 		self.num_internals() + 1
 	}
 
+	/// Names of the leaves, indexed by leaf.
+	pub fn names(&self) -> &[String] {
+		&self.names
+	}
+
 	pub fn is_internal(&self, node: Node) -> bool {
 		node.0 >= self.num_leaves()
 	}
@@ -586,7 +615,17 @@ impl Tree {
 		(0..self.num_leaves()).map(Leaf)
 	}
 
-	pub fn to_newick(&self) -> String {
+	/// Serializes the tree into Newick.  If `label_internals` is set,
+	/// internal nodes are named by their indices, so that they can be
+	/// matched with the output of other loggers.
+	///
+	/// `annotations` are either empty or hold the metadata of every node,
+	/// indexed by node, which is written as `[&key=value,...]` comments.
+	pub fn to_newick(
+		&self,
+		label_internals: bool,
+		annotations: &[Attributes],
+	) -> String {
 		let mut tree = NewickTree::new();
 
 		use std::collections::HashMap;
@@ -603,14 +642,21 @@ impl Tree {
 
 			let name = if self.is_leaf(node) {
 				self.names[node.0].clone()
+			} else if label_internals {
+				node.0.to_string()
 			} else {
 				String::new()
 			};
 
+			let attributes = annotations
+				.get(node.0)
+				.cloned()
+				.unwrap_or_default();
+
 			let newick_node = tree.add_node(NewickNode::new(
 				name,
 				Some(distance),
-				String::new(),
+				attributes,
 			));
 
 			map.insert(node, newick_node);
@@ -637,7 +683,7 @@ impl Serialize for Tree {
 	where
 		S: serde::Serializer,
 	{
-		serializer.serialize_str(&self.to_newick())
+		serializer.serialize_str(&self.to_newick(false, &[]))
 	}
 }
 
@@ -820,6 +866,12 @@ impl PyTree {
 		self.inner().num_leaves()
 	}
 
+	/// Names of the leaves, indexed by leaf.
+	#[getter]
+	fn names(&self) -> Vec<String> {
+		self.inner().names().to_vec()
+	}
+
 	/// Returns `True` if `node` is internal.
 	fn is_internal(&self, node: Bound<PyAny>) -> Result<bool> {
 		let node = to_node(node)?;
@@ -945,8 +997,39 @@ impl PyTree {
 		self.inner().verify()
 	}
 
-	fn newick(&self) -> String {
-		self.inner().to_newick()
+	/// A number which changes on every edit of the tree.
+	///
+	/// It rolls back together with the tree when a proposal is rejected, so
+	/// values computed from the tree can be cached alongside the version.
+	#[getter]
+	fn version(&self) -> u64 {
+		self.inner().version()
+	}
+
+	/// `True` if the tree was edited by the current proposal.
+	#[getter]
+	fn is_dirty(&self) -> bool {
+		self.inner().is_dirty()
+	}
+
+	#[pyo3(signature = (label_internals = false, annotations = None))]
+	fn newick(
+		&self,
+		label_internals: bool,
+		annotations: Option<Vec<Attributes>>,
+	) -> Result<String> {
+		let annotations = annotations.unwrap_or_default();
+
+		let tree = self.inner();
+		ensure!(
+			annotations.is_empty()
+				|| annotations.len() == tree.num_nodes(),
+			"Expected annotations for {} nodes, got {}",
+			tree.num_nodes(),
+			annotations.len(),
+		);
+
+		Ok(tree.to_newick(label_internals, &annotations))
 	}
 }
 
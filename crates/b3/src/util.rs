@@ -7,7 +7,6 @@ use std::cmp::Ordering;
 
 use crate::likelihood::Row;
 use data::{seq::DnaSeq, DnaNucleotide};
-use linalg::Vector;
 
 fn compare_seqs(a: &[DnaNucleotide], b: &[DnaNucleotide]) -> Ordering {
 	for (a, b) in a.iter().zip(b.iter()) {
@@ -20,7 +19,24 @@ fn compare_seqs(a: &[DnaNucleotide], b: &[DnaNucleotide]) -> Ordering {
 	Ordering::Equal
 }
 
-pub fn dna_to_rows(seqs: &[DnaSeq]) -> Vec<Vec<Row<4>>> {
+/// Unique site patterns of an alignment.
+pub struct Patterns<const N: usize> {
+	/// Tip partials of each pattern, one row per leaf.
+	pub rows: Vec<Vec<Row<N>>>,
+	/// Number of sites with each pattern.
+	pub weights: Vec<f64>,
+	/// Index of the pattern of each site in the alignment.
+	pub sites: Vec<usize>,
+}
+
+/// Converts aligned sequences into tip partials, one vector of leaf rows per
+/// unique site pattern.
+///
+/// `N` must be either 4 or 5.  With 4 states gaps are treated as missing data,
+/// and with 5 they are a separate state in the last position.
+pub fn dna_to_patterns<const N: usize>(seqs: &[DnaSeq]) -> Patterns<N> {
+	assert!(N == 4 || N == 5, "DNA partials must have 4 or 5 states");
+
 	let seq_len = seqs[0].len();
 	let num_seq = seqs.len();
 
@@ -33,35 +49,108 @@ pub fn dna_to_rows(seqs: &[DnaSeq]) -> Vec<Vec<Row<4>>> {
 		}
 	}
 
-	// Deduplicate same rows
-	transposed.sort_by(|a, b| compare_seqs(a, b));
-	transposed.dedup_by(|a, b| compare_seqs(a, b) == Ordering::Equal);
+	// Group identical columns together, keeping track of the sites they
+	// came from
+	let mut order: Vec<usize> = (0..seq_len).collect();
+	order.sort_by(|a, b| compare_seqs(&transposed[*a], &transposed[*b]));
+
+	let mut rows: Vec<Vec<Row<N>>> = Vec::new();
+	let mut weights = Vec::new();
+	let mut sites = vec![0; seq_len];
+	for (i, &site) in order.iter().enumerate() {
+		let is_new = i == 0
+			|| compare_seqs(
+				&transposed[order[i - 1]],
+				&transposed[site],
+			) != Ordering::Equal;
+		if is_new {
+			rows.push(transposed[site]
+				.iter()
+				.map(|base| tip_partial(*base))
+				.collect());
+			weights.push(0.0);
+		}
+
+		*weights.last_mut().unwrap() += 1.0;
+		sites[site] = rows.len() - 1;
+	}
+
+	Patterns {
+		rows,
+		weights,
+		sites,
+	}
+}
 
-	let mut out = vec![
-		vec![Vector::default(); transposed[0].len()];
-		transposed.len()
-	];
+/// Partial likelihood of a leaf with the observed `base`.
+///
+/// The partial is an indicator of the set of states compatible with the
+/// observation, so ambiguity codes like `R` (purine) are `[1, 0, 1, 0]`.  See
+/// [`dna_to_patterns`] for how gaps are handled.
+pub fn tip_partial<const N: usize>(base: DnaNucleotide) -> Row<N> {
+	use DnaNucleotide::*;
 
-	// TODO: find a place for this
-	fn to_row(base: &DnaNucleotide) -> Vector<f64, 4> {
-		match base {
-			DnaNucleotide::Adenine => [1.0, 0.0, 0.0, 0.0],
-			DnaNucleotide::Cytosine => [0.0, 1.0, 0.0, 0.0],
-			DnaNucleotide::Guanine => [0.0, 0.0, 1.0, 0.0],
-			DnaNucleotide::Thymine => [0.0, 0.0, 0.0, 1.0],
+	let mut row = Row::<N>::default();
 
-			_ => [0.25, 0.25, 0.25, 0.25],
+	if base == Gap {
+		if N == 5 {
+			row[4] = 1.0;
+		} else {
+			row = Row::ones();
 		}
-		.into()
+		return row;
 	}
 
-	for i in 0..transposed.len() {
-		for j in 0..transposed[0].len() {
-			out[i][j] = to_row(&transposed[i][j])
+	for (i, state) in
+		[Adenine, Cytosine, Guanine, Thymine].iter().enumerate()
+	{
+		if base.includes(state) {
+			row[i] = 1.0;
 		}
 	}
 
-	out
+	row
+}
+
+/// Converts a discrete trait with one value per leaf into tip partials.
+///
+/// Each value is either one of `states`, several of them separated by `|` for
+/// an ambiguous observation, or `?` for a missing one.  States past the length
+/// of `states` are padding and are never observed.  The result has a single
+/// pattern.
+pub fn traits_to_patterns<const N: usize>(
+	traits: &[String],
+	states: &[String],
+) -> Result<Patterns<N>> {
+	if states.len() > N {
+		bail!("At most {N} states are supported, got {}", states.len());
+	}
+
+	let mut rows = Vec::with_capacity(traits.len());
+	for value in traits {
+		let mut row = Row::<N>::default();
+		if value == "?" {
+			for i in 0..states.len() {
+				row[i] = 1.0;
+			}
+		} else {
+			for option in value.split('|') {
+				let Some(i) =
+					states.iter().position(|s| s == option)
+				else {
+					bail!("Unknown trait state '{option}'");
+				};
+				row[i] = 1.0;
+			}
+		}
+		rows.push(row);
+	}
+
+	Ok(Patterns {
+		rows: vec![rows],
+		weights: vec![1.0],
+		sites: vec![0],
+	})
 }
 
 #[derive(Debug)]
@@ -135,3 +224,69 @@ pub fn slices_iter(key: Bound<PyAny>, length: usize) -> Result<SlicesIter> {
 		curr_index: start,
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ambiguous_partials() {
+		use DnaNucleotide::*;
+
+		assert_eq!(tip_partial::<4>(Cytosine), [0.0, 1.0, 0.0, 0.0]);
+		assert_eq!(tip_partial::<4>(Purine), [1.0, 0.0, 1.0, 0.0]);
+		assert_eq!(tip_partial::<4>(NotGuanine), [1.0, 1.0, 0.0, 1.0]);
+		assert_eq!(tip_partial::<4>(Any), [1.0; 4]);
+		assert_eq!(tip_partial::<4>(Gap), [1.0; 4]);
+
+		assert_eq!(
+			tip_partial::<5>(Pyrimidine),
+			[0.0, 1.0, 0.0, 1.0, 0.0]
+		);
+		assert_eq!(tip_partial::<5>(Any), [1.0, 1.0, 1.0, 1.0, 0.0]);
+		assert_eq!(tip_partial::<5>(Gap), [0.0, 0.0, 0.0, 0.0, 1.0]);
+	}
+
+	#[test]
+	fn patterns() {
+		let seqs: Vec<DnaSeq> = ["ACAGA", "ACTGA", "GCAGG"]
+			.iter()
+			.map(|s| DnaSeq::try_from(*s).unwrap())
+			.collect();
+		let patterns = dna_to_patterns::<4>(&seqs);
+
+		// AAG, CCC, AAG, ATA, GGG
+		assert_eq!(patterns.rows.len(), 4);
+		assert_eq!(patterns.weights.iter().sum::<f64>(), 5.0);
+		assert_eq!(patterns.sites[0], patterns.sites[4]);
+		assert_eq!(patterns.weights[patterns.sites[0]], 2.0);
+
+		for (site, &pattern) in patterns.sites.iter().enumerate() {
+			for (leaf, seq) in seqs.iter().enumerate() {
+				assert_eq!(
+					patterns.rows[pattern][leaf],
+					tip_partial::<4>(seq[site])
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn traits() {
+		let states: Vec<String> =
+			["Kyiv", "Lviv", "Odesa"].map(String::from).to_vec();
+		let traits: Vec<String> =
+			["Lviv", "?", "Kyiv|Odesa"].map(String::from).to_vec();
+		let patterns =
+			traits_to_patterns::<4>(&traits, &states).unwrap();
+
+		assert_eq!(patterns.weights, [1.0]);
+		assert_eq!(patterns.sites, [0]);
+		assert_eq!(patterns.rows[0][0], [0.0, 1.0, 0.0, 0.0]);
+		assert_eq!(patterns.rows[0][1], [1.0, 1.0, 1.0, 0.0]);
+		assert_eq!(patterns.rows[0][2], [1.0, 0.0, 1.0, 0.0]);
+
+		let unknown = ["Kharkiv".to_string()];
+		assert!(traits_to_patterns::<4>(&unknown, &states).is_err());
+	}
+}
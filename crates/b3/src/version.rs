@@ -0,0 +1,68 @@
+/// Mutation counter for a piece of MCMC state.
+///
+/// Every mutation gets a fresh version number, so consumers can cache a value
+/// computed from the state together with the version it was computed at.  On
+/// rejection the version rolls back to the last accepted one, while the next
+/// mutation still gets a number which has never been seen before.  This way a
+/// cache made during a rejected proposal can never be mistaken for a valid one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Version {
+	current: u64,
+	accepted: u64,
+	last: u64,
+}
+
+impl Version {
+	/// Records a mutation.
+	pub fn touch(&mut self) {
+		self.last += 1;
+		self.current = self.last;
+	}
+
+	pub fn get(&self) -> u64 {
+		self.current
+	}
+
+	/// Returns `true` if the state was mutated since the last `accept` or
+	/// `reject`.
+	pub fn is_dirty(&self) -> bool {
+		self.current != self.accepted
+	}
+
+	pub fn accept(&mut self) {
+		self.accepted = self.current;
+	}
+
+	pub fn reject(&mut self) {
+		self.current = self.accepted;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn accept_and_reject() {
+		let mut version = Version::default();
+		assert!(!version.is_dirty());
+
+		version.touch();
+		let first = version.get();
+		assert!(version.is_dirty());
+		version.accept();
+		assert!(!version.is_dirty());
+		assert_eq!(version.get(), first);
+
+		version.touch();
+		let rejected = version.get();
+		version.reject();
+		assert!(!version.is_dirty());
+		assert_eq!(version.get(), first);
+
+		// Rejected numbers are never handed out again
+		version.touch();
+		assert_ne!(version.get(), rejected);
+		assert_ne!(version.get(), first);
+	}
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, ensure, Result};
+
+use super::{numbered_lines, Blocks};
+use crate::fasta::Record;
+use data::seq::Character;
+
+/// Parses a Clustal `.aln` alignment.  Conservation lines and the optional
+/// residue counts at the ends of lines are skipped.
+pub fn parse<C: Character>(text: &str) -> Result<Vec<Record<C>>> {
+	let mut lines = numbered_lines(text);
+	let (line, header) = lines
+		.next()
+		.ok_or_else(|| anyhow!("The Clustal file is empty"))?;
+	ensure!(
+		["CLUSTAL", "MUSCLE", "PROBCONS"]
+			.iter()
+			.any(|prefix| header.starts_with(prefix)),
+		"Expected a Clustal header at line {line}"
+	);
+
+	let mut blocks = Blocks::new();
+	for (line, text) in lines {
+		// Conservation lines are indented under the sequences
+		if text.starts_with(char::is_whitespace) {
+			continue;
+		}
+
+		let mut words = text.split_whitespace();
+		let (Some(name), Some(chunk)) = (words.next(), words.next())
+		else {
+			return Err(anyhow!(
+				"Expected a sequence name and characters at line {line}"
+			));
+		};
+		if let Some(count) = words.next() {
+			ensure!(
+				count.parse::<usize>().is_ok()
+					&& words.next().is_none(),
+				"Unexpected data after the sequence at line {line}"
+			);
+		}
+		blocks.push(name, chunk, line)?;
+	}
+
+	blocks.finish()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::DnaNucleotide;
+
+	#[test]
+	fn blocks() {
+		let text = "CLUSTAL W (1.83) multiple sequence alignment
+
+seq1    ACGT-- 4
+seq2    acgtaa 6
+        ****
+
+seq1    TTTT 8
+seq2    CCCC 10
+        ....
+";
+		let records = parse::<DnaNucleotide>(text).unwrap();
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].description(), "seq1");
+		assert_eq!(records[0].sequence().to_string(), "ACGT--TTTT");
+		assert_eq!(records[1].sequence().to_string(), "ACGTAACCCC");
+
+		let err = parse::<DnaNucleotide>(
+			"CLUSTAL\n\nseq1 ACGT\nseq2 ACGT extra\n",
+		)
+		.unwrap_err();
+		assert!(err.to_string().contains("line 4"), "{err}");
+		assert!(parse::<DnaNucleotide>("CLUSTAL\n\na ACGT\nb AC\n")
+			.is_err());
+		assert!(parse::<DnaNucleotide>(">a\nACGT\n").is_err());
+	}
+}
@@ -0,0 +1,242 @@
+use anyhow::{anyhow, bail, Context, Result};
+
+use std::{
+	collections::HashMap,
+	fmt::{self, Display},
+	io::{Read, Write},
+	str::FromStr,
+};
+
+use crate::{
+	decompress::Decoder,
+	fasta::{FastaReader, FastaWriter, Record},
+};
+use data::seq::{Character, Seq};
+
+pub mod clustal;
+pub mod phylip;
+#[cfg(feature = "python")]
+pub(crate) mod python;
+pub mod stockholm;
+
+/// Multiple sequence alignment formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	Fasta,
+	/// Relaxed or strict PHYLIP, either sequential or interleaved.
+	Phylip,
+	/// Clustal `.aln` files.
+	Clustal,
+	Stockholm,
+}
+
+impl Format {
+	/// Guesses the format from the start of `text`.  Returns `None` if it
+	/// doesn't look like any of them.
+	pub fn detect(text: &str) -> Option<Format> {
+		let start = text.trim_start();
+		let first = start.lines().next()?;
+
+		if start.starts_with('>') {
+			Some(Format::Fasta)
+		} else if first.starts_with("# STOCKHOLM") {
+			Some(Format::Stockholm)
+		} else if ["CLUSTAL", "MUSCLE", "PROBCONS"]
+			.iter()
+			.any(|prefix| first.starts_with(prefix))
+		{
+			Some(Format::Clustal)
+		} else if phylip::header(first).is_some() {
+			Some(Format::Phylip)
+		} else {
+			None
+		}
+	}
+}
+
+impl Display for Format {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Format::Fasta => "fasta",
+			Format::Phylip => "phylip",
+			Format::Clustal => "clustal",
+			Format::Stockholm => "stockholm",
+		})
+	}
+}
+
+impl FromStr for Format {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Format> {
+		Ok(match s.to_ascii_lowercase().as_str() {
+			"fasta" | "fa" | "fas" => Format::Fasta,
+			"phylip" | "phy" => Format::Phylip,
+			"clustal" | "aln" => Format::Clustal,
+			"stockholm" | "sto" => Format::Stockholm,
+			_ => bail!("Unknown alignment format '{s}'"),
+		})
+	}
+}
+
+/// Reads an alignment in `format`, or detects it if `format` is `None`.
+/// Gzipped input is decompressed.
+pub fn read<C: Character, R: Read>(
+	reader: R,
+	format: Option<Format>,
+) -> Result<Vec<Record<C>>> {
+	let mut text = String::new();
+	Decoder::new(reader).read_to_string(&mut text)?;
+	let format = match format {
+		Some(format) => format,
+		None => Format::detect(&text)
+			.ok_or_else(|| anyhow!("Unknown alignment format"))?,
+	};
+	parse(&text, format)
+}
+
+pub fn parse<C: Character>(
+	text: &str,
+	format: Format,
+) -> Result<Vec<Record<C>>> {
+	match format {
+		Format::Fasta => FastaReader::new(text.as_bytes()).collect(),
+		Format::Phylip => phylip::parse(text),
+		Format::Clustal => clustal::parse(text),
+		Format::Stockholm => stockholm::parse(text),
+	}
+}
+
+/// Writes `records` in FASTA or sequential relaxed PHYLIP.  The other formats
+/// can only be read.
+pub fn write<C: Character, W: Write>(
+	writer: W,
+	records: &[Record<C>],
+	format: Format,
+) -> Result<()> {
+	match format {
+		Format::Fasta => {
+			let mut writer = FastaWriter::new(writer);
+			for record in records {
+				writer.write_record(record)?;
+			}
+			writer.flush()
+		}
+		Format::Phylip => {
+			phylip::write(writer, records, phylip::Names::Relaxed)
+		}
+		Format::Clustal | Format::Stockholm => {
+			bail!("Writing {format} alignments isn't supported")
+		}
+	}
+}
+
+/// Parses a chunk of aligned characters, skipping whitespace.  Lowercase
+/// characters are accepted and converted to uppercase.
+fn residues<C: Character>(chunk: &str, line: usize) -> Result<Seq<C>> {
+	let chunk: String = chunk
+		.chars()
+		.filter(|c| !c.is_whitespace())
+		.map(|c| c.to_ascii_uppercase())
+		.collect();
+	Seq::try_from(chunk.as_str())
+		.with_context(|| format!("Invalid sequence at line {line}"))
+}
+
+/// Non-empty lines of `text` with their numbers, starting from one.
+fn numbered_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+	text.lines()
+		.enumerate()
+		.map(|(i, line)| (i + 1, line))
+		.filter(|(_, line)| !line.trim().is_empty())
+}
+
+/// Joins the chunks of sequences which are split into blocks, keeping the
+/// order of first appearance.  Used by Clustal and Stockholm.
+struct Blocks<C: Character> {
+	records: Vec<(String, Seq<C>)>,
+	/// Index of each name in `records`.
+	rows: HashMap<String, usize>,
+}
+
+impl<C: Character> Blocks<C> {
+	fn new() -> Self {
+		Blocks {
+			records: Vec::new(),
+			rows: HashMap::new(),
+		}
+	}
+
+	fn push(&mut self, name: &str, chunk: &str, line: usize) -> Result<()> {
+		let seq = residues(chunk, line)?;
+		match self.rows.get(name) {
+			Some(index) => self.records[*index].1.append(seq),
+			None => {
+				self.rows.insert(
+					name.to_owned(),
+					self.records.len(),
+				);
+				self.records.push((name.to_owned(), seq));
+			}
+		}
+		Ok(())
+	}
+
+	fn finish(self) -> Result<Vec<Record<C>>> {
+		if let Some((first, rest)) = self.records.split_first() {
+			for (name, seq) in rest {
+				if seq.len() != first.1.len() {
+					bail!(
+						"Sequence '{name}' has {} characters, but '{}' has {}",
+						seq.len(),
+						first.0,
+						first.1.len(),
+					);
+				}
+			}
+		}
+
+		Ok(self.records
+			.into_iter()
+			.map(|(name, seq)| Record::new(&name, seq))
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::DnaNucleotide;
+
+	#[test]
+	fn detection() {
+		assert_eq!(Format::detect("\n>a\nACGT\n"), Some(Format::Fasta));
+		assert_eq!(
+			Format::detect("# STOCKHOLM 1.0\n"),
+			Some(Format::Stockholm)
+		);
+		assert_eq!(
+			Format::detect(
+				"CLUSTAL W (1.83) multiple sequence alignment"
+			),
+			Some(Format::Clustal)
+		);
+		assert_eq!(
+			Format::detect(" 2 4\na ACGT\n"),
+			Some(Format::Phylip)
+		);
+		assert_eq!(Format::detect("#NEXUS\n"), None);
+		assert_eq!(Format::detect(""), None);
+
+		let text = "2 4\na ACGT\nb AC-T\n";
+		let records: Vec<Record<DnaNucleotide>> =
+			read(text.as_bytes(), None).unwrap();
+		let mut out = Vec::new();
+		write(&mut out, &records, Format::Fasta).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			">a\nACGT\n>b\nAC-T\n"
+		);
+		assert!(write(Vec::new(), &records, Format::Clustal).is_err());
+	}
+}
@@ -0,0 +1,286 @@
+use anyhow::{anyhow, bail, ensure, Result};
+
+use std::io::Write;
+
+use super::{numbered_lines, residues};
+use crate::fasta::Record;
+use data::seq::{Character, Seq};
+
+/// Width of the name column in strict PHYLIP.
+const STRICT_WIDTH: usize = 10;
+
+/// How sequence names are separated from the characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Names {
+	/// Names of any length which are followed by whitespace and can't
+	/// contain it.
+	Relaxed,
+	/// Names are the first ten characters of a line, padded with spaces.
+	Strict,
+}
+
+/// Parses the `ntax nchar` header line.
+pub(super) fn header(line: &str) -> Option<(usize, usize)> {
+	let mut words = line.split_whitespace();
+	let ntax = words.next()?.parse().ok()?;
+	let nchar = words.next()?.parse().ok()?;
+	Some((ntax, nchar))
+}
+
+/// Parses PHYLIP with either kind of names, trying relaxed ones first.  The
+/// error of the relaxed attempt is returned if neither works.
+pub fn parse<C: Character>(text: &str) -> Result<Vec<Record<C>>> {
+	parse_names(text, Names::Relaxed).or_else(|err| {
+		parse_names(text, Names::Strict).map_err(|_| err)
+	})
+}
+
+/// Parses sequential or interleaved PHYLIP.  Sequential layout is tried first,
+/// since the two are the same when every sequence fits on one line.
+pub fn parse_names<C: Character>(
+	text: &str,
+	names: Names,
+) -> Result<Vec<Record<C>>> {
+	let mut lines = numbered_lines(text);
+	let (line, first) = lines
+		.next()
+		.ok_or_else(|| anyhow!("The PHYLIP file is empty"))?;
+	let (ntax, nchar) = header(first).ok_or_else(|| {
+		anyhow!("Invalid PHYLIP header at line {line}, expected the numbers of taxa and characters")
+	})?;
+	let body: Vec<_> = lines.collect();
+
+	match sequential(&body, ntax, nchar, names) {
+		Ok(records) => Ok(records),
+		// Interleaved blocks have a line for every taxon
+		Err(_) if ntax > 0 && body.len() % ntax == 0 => {
+			interleaved(&body, ntax, nchar, names)
+		}
+		Err(err) => Err(err),
+	}
+}
+
+fn split_name(line: &str, names: Names) -> (&str, &str) {
+	match names {
+		Names::Relaxed => {
+			let line = line.trim_start();
+			line.split_once(char::is_whitespace)
+				.unwrap_or((line, ""))
+		}
+		Names::Strict => match line.char_indices().nth(STRICT_WIDTH) {
+			Some((i, _)) => (line[..i].trim(), &line[i..]),
+			None => (line.trim(), ""),
+		},
+	}
+}
+
+/// Parses the first line of a sequence, which starts with its name.
+fn named<C: Character>(
+	line: usize,
+	text: &str,
+	names: Names,
+) -> Result<(String, Seq<C>)> {
+	let (name, chunk) = split_name(text, names);
+	ensure!(!name.is_empty(), "Missing sequence name at line {line}");
+	Ok((name.to_owned(), residues(chunk, line)?))
+}
+
+fn sequential<C: Character>(
+	body: &[(usize, &str)],
+	ntax: usize,
+	nchar: usize,
+	names: Names,
+) -> Result<Vec<Record<C>>> {
+	let mut lines = body.iter();
+	let mut records = Vec::with_capacity(ntax);
+	for _ in 0..ntax {
+		let Some(&(line, text)) = lines.next() else {
+			bail!(
+				"Expected {ntax} sequences, found {}",
+				records.len()
+			);
+		};
+		let (name, mut seq) = named::<C>(line, text, names)?;
+		while seq.len() < nchar {
+			let Some(&(line, text)) = lines.next() else {
+				break;
+			};
+			seq.append(residues(text, line)?);
+		}
+		ensure!(
+			seq.len() == nchar,
+			"Sequence '{name}' at line {line} has {} characters instead of {nchar}",
+			seq.len(),
+		);
+		records.push(Record::new(&name, seq));
+	}
+
+	if let Some((line, _)) = lines.next() {
+		bail!("Unexpected data after the last sequence at line {line}");
+	}
+	Ok(records)
+}
+
+fn interleaved<C: Character>(
+	body: &[(usize, &str)],
+	ntax: usize,
+	nchar: usize,
+	names: Names,
+) -> Result<Vec<Record<C>>> {
+	let mut sequences: Vec<(String, Seq<C>)> = Vec::with_capacity(ntax);
+	for (i, &(line, text)) in body.iter().enumerate() {
+		if i < ntax {
+			sequences.push(named(line, text, names)?);
+		} else {
+			sequences[i % ntax].1.append(residues(text, line)?);
+		}
+	}
+
+	for (name, seq) in &sequences {
+		ensure!(
+			seq.len() == nchar,
+			"Sequence '{name}' has {} characters instead of {nchar}",
+			seq.len(),
+		);
+	}
+	Ok(sequences
+		.into_iter()
+		.map(|(name, seq)| Record::new(&name, seq))
+		.collect())
+}
+
+/// Writes `records` as sequential PHYLIP, one sequence per line.  Record
+/// descriptions are used as names: relaxed names can't contain whitespace and
+/// strict ones can't be longer than ten characters.
+pub fn write<C: Character, W: Write>(
+	mut writer: W,
+	records: &[Record<C>],
+	names: Names,
+) -> Result<()> {
+	let nchar = records.first().map_or(0, |r| r.sequence().len());
+	for record in records {
+		let name = record.description();
+		ensure!(
+			record.sequence().len() == nchar,
+			"Sequence '{name}' has {} characters, but the alignment has {nchar}",
+			record.sequence().len(),
+		);
+		ensure!(!name.trim().is_empty(), "PHYLIP names can't be empty");
+		match names {
+			Names::Relaxed => ensure!(
+				!name.contains(char::is_whitespace),
+				"Relaxed PHYLIP names can't contain whitespace: '{name}'"
+			),
+			Names::Strict => ensure!(
+				name.chars().count() <= STRICT_WIDTH,
+				"Strict PHYLIP names can't be longer than {STRICT_WIDTH} characters: '{name}'"
+			),
+		}
+	}
+
+	let width = match names {
+		Names::Relaxed => records
+			.iter()
+			.map(|r| r.description().chars().count() + 1)
+			.max()
+			.unwrap_or(0),
+		Names::Strict => STRICT_WIDTH,
+	};
+	writeln!(writer, "{} {nchar}", records.len())?;
+	for record in records {
+		let name = record.description();
+		writeln!(writer, "{name:<width$}{}", record.sequence())?;
+	}
+	writer.flush()?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::DnaNucleotide;
+
+	type Records = Vec<Record<DnaNucleotide>>;
+
+	fn pairs(records: &Records) -> Vec<(String, String)> {
+		records.iter()
+			.map(|r| {
+				(
+					r.description().to_owned(),
+					r.sequence().to_string(),
+				)
+			})
+			.collect()
+	}
+
+	fn expected(data: &[(&str, &str)]) -> Vec<(String, String)> {
+		data.iter()
+			.map(|(name, seq)| (name.to_string(), seq.to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn layouts() {
+		let sequential = "2 8\nalpha ACGT\nacgt\n\nbeta  AC-T ACGT\n";
+		let records: Records = parse(sequential).unwrap();
+		assert_eq!(
+			pairs(&records),
+			expected(&[
+				("alpha", "ACGTACGT"),
+				("beta", "AC-TACGT")
+			])
+		);
+
+		let interleaved =
+			" 2 8\nalpha ACGT\nbeta  AC-T\n\nAAAA\nCCCC\n";
+		let records: Records = parse(interleaved).unwrap();
+		assert_eq!(
+			pairs(&records),
+			expected(&[
+				("alpha", "ACGTAAAA"),
+				("beta", "AC-TCCCC")
+			])
+		);
+
+		let strict = "2 4\nSequence 1ACGT\nSeq2      AC-T\n";
+		let records: Records = parse(strict).unwrap();
+		assert_eq!(
+			pairs(&records),
+			expected(&[("Sequence 1", "ACGT"), ("Seq2", "AC-T")])
+		);
+
+		let err = parse::<DnaNucleotide>("2 4\na ACGT\nb ACGZ\n")
+			.unwrap_err();
+		assert!(format!("{err:#}").contains("line 3"), "{err:#}");
+		assert!(parse::<DnaNucleotide>("2 4\na ACGT\n").is_err());
+		assert!(parse::<DnaNucleotide>("a ACGT\n").is_err());
+	}
+
+	#[test]
+	fn writing() {
+		let records: Records =
+			parse("2 4\nSequence 1ACGT\nSeq2      AC-T\n").unwrap();
+
+		let mut out = Vec::new();
+		write(&mut out, &records, Names::Strict).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert_eq!(out, "2 4\nSequence 1ACGT\nSeq2      AC-T\n");
+
+		assert!(write(Vec::new(), &records, Names::Relaxed).is_err());
+		let records = vec![
+			Record::new("alpha", records[0].sequence().clone()),
+			Record::new("b", records[1].sequence().clone()),
+		];
+		let mut out = Vec::new();
+		write(&mut out, &records, Names::Relaxed).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert_eq!(out, "2 4\nalpha ACGT\nb     AC-T\n");
+
+		let records: Records = vec![
+			Record::new("a", Seq::try_from("ACGT").unwrap()),
+			Record::new("b", Seq::try_from("AC").unwrap()),
+		];
+		assert!(write(Vec::new(), &records, Names::Relaxed).is_err());
+	}
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use pyo3::prelude::*;
+
+use std::{fs::File, io::BufWriter};
+
+use super::{parse, read, write, Format};
+use crate::{
+	fasta::{python::PyFastaDnaRecord, Record},
+	python::open,
+};
+use data::DnaNucleotide;
+
+#[pyclass(name = "Alignment", module = "aspartik.io.alignment", frozen)]
+pub(crate) struct PyAlignment {
+	records: Vec<Record<DnaNucleotide>>,
+}
+
+fn format(name: Option<&str>) -> Result<Option<Format>> {
+	name.map(str::parse).transpose()
+}
+
+#[pymethods]
+impl PyAlignment {
+	/// Reads a DNA alignment from a path or a file-like object.  The format
+	/// is detected if it isn't passed.
+	#[new]
+	#[pyo3(signature = (source, format = None))]
+	fn new(source: &Bound<PyAny>, format: Option<&str>) -> Result<Self> {
+		let format = self::format(format)?;
+		Ok(PyAlignment {
+			records: read(open(source)?, format)?,
+		})
+	}
+
+	#[staticmethod]
+	#[pyo3(signature = (text, format = None))]
+	fn parse(text: &str, format: Option<&str>) -> Result<Self> {
+		let format = match self::format(format)? {
+			Some(format) => format,
+			None => Format::detect(text).ok_or_else(|| {
+				anyhow!("Unknown alignment format")
+			})?,
+		};
+		Ok(PyAlignment {
+			records: parse(text, format)?,
+		})
+	}
+
+	#[staticmethod]
+	fn from_records(records: Vec<PyRef<PyFastaDnaRecord>>) -> Self {
+		PyAlignment {
+			records: records.iter().map(|r| r.0.clone()).collect(),
+		}
+	}
+
+	#[getter]
+	fn records(&self) -> Vec<PyFastaDnaRecord> {
+		self.records.iter().cloned().map(Into::into).collect()
+	}
+
+	fn __len__(&self) -> usize {
+		self.records.len()
+	}
+
+	/// Writes the alignment as FASTA or relaxed PHYLIP.
+	#[pyo3(signature = (path, format = "fasta"))]
+	fn write(&self, path: &str, format: &str) -> Result<()> {
+		let format = format.parse()?;
+		let file = BufWriter::new(File::create(path)?);
+		write(file, &self.records, format)
+	}
+}
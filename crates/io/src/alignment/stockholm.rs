@@ -0,0 +1,78 @@
+use anyhow::{anyhow, ensure, Result};
+
+use super::{numbered_lines, Blocks};
+use crate::fasta::Record;
+use data::seq::Character;
+
+/// Parses the first alignment of a Stockholm file.  Markup lines (`#=GF`,
+/// `#=GS`, `#=GR`, `#=GC`) are skipped, and the '.' gaps of insert columns
+/// are read as regular '-' gaps.
+pub fn parse<C: Character>(text: &str) -> Result<Vec<Record<C>>> {
+	let mut lines = numbered_lines(text);
+	let (line, header) = lines
+		.next()
+		.ok_or_else(|| anyhow!("The Stockholm file is empty"))?;
+	ensure!(
+		header.starts_with("# STOCKHOLM"),
+		"Expected a '# STOCKHOLM' header at line {line}"
+	);
+
+	let mut blocks = Blocks::new();
+	let mut terminated = false;
+	for (line, text) in lines {
+		let text = text.trim();
+		if text == "//" {
+			terminated = true;
+			break;
+		}
+		if text.starts_with('#') {
+			continue;
+		}
+
+		let (name, chunk) = text
+			.split_once(char::is_whitespace)
+			.ok_or_else(|| {
+				anyhow!("Expected a sequence name and characters at line {line}")
+			})?;
+		blocks.push(name, &chunk.replace('.', "-"), line)?;
+	}
+	ensure!(terminated, "The alignment isn't terminated with '//'");
+
+	blocks.finish()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::DnaNucleotide;
+
+	#[test]
+	fn markup() {
+		let text = "# STOCKHOLM 1.0
+#=GF ID   example
+#=GS seq1/1-6 AC P00001
+
+seq1/1-6  ACG..T
+#=GR seq1/1-6 SS  <<..>>
+seq2/3-8  acgTAT
+#=GC SS_cons  <<..>>
+
+seq1/1-6  AA
+seq2/3-8  CC
+//
+";
+		let records = parse::<DnaNucleotide>(text).unwrap();
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].description(), "seq1/1-6");
+		assert_eq!(records[0].sequence().to_string(), "ACG--TAA");
+		assert_eq!(records[1].sequence().to_string(), "ACGTATCC");
+
+		assert!(parse::<DnaNucleotide>("# STOCKHOLM 1.0\na ACGT\n")
+			.is_err());
+		let err = parse::<DnaNucleotide>(
+			"# STOCKHOLM 1.0\n\na AC!T\n//\n",
+		)
+		.unwrap_err();
+		assert!(err.to_string().contains("line 3"), "{err}");
+	}
+}
@@ -0,0 +1,105 @@
+use flate2::bufread::MultiGzDecoder;
+
+use std::io::{self, BufRead, BufReader, Read};
+
+/// First two bytes of a gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A buffered reader which transparently decompresses gzip input.  The format
+/// is detected by the magic bytes at the start of the stream, so plain input
+/// is passed through as is.
+///
+/// BGZF files (produced by `bgzip`) are a series of concatenated gzip members,
+/// and all of them are read.
+pub enum Decoder<R: Read> {
+	Plain(BufReader<R>),
+	Gzip(BufReader<MultiGzDecoder<BufReader<R>>>),
+}
+
+impl<R: Read> Decoder<R> {
+	/// Peeks at the start of `reader` to detect its format.  If peeking
+	/// fails the input is treated as plain, so that the error is reported
+	/// by the first read instead.
+	pub fn new(reader: R) -> Self {
+		let mut reader = BufReader::new(reader);
+		let is_gzip = match reader.fill_buf() {
+			Ok(buf) => buf.starts_with(&GZIP_MAGIC),
+			Err(_) => false,
+		};
+
+		if is_gzip {
+			Decoder::Gzip(BufReader::new(MultiGzDecoder::new(
+				reader,
+			)))
+		} else {
+			Decoder::Plain(reader)
+		}
+	}
+
+	pub fn is_compressed(&self) -> bool {
+		matches!(self, Decoder::Gzip(_))
+	}
+}
+
+impl<R: Read> Read for Decoder<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			Decoder::Plain(reader) => reader.read(buf),
+			Decoder::Gzip(reader) => reader.read(buf),
+		}
+	}
+}
+
+impl<R: Read> BufRead for Decoder<R> {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		match self {
+			Decoder::Plain(reader) => reader.fill_buf(),
+			Decoder::Gzip(reader) => reader.fill_buf(),
+		}
+	}
+
+	fn consume(&mut self, amount: usize) {
+		match self {
+			Decoder::Plain(reader) => reader.consume(amount),
+			Decoder::Gzip(reader) => reader.consume(amount),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use flate2::{write::GzEncoder, Compression};
+
+	use std::io::Write;
+
+	use super::*;
+
+	fn gzip(data: &[u8]) -> Vec<u8> {
+		let mut encoder =
+			GzEncoder::new(Vec::new(), Compression::fast());
+		encoder.write_all(data).unwrap();
+		encoder.finish().unwrap()
+	}
+
+	fn read_all(input: &[u8]) -> (bool, String) {
+		let mut decoder = Decoder::new(input);
+		let mut out = String::new();
+		decoder.read_to_string(&mut out).unwrap();
+		(decoder.is_compressed(), out)
+	}
+
+	#[test]
+	fn formats() {
+		assert_eq!(read_all(b"plain"), (false, "plain".to_owned()));
+		assert_eq!(read_all(b""), (false, String::new()));
+		assert_eq!(read_all(&gzip(b"gzip")), (true, "gzip".to_owned()));
+
+		// BGZF-style concatenated members
+		let mut members = gzip(b"first ");
+		members.extend(gzip(b"second"));
+		assert_eq!(
+			read_all(&members),
+			(true, "first second".to_owned())
+		);
+	}
+}
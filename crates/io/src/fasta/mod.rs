@@ -1,11 +1,15 @@
 use anyhow::{anyhow, Context, Error, Result};
 
-use std::io::{BufRead, BufReader, Lines, Read};
+use std::io::{BufRead, Lines, Read};
 
+use crate::decompress::Decoder;
 use data::seq::{Character, Seq};
 
 #[cfg(feature = "python")]
 pub mod python;
+mod write;
+
+pub use write::{FastaWriter, Header};
 
 #[derive(Debug, Clone)]
 pub struct Record<C: Character> {
@@ -16,6 +20,14 @@ pub struct Record<C: Character> {
 }
 
 impl<C: Character> Record<C> {
+	/// Creates a record from a description without the leading '>'.
+	pub fn new(description: &str, seq: Seq<C>) -> Self {
+		Record {
+			description: format!(">{description}"),
+			seq,
+		}
+	}
+
 	/// The sequence header line, exactly as it appeared in the source.
 	pub fn raw_description(&self) -> &str {
 		&self.description
@@ -28,6 +40,11 @@ impl<C: Character> Record<C> {
 		&self.description[1..]
 	}
 
+	/// The first word of the description.
+	pub fn id(&self) -> &str {
+		self.description().split_whitespace().next().unwrap_or("")
+	}
+
 	pub fn sequence(&self) -> &Seq<C> {
 		&self.seq
 	}
@@ -45,18 +62,18 @@ impl<C: Character> From<Record<C>> for Seq<C> {
 
 pub struct FastaReader<C: Character, R: Read> {
 	current: Option<Record<C>>,
-	reader: Lines<BufReader<R>>,
+	reader: Lines<Decoder<R>>,
 	line: usize,
 }
 
 impl<C: Character, R: Read> FastaReader<C, R> {
-	/// Creates a FASTA parser from a byte reader.  The reader is wrapped in
-	/// `BufReader` internally, so there's no need for the caller to buffer
-	/// it manually.
+	/// Creates a FASTA parser from a byte reader.  The reader is buffered
+	/// internally, so there's no need for the caller to buffer it manually.
+	/// Gzip and bgzip input is decompressed on the fly.
 	pub fn new(reader: R) -> Self {
 		FastaReader {
 			current: None,
-			reader: BufReader::new(reader).lines(),
+			reader: Decoder::new(reader).lines(),
 			line: 0,
 		}
 	}
@@ -123,3 +140,32 @@ fn sequence_error<C: Character, R: Read>(fasta: &FastaReader<C, R>) -> Error {
 		anyhow!("Failed to parse sequence at line {}", fasta.line)
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use flate2::{write::GzEncoder, Compression};
+
+	use std::io::Write;
+
+	use super::*;
+	use data::DnaNucleotide;
+
+	#[test]
+	fn gzip() {
+		let mut encoder =
+			GzEncoder::new(Vec::new(), Compression::fast());
+		encoder.write_all(b">a one\nACGT\nAC\n; comment\n>b\nTT\n")
+			.unwrap();
+		let compressed = encoder.finish().unwrap();
+
+		let records: Vec<Record<DnaNucleotide>> =
+			FastaReader::new(compressed.as_slice())
+				.collect::<Result<_>>()
+				.unwrap();
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].id(), "a");
+		assert_eq!(records[0].sequence().to_string(), "ACGTAC");
+		assert_eq!(records[1].raw_description(), ">b");
+		assert_eq!(records[1].sequence().to_string(), "TT");
+	}
+}
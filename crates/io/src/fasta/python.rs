@@ -1,14 +1,18 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use parking_lot::Mutex;
 use pyo3::prelude::*;
 
-use std::fs::File;
+use std::{
+	fs::File,
+	io::{BufWriter, Cursor},
+};
 
-use super::{FastaReader, Record};
+use super::{FastaReader, FastaWriter, Header, Record};
+use crate::python::{open, Source};
 use data::{seq::python::PyDnaSeq, DnaNucleotide};
 
 #[pyclass(name = "FASTADNARecord", module = "aspartik.io.fasta", frozen)]
-pub struct PyFastaDnaRecord(Record<DnaNucleotide>);
+pub struct PyFastaDnaRecord(pub(crate) Record<DnaNucleotide>);
 
 impl From<Record<DnaNucleotide>> for PyFastaDnaRecord {
 	fn from(value: Record<DnaNucleotide>) -> Self {
@@ -18,6 +22,11 @@ impl From<Record<DnaNucleotide>> for PyFastaDnaRecord {
 
 #[pymethods]
 impl PyFastaDnaRecord {
+	#[new]
+	fn new(description: &str, sequence: &PyDnaSeq) -> Self {
+		Self(Record::new(description, sequence.0.clone()))
+	}
+
 	#[getter]
 	fn sequence(&self) -> PyDnaSeq {
 		// TODO: perhaps there's a way to avoid cloning.  Probably by
@@ -25,6 +34,11 @@ impl PyFastaDnaRecord {
 		self.0.sequence().to_owned().into()
 	}
 
+	#[getter]
+	fn id(&self) -> String {
+		self.0.id().to_owned()
+	}
+
 	#[getter]
 	fn raw_description(&self) -> String {
 		self.0.raw_description().to_owned()
@@ -38,18 +52,30 @@ impl PyFastaDnaRecord {
 
 #[pyclass(name = "FASTADNAReader", module = "aspartik.io.fasta", frozen)]
 pub struct PyFastaDnaReader {
-	inner: Mutex<FastaReader<DnaNucleotide, File>>,
+	inner: Mutex<FastaReader<DnaNucleotide, Source>>,
+}
+
+impl PyFastaDnaReader {
+	fn from_source(source: Source) -> Self {
+		Self {
+			inner: Mutex::new(FastaReader::new(source)),
+		}
+	}
 }
 
 #[pymethods]
 impl PyFastaDnaReader {
+	/// `source` is either a path or a file-like object.  Gzipped input is
+	/// decompressed automatically.
 	#[new]
-	fn new(path: &str) -> Result<Self> {
-		let file = File::open(path)?;
-		let reader = FastaReader::new(file);
-		Ok(Self {
-			inner: Mutex::new(reader),
-		})
+	fn new(source: &Bound<PyAny>) -> Result<Self> {
+		Ok(Self::from_source(open(source)?))
+	}
+
+	/// Reads records from the contents of a FASTA file.
+	#[staticmethod]
+	fn from_string(text: String) -> Self {
+		Self::from_source(Box::new(Cursor::new(text.into_bytes())))
 	}
 
 	fn __iter__(this: PyRef<Self>) -> PyRef<Self> {
@@ -60,3 +86,48 @@ impl PyFastaDnaReader {
 		self.inner.lock().next().map(|r| r.map(|r| r.into()))
 	}
 }
+
+#[pyclass(name = "FASTADNAWriter", module = "aspartik.io.fasta", frozen)]
+pub struct PyFastaDnaWriter {
+	inner: Mutex<Option<FastaWriter<BufWriter<File>>>>,
+}
+
+#[pymethods]
+impl PyFastaDnaWriter {
+	#[new]
+	#[pyo3(signature = (path, line_width = 60, ids_only = false))]
+	fn new(path: &str, line_width: usize, ids_only: bool) -> Result<Self> {
+		let file = BufWriter::new(File::create(path)?);
+		let mut writer = FastaWriter::new(file);
+		writer.set_line_width(line_width);
+		if ids_only {
+			writer.set_header(Header::Id);
+		}
+		Ok(Self {
+			inner: Mutex::new(Some(writer)),
+		})
+	}
+
+	fn write(&self, description: &str, sequence: &PyDnaSeq) -> Result<()> {
+		self.inner
+			.lock()
+			.as_mut()
+			.ok_or_else(|| anyhow!("The writer is closed"))?
+			.write(description, &sequence.0)
+	}
+
+	fn write_record(&self, record: &PyFastaDnaRecord) -> Result<()> {
+		self.inner
+			.lock()
+			.as_mut()
+			.ok_or_else(|| anyhow!("The writer is closed"))?
+			.write_record(&record.0)
+	}
+
+	fn close(&self) -> Result<()> {
+		if let Some(mut writer) = self.inner.lock().take() {
+			writer.flush()?;
+		}
+		Ok(())
+	}
+}
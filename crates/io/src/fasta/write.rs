@@ -0,0 +1,139 @@
+use anyhow::{ensure, Result};
+
+use std::io::Write;
+
+use super::Record;
+use data::seq::{Character, Seq};
+
+/// Which part of the description goes into the header line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Header {
+	/// The whole description.
+	#[default]
+	Full,
+	/// Only the ID, the description up to the first whitespace.
+	Id,
+}
+
+/// Writes records in the FASTA format, wrapping the sequences at a fixed
+/// width.
+pub struct FastaWriter<W: Write> {
+	writer: W,
+	line_width: usize,
+	header: Header,
+}
+
+impl<W: Write> FastaWriter<W> {
+	/// Creates a writer which wraps sequences at 60 characters and writes
+	/// full descriptions.
+	pub fn new(writer: W) -> Self {
+		FastaWriter {
+			writer,
+			line_width: 60,
+			header: Header::Full,
+		}
+	}
+
+	/// Maximum number of characters on a sequence line.  Zero disables
+	/// wrapping, so that every sequence is written on a single line.
+	pub fn set_line_width(&mut self, line_width: usize) {
+		self.line_width = line_width;
+	}
+
+	pub fn set_header(&mut self, header: Header) {
+		self.header = header;
+	}
+
+	/// Writes a single record.  A leading '>' in `description` is ignored,
+	/// so both the raw and the stripped descriptions can be passed.
+	pub fn write<C: Character>(
+		&mut self,
+		description: &str,
+		seq: &Seq<C>,
+	) -> Result<()> {
+		let description =
+			description.strip_prefix('>').unwrap_or(description);
+		ensure!(
+			!description.contains(['\n', '\r']),
+			"FASTA descriptions can't contain line breaks: '{}'",
+			description.escape_debug(),
+		);
+		let description = match self.header {
+			Header::Full => description,
+			Header::Id => description
+				.split_whitespace()
+				.next()
+				.unwrap_or(""),
+		};
+		writeln!(self.writer, ">{description}")?;
+
+		let width = match self.line_width {
+			0 => seq.len().max(1),
+			width => width,
+		};
+		let mut line = String::with_capacity(width);
+		for chunk in seq.chunks(width) {
+			line.clear();
+			line.extend(chunk
+				.iter()
+				.map(|c| -> char { (*c).into() }));
+			writeln!(self.writer, "{line}")?;
+		}
+
+		Ok(())
+	}
+
+	pub fn write_record<C: Character>(
+		&mut self,
+		record: &Record<C>,
+	) -> Result<()> {
+		self.write(record.description(), record.sequence())
+	}
+
+	pub fn flush(&mut self) -> Result<()> {
+		self.writer.flush()?;
+		Ok(())
+	}
+
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::fasta::FastaReader;
+	use data::DnaNucleotide;
+
+	#[test]
+	fn wrapping() {
+		let seq: Seq<DnaNucleotide> = "ACGTACGTAC".try_into().unwrap();
+
+		let mut writer = FastaWriter::new(Vec::new());
+		writer.set_line_width(4);
+		writer.write(">first sequence", &seq).unwrap();
+		writer.set_line_width(0);
+		writer.set_header(Header::Id);
+		writer.write("second sequence", &seq).unwrap();
+		writer.write("empty", &Seq::<DnaNucleotide>::new()).unwrap();
+		assert!(writer.write("bad\nline", &seq).is_err());
+
+		let out = String::from_utf8(writer.into_inner()).unwrap();
+		assert_eq!(
+			out,
+			">first sequence\nACGT\nACGT\nAC\n>second\nACGTACGTAC\n>empty\n"
+		);
+
+		let records: Vec<Record<DnaNucleotide>> =
+			FastaReader::new(out.as_bytes())
+				.collect::<Result<_>>()
+				.unwrap();
+		assert_eq!(records.len(), 3);
+		assert_eq!(records[0].id(), "first");
+		assert_eq!(records[0].description(), "first sequence");
+		assert_eq!(records[0].sequence(), &seq);
+		assert_eq!(records[1].sequence(), &seq);
+		assert!(records[2].sequence().is_empty());
+	}
+}
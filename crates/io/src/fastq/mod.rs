@@ -0,0 +1,282 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+use std::io::{BufRead, Lines, Read};
+
+use crate::decompress::Decoder;
+use data::seq::DnaSeq;
+
+#[cfg(feature = "python")]
+pub(crate) mod python;
+mod trim;
+
+/// Offset of the ASCII quality characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+	/// Phred+33, used by Sanger and Illumina 1.8 and later.
+	#[default]
+	Phred33,
+	/// Phred+64, used by Illumina 1.3 to 1.7.
+	Phred64,
+}
+
+impl Encoding {
+	pub fn offset(self) -> u8 {
+		match self {
+			Encoding::Phred33 => 33,
+			Encoding::Phred64 => 64,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+	/// Header line without the leading '@'.
+	description: String,
+	seq: DnaSeq,
+	/// Phred scores, one per character of `seq`.
+	qualities: Vec<u8>,
+}
+
+impl Record {
+	/// Creates a record from a description without the leading '@'.  Fails
+	/// if the numbers of characters and qualities differ.
+	pub fn new(
+		description: &str,
+		seq: DnaSeq,
+		qualities: Vec<u8>,
+	) -> Result<Self> {
+		ensure!(
+			seq.len() == qualities.len(),
+			"The record '{description}' has {} characters, but {} qualities",
+			seq.len(),
+			qualities.len(),
+		);
+		Ok(Record {
+			description: description.to_owned(),
+			seq,
+			qualities,
+		})
+	}
+
+	pub fn description(&self) -> &str {
+		&self.description
+	}
+
+	/// The first word of the description.
+	pub fn id(&self) -> &str {
+		self.description.split_whitespace().next().unwrap_or("")
+	}
+
+	pub fn sequence(&self) -> &DnaSeq {
+		&self.seq
+	}
+
+	/// Phred quality scores of the characters.
+	pub fn qualities(&self) -> &[u8] {
+		&self.qualities
+	}
+
+	pub fn into_parts(self) -> (String, DnaSeq, Vec<u8>) {
+		(self.description, self.seq, self.qualities)
+	}
+
+	pub fn len(&self) -> usize {
+		self.seq.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.seq.is_empty()
+	}
+
+	/// Mean Phred score, or `None` for an empty record.
+	pub fn mean_quality(&self) -> Option<f64> {
+		if self.is_empty() {
+			return None;
+		}
+		let total: f64 = self.qualities.iter().map(|q| *q as f64).sum();
+		Some(total / self.len() as f64)
+	}
+
+	/// Probabilities of each character being wrong, `10^(-q/10)`.
+	pub fn error_probabilities(&self) -> Vec<f64> {
+		self.qualities
+			.iter()
+			.map(|q| 10f64.powf(-(*q as f64) / 10.0))
+			.collect()
+	}
+}
+
+/// Reads four-line FASTQ records.  Gzip and bgzip input is decompressed.
+pub struct FastqReader<R: Read> {
+	reader: Lines<Decoder<R>>,
+	line: usize,
+	encoding: Encoding,
+}
+
+impl<R: Read> FastqReader<R> {
+	/// Creates a reader for Phred+33 qualities.  The reader is buffered
+	/// internally.
+	pub fn new(reader: R) -> Self {
+		FastqReader {
+			reader: Decoder::new(reader).lines(),
+			line: 0,
+			encoding: Encoding::Phred33,
+		}
+	}
+
+	pub fn set_encoding(&mut self, encoding: Encoding) {
+		self.encoding = encoding;
+	}
+
+	fn next_line(&mut self) -> Result<Option<String>> {
+		let line = self.reader.next().transpose()?;
+		if line.is_some() {
+			self.line += 1;
+		}
+		Ok(line)
+	}
+
+	/// Next line of the record `description`, which must exist.
+	fn expect_line(&mut self, description: &str) -> Result<String> {
+		self.next_line()?.ok_or_else(|| {
+			anyhow!(
+				"Unexpected end of file in the record '{description}' after line {}",
+				self.line,
+			)
+		})
+	}
+
+	fn record(&mut self) -> Result<Option<Record>> {
+		// Blank lines between records are allowed
+		let header = loop {
+			match self.next_line()? {
+				None => return Ok(None),
+				Some(line) if line.trim().is_empty() => {
+					continue
+				}
+				Some(line) => break line,
+			}
+		};
+		let Some(description) = header.strip_prefix('@') else {
+			bail!("Expected a '@' header at line {}", self.line);
+		};
+
+		let sequence = self.expect_line(description)?;
+		let seq = DnaSeq::try_from(sequence.trim_end()).with_context(
+			|| record_error(description, self.line),
+		)?;
+
+		let separator = self.expect_line(description)?;
+		let Some(repeated) = separator.strip_prefix('+') else {
+			bail!(
+				"Expected a '+' separator for the record '{description}' at line {}",
+				self.line,
+			);
+		};
+		let repeated = repeated.trim_end();
+		ensure!(
+			repeated.is_empty() || repeated == description,
+			"The separator '+{repeated}' doesn't match the header of the record '{description}' at line {}",
+			self.line,
+		);
+
+		let line = self.expect_line(description)?;
+		let line = line.trim_end();
+		ensure!(
+			line.len() == seq.len(),
+			"{}: {} qualities for {} characters",
+			record_error(description, self.line),
+			line.len(),
+			seq.len(),
+		);
+		let offset = self.encoding.offset();
+		let qualities = line
+			.bytes()
+			.map(|byte| match byte {
+				b'!'..=b'~' if byte >= offset => {
+					Ok(byte - offset)
+				}
+				_ => Err(anyhow!(
+					"{}: invalid quality character '{}'",
+					record_error(description, self.line),
+					byte.escape_ascii(),
+				)),
+			})
+			.collect::<Result<_>>()?;
+
+		Ok(Some(Record {
+			description: description.to_owned(),
+			seq,
+			qualities,
+		}))
+	}
+}
+
+impl<R: Read> Iterator for FastqReader<R> {
+	type Item = Result<Record>;
+
+	fn next(&mut self) -> Option<Result<Record>> {
+		self.record().transpose()
+	}
+}
+
+fn record_error(description: &str, line: usize) -> String {
+	format!("Failed to parse the record '{description}' at line {line}")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn read(text: &str) -> Result<Vec<Record>> {
+		FastqReader::new(text.as_bytes()).collect()
+	}
+
+	#[test]
+	fn records() {
+		let records = read(
+			"@read1 sample=A\nACGTN\n+\nII5#!\n\n@read2\nAC\n+read2\n~~\n",
+		)
+		.unwrap();
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].id(), "read1");
+		assert_eq!(records[0].description(), "read1 sample=A");
+		assert_eq!(records[0].sequence().to_string(), "ACGTN");
+		assert_eq!(records[0].qualities(), [40, 40, 20, 2, 0]);
+		assert_eq!(records[1].qualities(), [93, 93]);
+		assert_eq!(records[1].mean_quality(), Some(93.0));
+
+		let mut reader = FastqReader::new("@r\nAC\n+\nhB\n".as_bytes());
+		reader.set_encoding(Encoding::Phred64);
+		let record = reader.next().unwrap().unwrap();
+		assert_eq!(record.qualities(), [40, 2]);
+		let probabilities = record.error_probabilities();
+		assert!((probabilities[0] - 1e-4).abs() < 1e-12);
+	}
+
+	#[test]
+	fn errors() {
+		let message = |text| format!("{:#}", read(text).unwrap_err());
+
+		let err = message("@r1\nAC\n+\nII\n@r2\nACG\n+\nII\n");
+		assert!(err.contains("'r2' at line 8"), "{err}");
+		assert!(err.contains("2 qualities for 3 characters"), "{err}");
+
+		let err = message("@r1\nAC\n+\nI\x7f\n");
+		assert!(err.contains("invalid quality"), "{err}");
+		let err = message("@r1\nAZ\n+\nII\n");
+		assert!(err.contains("'r1' at line 2"), "{err}");
+		let err = message("r1\nAC\n+\nII\n");
+		assert!(err.contains("line 1"), "{err}");
+		let err = message("@r1\nAC\n-\nII\n");
+		assert!(err.contains("line 3"), "{err}");
+		let err = message("@r1\nAC\n+r2\nII\n");
+		assert!(err.contains("doesn't match"), "{err}");
+		let err = message("@r1\nAC\n+\n");
+		assert!(err.contains("end of file"), "{err}");
+
+		let mut reader = FastqReader::new("@r\nAC\n+\n?I\n".as_bytes());
+		reader.set_encoding(Encoding::Phred64);
+		assert!(reader.next().unwrap().is_err());
+	}
+}
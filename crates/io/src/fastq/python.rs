@@ -0,0 +1,143 @@
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use pyo3::prelude::*;
+
+use std::io::Cursor;
+
+use super::{Encoding, FastqReader, Record};
+use crate::python::{open, Source};
+use data::seq::python::PyDnaSeq;
+
+#[derive(Clone)]
+#[pyclass(name = "FASTQRecord", module = "aspartik.io.fastq", frozen)]
+pub(crate) struct PyFastqRecord(Record);
+
+impl PyFastqRecord {
+	/// Applies `trim` to a copy of the record.
+	fn trimmed(&self, trim: impl FnOnce(&mut Record)) -> Self {
+		let mut record = self.0.clone();
+		trim(&mut record);
+		PyFastqRecord(record)
+	}
+}
+
+#[pymethods]
+impl PyFastqRecord {
+	#[new]
+	fn new(
+		description: &str,
+		sequence: &PyDnaSeq,
+		qualities: Vec<u8>,
+	) -> Result<Self> {
+		let record = Record::new(
+			description,
+			sequence.0.clone(),
+			qualities,
+		)?;
+		Ok(PyFastqRecord(record))
+	}
+
+	#[getter]
+	fn id(&self) -> String {
+		self.0.id().to_owned()
+	}
+
+	#[getter]
+	fn description(&self) -> String {
+		self.0.description().to_owned()
+	}
+
+	#[getter]
+	fn sequence(&self) -> PyDnaSeq {
+		self.0.sequence().to_owned().into()
+	}
+
+	#[getter]
+	fn qualities(&self) -> Vec<u8> {
+		self.0.qualities().to_vec()
+	}
+
+	fn __len__(&self) -> usize {
+		self.0.len()
+	}
+
+	fn mean_quality(&self) -> Option<f64> {
+		self.0.mean_quality()
+	}
+
+	fn error_probabilities(&self) -> Vec<f64> {
+		self.0.error_probabilities()
+	}
+
+	fn trim(&self, start: usize, end: usize) -> Result<Self> {
+		if start > end || end > self.0.len() {
+			bail!(
+				"Range {start}..{end} is out of bounds for a record of length {}",
+				self.0.len()
+			);
+		}
+		Ok(self.trimmed(|record| record.trim(start..end)))
+	}
+
+	fn trim_ends(&self, threshold: u8) -> Self {
+		self.trimmed(|record| record.trim_ends(threshold))
+	}
+
+	fn trim_sliding_window(&self, window: usize, threshold: f64) -> Self {
+		self.trimmed(|record| {
+			record.trim_sliding_window(window, threshold)
+		})
+	}
+
+	fn trim_bwa(&self, threshold: u8) -> Self {
+		self.trimmed(|record| record.trim_bwa(threshold))
+	}
+}
+
+#[pyclass(name = "FASTQReader", module = "aspartik.io.fastq", frozen)]
+pub(crate) struct PyFastqReader {
+	inner: Mutex<FastqReader<Source>>,
+}
+
+impl PyFastqReader {
+	fn from_source(source: Source, encoding: &str) -> Result<Self> {
+		let encoding = match encoding {
+			"phred33" => Encoding::Phred33,
+			"phred64" => Encoding::Phred64,
+			_ => bail!(
+				"Unknown quality encoding '{encoding}', expected 'phred33' or 'phred64'"
+			),
+		};
+		let mut reader = FastqReader::new(source);
+		reader.set_encoding(encoding);
+		Ok(Self {
+			inner: Mutex::new(reader),
+		})
+	}
+}
+
+#[pymethods]
+impl PyFastqReader {
+	/// `source` is either a path or a file-like object.  Gzipped input is
+	/// decompressed automatically.
+	#[new]
+	#[pyo3(signature = (source, encoding = "phred33"))]
+	fn new(source: &Bound<PyAny>, encoding: &str) -> Result<Self> {
+		Self::from_source(open(source)?, encoding)
+	}
+
+	#[staticmethod]
+	#[pyo3(signature = (text, encoding = "phred33"))]
+	fn from_string(text: String, encoding: &str) -> Result<Self> {
+		let source = Box::new(Cursor::new(text.into_bytes()));
+		Self::from_source(source, encoding)
+	}
+
+	fn __iter__(this: PyRef<Self>) -> PyRef<Self> {
+		this
+	}
+
+	fn __next__(&self) -> Option<Result<PyFastqRecord>> {
+		self.inner.lock().next().map(|r| r.map(PyFastqRecord))
+	}
+}
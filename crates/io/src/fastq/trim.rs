@@ -0,0 +1,125 @@
+use std::ops::Range;
+
+use super::Record;
+use data::seq::DnaSeq;
+
+impl Record {
+	/// Keeps only the characters in `range` and their qualities.
+	///
+	/// # Panics
+	///
+	/// If `range` is out of bounds.
+	pub fn trim(&mut self, range: Range<usize>) {
+		self.seq = DnaSeq::from(&self.seq[range.clone()]);
+		self.qualities = self.qualities[range].to_vec();
+	}
+
+	/// Removes the characters with qualities below `threshold` from both
+	/// ends.
+	pub fn trim_ends(&mut self, threshold: u8) {
+		let keep = |q: &u8| *q >= threshold;
+		let start = self
+			.qualities
+			.iter()
+			.position(keep)
+			.unwrap_or(self.len());
+		let end = self
+			.qualities
+			.iter()
+			.rposition(keep)
+			.map_or(start, |i| i + 1);
+		self.trim(start..end);
+	}
+
+	/// Cuts the read at the start of the first `window` characters with a
+	/// mean quality below `threshold`, like `SLIDINGWINDOW` of Trimmomatic.
+	/// Reads shorter than `window` are checked as a single window.
+	pub fn trim_sliding_window(&mut self, window: usize, threshold: f64) {
+		if self.is_empty() {
+			return;
+		}
+		let window = window.clamp(1, self.len());
+		let end = self
+			.qualities
+			.windows(window)
+			.position(|qualities| {
+				let total: f64 = qualities
+					.iter()
+					.map(|q| *q as f64)
+					.sum();
+				total / (window as f64) < threshold
+			})
+			.unwrap_or(self.len());
+		self.trim(0..end);
+	}
+
+	/// Trims the 3' end with the algorithm of BWA, which is also used by
+	/// cutadapt: the cut maximizes the sum of `threshold - q` over the
+	/// removed characters.  Unlike [`trim_ends`][Self::trim_ends], a few
+	/// good characters in a low-quality tail don't stop the trimming.
+	pub fn trim_bwa(&mut self, threshold: u8) {
+		let mut sum = 0i64;
+		let mut best = 0i64;
+		let mut end = self.len();
+		for (i, q) in self.qualities.iter().enumerate().rev() {
+			sum += threshold as i64 - *q as i64;
+			if sum < 0 {
+				break;
+			}
+			if sum > best {
+				best = sum;
+				end = i;
+			}
+		}
+		self.trim(0..end);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use anyhow::Result;
+
+	use super::*;
+
+	fn record(qualities: &[u8]) -> Result<Record> {
+		let seq = "A".repeat(qualities.len());
+		Record::new(
+			"read",
+			seq.as_str().try_into()?,
+			qualities.to_vec(),
+		)
+	}
+
+	#[test]
+	fn trimming() -> Result<()> {
+		let mut read = record(&[2, 30, 30, 10, 30, 5, 2])?;
+		read.trim_ends(20);
+		assert_eq!(read.qualities(), [30, 30, 10, 30]);
+		assert_eq!(read.len(), 4);
+
+		let mut read = record(&[2, 2])?;
+		read.trim_ends(20);
+		assert!(read.is_empty());
+		read.trim_sliding_window(4, 20.0);
+		read.trim_bwa(20);
+		assert!(read.is_empty());
+
+		let mut read = record(&[30, 30, 30, 20, 10, 30, 30])?;
+		read.trim_sliding_window(2, 20.0);
+		assert_eq!(read.qualities(), [30, 30, 30]);
+
+		let mut read = record(&[10, 10])?;
+		read.trim_sliding_window(4, 20.0);
+		assert!(read.is_empty());
+
+		// The 25 in the tail doesn't stop the trimming
+		let mut read = record(&[40, 40, 40, 10, 25, 5, 2])?;
+		read.trim_bwa(20);
+		assert_eq!(read.qualities(), [40, 40, 40]);
+		assert_eq!(read.sequence().len(), 3);
+
+		assert!(Record::new("read", DnaSeq::new(), vec![1]).is_err());
+
+		Ok(())
+	}
+}
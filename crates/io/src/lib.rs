@@ -1,5 +1,11 @@
+pub mod alignment;
+pub mod decompress;
 pub mod fasta;
+pub mod fastq;
 pub mod newick;
+pub mod nexus;
+#[cfg(feature = "python")]
+mod python;
 
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
@@ -10,8 +16,16 @@ pub fn pymodule(py: Python) -> PyResult<Bound<PyModule>> {
 
 	m.add_class::<newick::python::PyNode>()?;
 	m.add_class::<newick::python::PyTree>()?;
+	m.add_class::<newick::python::PyNewickReader>()?;
 	m.add_class::<fasta::python::PyFastaDnaRecord>()?;
 	m.add_class::<fasta::python::PyFastaDnaReader>()?;
+	m.add_class::<fasta::python::PyFastaDnaWriter>()?;
+	m.add_class::<fastq::python::PyFastqRecord>()?;
+	m.add_class::<fastq::python::PyFastqReader>()?;
+	m.add_class::<nexus::python::PyNexus>()?;
+	m.add_class::<nexus::python::PyCharacters>()?;
+	m.add_class::<nexus::python::PyNexusTreesWriter>()?;
+	m.add_class::<alignment::python::PyAlignment>()?;
 
 	Ok(m)
 }
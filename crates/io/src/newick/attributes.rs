@@ -0,0 +1,226 @@
+use nom::{
+	branch::alt,
+	bytes::complete::{tag, take_till, take_till1},
+	character::complete::{char, multispace0},
+	combinator::{all_consuming, map, opt},
+	multi::separated_list0,
+	sequence::{delimited, preceded},
+	IResult, Parser,
+};
+
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Display, Write},
+};
+
+use super::parse::quoted;
+
+/// Metadata of a node, from the `[&key=value,...]` comments used by BEAST
+/// and FigTree.
+pub type Attributes = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Number(f64),
+	/// Any value which isn't a number.  It's always written in double
+	/// quotes.
+	Text(String),
+	/// A `{a,b}` set, which is also used for ranges like `height_95%_HPD`.
+	Set(Vec<Value>),
+}
+
+impl From<f64> for Value {
+	fn from(value: f64) -> Self {
+		Value::Number(value)
+	}
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self {
+		Value::Text(value)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self {
+		Value::Text(value.to_owned())
+	}
+}
+
+impl From<Vec<Value>> for Value {
+	fn from(value: Vec<Value>) -> Self {
+		Value::Set(value)
+	}
+}
+
+impl Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Number(value) => write!(f, "{value}"),
+			Value::Text(value) => {
+				// `]` would end the comment
+				f.write_char('"')?;
+				for c in value.chars() {
+					if matches!(c, '\\' | '"' | ']') {
+						f.write_char('\\')?;
+					}
+					f.write_char(c)?;
+				}
+				f.write_char('"')
+			}
+			Value::Set(values) => {
+				f.write_str("{")?;
+				for (i, value) in values.iter().enumerate() {
+					if i != 0 {
+						f.write_str(",")?;
+					}
+					value.fmt(f)?;
+				}
+				f.write_str("}")
+			}
+		}
+	}
+}
+
+/// Writes `attributes` as comma-separated `key=value` pairs, without the
+/// enclosing comment.  Keys which wouldn't be read back as they are get
+/// quoted like text values.
+pub(crate) fn serialize(attributes: &Attributes) -> String {
+	attributes
+		.iter()
+		.map(|(key, value)| {
+			let special = |c| matches!(c, ',' | '=' | ']' | '"');
+			if key.is_empty()
+				|| key.trim() != key || key.contains(special)
+			{
+				format!("{}={value}", Value::from(key.as_str()))
+			} else {
+				format!("{key}={value}")
+			}
+		})
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// Parses the contents of a metadata comment, without the leading `&`, into
+/// `attributes`.  Keys without a value are set to an empty string.  Returns
+/// `None` if the comment is malformed.
+pub(crate) fn parse(input: &str, attributes: &mut Attributes) -> Option<()> {
+	let (_, pairs) = all_consuming(separated_list0(char(','), pair))
+		.parse(input)
+		.ok()?;
+	for (key, value) in pairs {
+		let value = value.unwrap_or_else(|| Value::Text(String::new()));
+		attributes.insert(key, value);
+	}
+	Some(())
+}
+
+fn pair(input: &str) -> IResult<&str, (String, Option<Value>)> {
+	let bare = map(take_till1(|c| c == '=' || c == ','), |key: &str| {
+		key.trim().to_owned()
+	});
+	let key = alt((delimited(multispace0, quoted, multispace0), bare));
+	(key, opt(preceded(char('='), value))).parse(input)
+}
+
+/// Recursive.
+fn value(input: &str) -> IResult<&str, Value> {
+	let set = delimited(
+		(char('{'), multispace0),
+		separated_list0(char(','), value),
+		char('}'),
+	);
+
+	delimited(
+		multispace0,
+		alt((
+			map(set, Value::Set),
+			map(quoted, Value::Text),
+			map(single_quoted, |s: &str| Value::Text(s.to_owned())),
+			map(bare, |s: &str| match s.parse() {
+				Ok(number) => Value::Number(number),
+				Err(_) => Value::Text(s.to_owned()),
+			}),
+		)),
+		multispace0,
+	)
+	.parse(input)
+}
+
+fn single_quoted(input: &str) -> IResult<&str, &str> {
+	delimited(tag("'"), take_till(|c| c == '\''), tag("'")).parse(input)
+}
+
+fn bare(input: &str) -> IResult<&str, &str> {
+	let special = |c| matches!(c, ',' | '{' | '}' | '"');
+	map(take_till1(special), str::trim).parse(input)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::newick::Tree;
+
+	#[test]
+	fn values() {
+		let mut attributes = Attributes::new();
+		parse(
+			r#"rate=0.5,location="New York",height_95%_HPD={1.5,2e1},state=A,set={a, {1}},flag"#,
+			&mut attributes,
+		)
+		.unwrap();
+
+		assert_eq!(attributes["rate"], Value::Number(0.5));
+		assert_eq!(attributes["location"], "New York".into());
+		assert_eq!(
+			attributes["height_95%_HPD"],
+			Value::Set(vec![1.5.into(), 20.0.into()])
+		);
+		assert_eq!(attributes["state"], "A".into());
+		assert_eq!(
+			attributes["set"],
+			Value::Set(vec![
+				"a".into(),
+				Value::Set(vec![1.0.into()])
+			])
+		);
+		assert_eq!(attributes["flag"], "".into());
+
+		assert_eq!(
+			serialize(&attributes),
+			r#"flag="",height_95%_HPD={1.5,20},location="New York",rate=0.5,set={"a",{1}},state="A""#
+		);
+
+		assert!(parse("rate={1,2", &mut attributes).is_none());
+		assert!(parse("rate=\"1", &mut attributes).is_none());
+		assert!(parse("=1", &mut attributes).is_none());
+	}
+
+	#[test]
+	fn round_trip() {
+		let attributes = Attributes::from([
+			("a,b".to_owned(), "x]y".into()),
+			("k=v]".to_owned(), r#"quote " and \"#.into()),
+			(" padded".to_owned(), Value::Set(vec!["]".into()])),
+			("plain".to_owned(), 1.0.into()),
+		]);
+		let text = serialize(&attributes);
+		assert_eq!(
+			text,
+			r#"" padded"={"\]"},"a,b"="x\]y","k=v\]"="quote \" and \\",plain=1"#
+		);
+
+		let mut parsed = Attributes::new();
+		parse(&text, &mut parsed).unwrap();
+		assert_eq!(parsed, attributes);
+
+		// The escaped `]` doesn't end the comment
+		let mut tree = Tree::parse("(A,B);").unwrap();
+		let a = tree.find("A").unwrap();
+		*tree.get_node_mut(a).attributes_mut() = attributes.clone();
+		let tree = Tree::parse(&tree.serialize()).unwrap();
+		let a = tree.find("A").unwrap();
+		assert_eq!(tree.get_node(a).attributes(), &attributes);
+	}
+}
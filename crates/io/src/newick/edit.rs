@@ -0,0 +1,596 @@
+use anyhow::{ensure, Context, Result};
+use petgraph::visit::EdgeRef;
+use rand::Rng;
+
+use std::{
+	cmp::Reverse,
+	collections::{HashMap, HashSet},
+	iter,
+};
+
+use super::{Attributes, Node, NodeIndex, Tree};
+
+impl Tree {
+	/// Makes `node` the root by reversing the branches between it and the
+	/// old root.  Branch lengths stay with their branches.  If the old root
+	/// is left with a single child, it's removed and its branches are
+	/// joined.
+	pub fn reroot(&mut self, node: NodeIndex) {
+		let Some(old_root) = self.root else {
+			self.root = Some(node);
+			return;
+		};
+		if node == old_root {
+			return;
+		}
+
+		// From `node` up to the old root
+		let path: Vec<_> =
+			iter::once(node).chain(self.ancestors(node)).collect();
+		let distances: Vec<_> =
+			path.iter().map(|n| self.graph[*n].distance).collect();
+
+		for pair in path.windows(2) {
+			if let Some(edge) =
+				self.graph.find_edge(pair[1], pair[0])
+			{
+				self.graph.remove_edge(edge);
+			}
+		}
+		for (i, pair) in path.windows(2).enumerate() {
+			self.graph.add_edge(pair[0], pair[1], ());
+			self.graph[pair[1]].distance = distances[i];
+		}
+		self.graph[node].distance = distances[distances.len() - 1];
+		self.root = Some(node);
+
+		match self.children_of(old_root).len() {
+			0 if self.graph[old_root].name.is_empty() => {
+				self.graph.remove_node(old_root);
+			}
+			1 => self.splice(old_root),
+			_ => (),
+		}
+	}
+
+	/// Roots the tree in the middle of the branch above the MRCA of
+	/// `outgroup`.  If the outgroup spans the current root, the tree is
+	/// rooted above the MRCA of the remaining leaves instead.
+	pub fn reroot_outgroup(
+		&mut self,
+		outgroup: &[NodeIndex],
+	) -> Result<()> {
+		ensure!(!outgroup.is_empty(), "The outgroup is empty");
+		let root = self.root.context("The tree has no root")?;
+		let mut mrca = self
+			.mrca(outgroup)
+			.context("The outgroup isn't connected to the tree")?;
+
+		if mrca == root {
+			let excluded: HashSet<_> = outgroup
+				.iter()
+				.flat_map(|node| self.preorder(*node))
+				.collect();
+			let ingroup: Vec<_> = self
+				.leaves()
+				.filter(|leaf| !excluded.contains(leaf))
+				.collect();
+			ensure!(
+				!ingroup.is_empty(),
+				"The outgroup has all leaves"
+			);
+			mrca = self.mrca(&ingroup).unwrap_or(root);
+			ensure!(mrca != root, "The outgroup isn't a clade");
+		}
+
+		let middle = self.split(mrca, self.length(mrca) / 2.0);
+		self.reroot(middle);
+		Ok(())
+	}
+
+	/// Roots the tree halfway between the two leaves which are the
+	/// farthest apart.
+	pub fn reroot_midpoint(&mut self) -> Result<()> {
+		let leaves: Vec<_> = self.leaves().collect();
+		ensure!(
+			leaves.len() >= 2,
+			"Midpoint rooting needs at least two leaves"
+		);
+
+		let distances = self.cophenetic();
+		let (mut a, mut b) = (leaves[0], leaves[1]);
+		let mut longest = f64::NEG_INFINITY;
+		for i in 0..leaves.len() {
+			for j in (i + 1)..leaves.len() {
+				if distances[i][j] > longest {
+					longest = distances[i][j];
+					(a, b) = (leaves[i], leaves[j]);
+				}
+			}
+		}
+
+		// Walk up from the leaf which is farther from the MRCA
+		let mrca = self.mrca(&[a, b]).context("Disconnected leaves")?;
+		let half = longest / 2.0;
+		let from_a = self.distance_between(a, mrca).unwrap_or(0.0);
+		let (mut node, mut remaining) = if half <= from_a {
+			(a, half)
+		} else {
+			(b, longest - half)
+		};
+		while node != mrca && remaining > self.length(node) {
+			remaining -= self.length(node);
+			node = self.parent_of(node).unwrap_or(mrca);
+		}
+
+		if node == mrca {
+			self.reroot(mrca);
+		} else {
+			let middle = self.split(node, remaining);
+			self.reroot(middle);
+		}
+		Ok(())
+	}
+
+	/// Sorts the children of every node by the number of leaves under
+	/// them, smallest first if `ascending` is set.  Ties keep their order.
+	pub fn ladderize(&mut self, ascending: bool) {
+		let Some(root) = self.root else {
+			return;
+		};
+
+		let order: Vec<_> = self.preorder(root).collect();
+		let mut sizes = HashMap::new();
+		for node in order.into_iter().rev() {
+			let children = self.children_of(node);
+			let size = if children.is_empty() {
+				1
+			} else {
+				children.iter().map(|child| sizes[child]).sum()
+			};
+			sizes.insert(node, size);
+
+			let mut sorted = children.clone();
+			if ascending {
+				sorted.sort_by_key(|child| sizes[child]);
+			} else {
+				sorted.sort_by_key(|child| {
+					Reverse(sizes[child])
+				});
+			}
+			if sorted != children {
+				self.set_children(node, &sorted);
+			}
+		}
+	}
+
+	/// Removes `nodes` together with their subtrees.  Internal nodes left
+	/// with a single child are removed and their branches are joined, and
+	/// the ones left without children are removed too.
+	pub fn prune(&mut self, nodes: &[NodeIndex]) {
+		for node in nodes {
+			// It could've been removed with an earlier subtree
+			if !self.contains(*node) {
+				continue;
+			}
+
+			let parent = self.parent_of(*node);
+			let subtree: Vec<_> = self.preorder(*node).collect();
+			for node in subtree {
+				self.graph.remove_node(node);
+			}
+
+			match parent {
+				Some(parent) => self.clean_up(parent),
+				None => self.root = None,
+			}
+		}
+	}
+
+	/// Removes internal branches shorter than `threshold`, turning them into
+	/// polytomies.  The lengths of the removed branches are added to the
+	/// ones below them, so that the leaf heights don't change.  Branches
+	/// without a length are kept.
+	pub fn collapse(&mut self, threshold: f64) {
+		let Some(root) = self.root else {
+			return;
+		};
+
+		let order: Vec<_> = self.preorder(root).collect();
+		for node in order {
+			let Some(parent) = self.parent_of(node) else {
+				continue;
+			};
+			let Some(length) = self.graph[node].distance else {
+				continue;
+			};
+			if self.is_leaf(node) || length >= threshold {
+				continue;
+			}
+
+			let children = self.children_of(node);
+			for child in &children {
+				let distance = self.graph[*child].distance;
+				self.graph[*child].distance =
+					Some(distance.unwrap_or(0.0) + length);
+			}
+			let siblings: Vec<_> = self
+				.children_of(parent)
+				.into_iter()
+				.flat_map(|sibling| {
+					if sibling == node {
+						children.clone()
+					} else {
+						vec![sibling]
+					}
+				})
+				.collect();
+			self.graph.remove_node(node);
+			self.set_children(parent, &siblings);
+		}
+	}
+
+	/// Replaces every node with more than two children by a random binary
+	/// subtree, with zero-length internal branches.
+	pub fn resolve_polytomies<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+		let nodes: Vec<_> = self.nodes().collect();
+		for node in nodes {
+			let mut children = self.children_of(node);
+			if children.len() <= 2 {
+				continue;
+			}
+
+			while children.len() > 2 {
+				let a = children.swap_remove(
+					rng.random_range(0..children.len()),
+				);
+				let b = children.swap_remove(
+					rng.random_range(0..children.len()),
+				);
+				let joined = self.graph.add_node(Node::new(
+					String::new(),
+					Some(0.0),
+					Attributes::new(),
+				));
+				// The edges from `node` are replaced below
+				self.graph.add_edge(joined, a, ());
+				self.graph.add_edge(joined, b, ());
+				children.push(joined);
+			}
+			self.set_children(node, &children);
+		}
+	}
+
+	/// Replaces the children of `node`.  They must not have other parents.
+	fn set_children(&mut self, node: NodeIndex, children: &[NodeIndex]) {
+		let edges: Vec<_> =
+			self.graph.edges(node).map(|edge| edge.id()).collect();
+		for edge in edges {
+			self.graph.remove_edge(edge);
+		}
+		for child in children {
+			self.graph.add_edge(node, *child, ());
+		}
+	}
+
+	/// Inserts a node on the branch above `node`, `offset` away from it, and
+	/// returns the new node.
+	fn split(&mut self, node: NodeIndex, offset: f64) -> NodeIndex {
+		let distance = self.graph[node].distance;
+		let middle = self.graph.add_node(Node::new(
+			String::new(),
+			distance.map(|length| length - offset),
+			Attributes::new(),
+		));
+
+		if let Some(parent) = self.parent_of(node) {
+			let children: Vec<_> =
+				self.children_of(parent)
+					.into_iter()
+					.map(|child| {
+						if child == node {
+							middle
+						} else {
+							child
+						}
+					})
+					.collect();
+			self.set_children(parent, &children);
+		} else {
+			self.root = Some(middle);
+		}
+		self.graph.add_edge(middle, node, ());
+		self.graph[node].distance = distance.map(|_| offset);
+
+		middle
+	}
+
+	/// Removes `node`, which has a single child, and joins the branches
+	/// above and below it.
+	fn splice(&mut self, node: NodeIndex) {
+		let child = self.children_of(node)[0];
+		let above = self.graph[node].distance;
+		let below = self.graph[child].distance;
+		let parent = self.parent_of(node);
+
+		if let Some(parent) = parent {
+			let children: Vec<_> = self
+				.children_of(parent)
+				.into_iter()
+				.map(|sibling| {
+					if sibling == node {
+						child
+					} else {
+						sibling
+					}
+				})
+				.collect();
+			self.graph.remove_node(node);
+			self.set_children(parent, &children);
+			self.graph[child].distance = match (above, below) {
+				(None, None) => None,
+				_ => Some(above.unwrap_or(0.0)
+					+ below.unwrap_or(0.0)),
+			};
+		} else {
+			self.graph.remove_node(node);
+			self.root = Some(child);
+			self.graph[child].distance = above;
+		}
+	}
+
+	/// Removes `node` if it has lost all of its children, or splices it if
+	/// it has a single one left.  Recursive.
+	fn clean_up(&mut self, node: NodeIndex) {
+		match self.children_of(node).len() {
+			0 => {
+				let parent = self.parent_of(node);
+				self.graph.remove_node(node);
+				match parent {
+					Some(parent) => self.clean_up(parent),
+					None => self.root = None,
+				}
+			}
+			1 => self.splice(node),
+			_ => (),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::SeedableRng;
+	use rand_pcg::Pcg64;
+
+	fn find(tree: &Tree, names: &[&str]) -> Vec<NodeIndex> {
+		names.iter().map(|name| tree.find(name).unwrap()).collect()
+	}
+
+	/// Path lengths between all pairs of leaves, by their names.  Rerooting
+	/// and resolving polytomies must keep them.
+	fn distances(tree: &Tree) -> Vec<(String, String, f64)> {
+		let name =
+			|node: NodeIndex| tree.get_node(node).name().to_owned();
+		let leaves: Vec<_> = tree.leaves().collect();
+		let mut out = Vec::new();
+		for a in &leaves {
+			for b in &leaves {
+				let distance =
+					tree.distance_between(*a, *b).unwrap();
+				out.push((name(*a), name(*b), distance));
+			}
+		}
+		out.sort_by(|x, y| (&x.0, &x.1).cmp(&(&y.0, &y.1)));
+		out
+	}
+
+	fn assert_same(
+		a: &[(String, String, f64)],
+		b: &[(String, String, f64)],
+	) {
+		assert_eq!(a.len(), b.len());
+		for (x, y) in a.iter().zip(b) {
+			assert_eq!((&x.0, &x.1), (&y.0, &y.1));
+			assert!((x.2 - y.2).abs() < 1e-12, "{x:?} != {y:?}");
+		}
+	}
+
+	#[test]
+	fn reroot() {
+		let mut tree =
+			Tree::parse("((A:1,B:2):1,(C:1,D:1):3);").unwrap();
+		let outgroup = find(&tree, &["C", "D"]);
+		tree.reroot_outgroup(&outgroup).unwrap();
+		assert_eq!(tree.serialize(), "((C:1,D:1):1.5,(A:1,B:2):2.5);");
+
+		let [b, c] = find(&tree, &["B", "C"])[..] else {
+			unreachable!()
+		};
+		tree.reroot(c);
+		assert_eq!(tree.serialize(), "((D:1,(A:1,B:2):4):1)C;");
+		assert_eq!(tree.distance_between(b, c), Some(7.0));
+
+		let mut tree = Tree::parse("((A:1,B:7):1,C:1);").unwrap();
+		tree.reroot_midpoint().unwrap();
+		assert_eq!(tree.serialize(), "(B:4.5,(A:1,C:2):2.5);");
+
+		// The outgroup spans the root
+		let mut tree = Tree::parse("(A:1,B:1,(C:1,D:1):1);").unwrap();
+		let outgroup = find(&tree, &["A", "B"]);
+		tree.reroot_outgroup(&outgroup).unwrap();
+		assert_eq!(tree.serialize(), "((C:1,D:1):0.5,(A:1,B:1):0.5);");
+		assert!(tree.reroot_outgroup(&[]).is_err());
+	}
+
+	#[test]
+	fn edit() {
+		let mut tree =
+			Tree::parse("(((A:1,B:1):1,C:2):1,(D:1,E:1):2);")
+				.unwrap();
+		tree.ladderize(true);
+		assert_eq!(
+			tree.serialize(),
+			"((D:1,E:1):2,(C:2,(A:1,B:1):1):1);"
+		);
+		tree.ladderize(false);
+		assert_eq!(
+			tree.serialize(),
+			"(((A:1,B:1):1,C:2):1,(D:1,E:1):2);"
+		);
+
+		let pruned = find(&tree, &["C", "D"]);
+		tree.prune(&pruned);
+		assert_eq!(tree.serialize(), "((A:1,B:1):2,E:3);");
+		let pruned = find(&tree, &["A", "B"]);
+		tree.prune(&pruned);
+		assert_eq!(tree.serialize(), "E;");
+
+		let mut tree =
+			Tree::parse("(((A:1,B:1):0.1,C:1.1):1,(D:1,E:1):2);")
+				.unwrap();
+		tree.collapse(0.5);
+		assert_eq!(
+			tree.serialize(),
+			"((A:1.1,B:1.1,C:1.1):1,(D:1,E:1):2);"
+		);
+
+		let mut rng = Pcg64::seed_from_u64(4);
+		tree.resolve_polytomies(&mut rng);
+		let root = *tree.root().unwrap();
+		assert!(tree
+			.preorder(root)
+			.all(|node| tree.children_of(node).len() <= 2));
+		assert_eq!(tree.leaves().count(), 5);
+		let [a, c] = find(&tree, &["A", "C"])[..] else {
+			unreachable!()
+		};
+		assert_eq!(tree.distance_between(a, c), Some(2.2));
+	}
+
+	#[test]
+	fn reroot_on_internal_node() {
+		let source = "((A:1,B:2)AB:1,(C:1,D:1):3);";
+		let mut tree = Tree::parse(source).unwrap();
+		let before = distances(&tree);
+
+		let ab = tree.find("AB").unwrap();
+		tree.reroot(ab);
+		// The old root is left with one child and is spliced
+		assert_eq!(tree.serialize(), "(A:1,B:2,(C:1,D:1):4)AB;");
+		assert_same(&distances(&tree), &before);
+
+		// Rerooting at the root changes nothing
+		let mut tree = Tree::parse(source).unwrap();
+		tree.reroot(*tree.root().unwrap());
+		assert_eq!(tree.serialize(), source);
+	}
+
+	#[test]
+	fn reroot_outgroup_leaf() {
+		let mut tree =
+			Tree::parse("((A:1,B:2):1,(C:1,D:1):3);").unwrap();
+		let before = distances(&tree);
+		let outgroup = find(&tree, &["D"]);
+		tree.reroot_outgroup(&outgroup).unwrap();
+		assert_eq!(tree.serialize(), "(D:0.5,(C:1,(A:1,B:2):4):0.5);");
+		assert_same(&distances(&tree), &before);
+
+		let all = find(&tree, &["A", "B", "C", "D"]);
+		assert!(tree.reroot_outgroup(&all).is_err());
+	}
+
+	#[test]
+	fn reroot_midpoint_unbalanced() {
+		// A caterpillar with a long branch to F
+		let mut tree =
+			Tree::parse("((((A:1,B:1):1,C:1):1,D:1):1,F:10);")
+				.unwrap();
+		let before = distances(&tree);
+		tree.reroot_midpoint().unwrap();
+		assert_same(&distances(&tree), &before);
+
+		// A and F are the farthest apart, 14 from each other
+		let root = *tree.root().unwrap();
+		let [a, f] = find(&tree, &["A", "F"])[..] else {
+			unreachable!()
+		};
+		assert_eq!(tree.distance_between(root, a), Some(7.0));
+		assert_eq!(tree.distance_between(root, f), Some(7.0));
+		assert_eq!(
+			tree.serialize(),
+			"(F:7,(((A:1,B:1):1,C:1):1,D:1):4);"
+		);
+
+		// The midpoint falls on the current root
+		let source = "((A:1,B:1):1,(C:1,D:1):1);";
+		let mut tree = Tree::parse(source).unwrap();
+		tree.reroot_midpoint().unwrap();
+		assert_eq!(tree.serialize(), source);
+		assert!(Tree::parse("A;").unwrap().reroot_midpoint().is_err());
+	}
+
+	#[test]
+	fn ladderize_ties() {
+		let mut tree = Tree::parse("((A,B),C,(D,E),F);").unwrap();
+		tree.ladderize(true);
+		assert_eq!(tree.serialize(), "(C,F,(A,B),(D,E));");
+		tree.ladderize(false);
+		assert_eq!(tree.serialize(), "((A,B),(D,E),C,F);");
+	}
+
+	#[test]
+	fn prune_unary_parent() {
+		let mut tree =
+			Tree::parse("((A:1,(B:1,C:1):1):1,D:1);").unwrap();
+		let c = find(&tree, &["C"]);
+		tree.prune(&c);
+		// The parent of C is spliced, and its branch is joined to B
+		assert_eq!(tree.serialize(), "((A:1,B:2):1,D:1);");
+
+		// The root is left with a single child
+		let d = find(&tree, &["D"]);
+		tree.prune(&d);
+		assert_eq!(tree.serialize(), "(A:1,B:2);");
+
+		// An internal node, listed together with its descendant
+		let mut tree = Tree::parse("((A,B)AB,(C,D)CD,E);").unwrap();
+		let pruned = find(&tree, &["AB", "A", "D"]);
+		tree.prune(&pruned);
+		assert_eq!(tree.serialize(), "(C,E);");
+	}
+
+	#[test]
+	fn collapse_short_branches() {
+		let source = "((A:1,(B:1,C:1):0.25):0.5,(D:1,E:1));";
+		let mut tree = Tree::parse(source).unwrap();
+
+		tree.collapse(0.0);
+		assert_eq!(tree.serialize(), source);
+
+		// The leaf heights stay, and the branch without a length is
+		// kept
+		tree.collapse(1.0);
+		assert_eq!(
+			tree.serialize(),
+			"(A:1.5,B:1.75,C:1.75,(D:1,E:1));"
+		);
+	}
+
+	#[test]
+	fn resolve_polytomies_keeps_distances() {
+		let mut tree =
+			Tree::parse("(A:1,B:2,C:3,(D:1,E:1,F:1,G:1):1);")
+				.unwrap();
+		let before = distances(&tree);
+
+		let mut rng = Pcg64::seed_from_u64(1);
+		tree.resolve_polytomies(&mut rng);
+		let root = *tree.root().unwrap();
+		assert!(tree.preorder(root).all(|node| {
+			matches!(tree.children_of(node).len(), 0 | 2)
+		}));
+		assert_eq!(tree.leaves().count(), 7);
+		assert_same(&distances(&tree), &before);
+	}
+}
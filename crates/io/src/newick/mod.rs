@@ -1,17 +1,26 @@
 use petgraph::stable_graph::StableDiGraph;
 
-mod parse;
+use std::str::FromStr;
+
+mod attributes;
+mod edit;
+pub(crate) mod parse;
 #[cfg(feature = "python")]
 pub(crate) mod python;
+mod query;
+mod read;
 mod serialize;
 
+pub use attributes::{Attributes, Value};
+pub use parse::ParseError;
 pub use petgraph::stable_graph::NodeIndex;
+pub use read::NewickReader;
 
 #[derive(Debug, Clone, Default)]
 pub struct Node {
 	name: String,
-	#[allow(dead_code)]
-	attributes: String,
+	/// Written as a `[&key=value,...]` comment.
+	attributes: Attributes,
 	distance: Option<f64>,
 }
 
@@ -19,7 +28,7 @@ impl Node {
 	pub fn new(
 		name: String,
 		distance: Option<f64>,
-		attributes: String,
+		attributes: Attributes,
 	) -> Node {
 		Node {
 			name,
@@ -32,9 +41,29 @@ impl Node {
 		&self.name
 	}
 
+	pub fn set_name(&mut self, name: String) {
+		self.name = name;
+	}
+
 	pub fn distance(&self) -> Option<f64> {
 		self.distance
 	}
+
+	pub fn attributes(&self) -> &Attributes {
+		&self.attributes
+	}
+
+	pub fn attributes_mut(&mut self) -> &mut Attributes {
+		&mut self.attributes
+	}
+
+	pub fn set_attribute(
+		&mut self,
+		key: impl Into<String>,
+		value: impl Into<Value>,
+	) {
+		self.attributes.insert(key.into(), value.into());
+	}
 }
 
 #[derive(Debug, Clone, Default)]
@@ -48,6 +77,38 @@ impl Tree {
 		Tree::default()
 	}
 
+	/// Parses a single tree terminated by a semicolon.  Only whitespace and
+	/// comments may follow it.
+	pub fn parse(source: &str) -> Result<Tree, ParseError> {
+		let (rest, tree) = parse::tree(source)?;
+		if !parse::is_blank(rest) {
+			let rest = source.len() - rest.len();
+			return Err(ParseError::new(
+				source,
+				&source[rest..],
+				"Unexpected text after the tree",
+			));
+		}
+		Ok(tree)
+	}
+
+	/// Parses all of the semicolon-terminated trees in `source`.
+	pub fn parse_all(source: &str) -> Result<Vec<Tree>, ParseError> {
+		let mut trees = Vec::new();
+		let mut rest = source;
+		while !parse::is_blank(rest) {
+			let (after, tree) =
+				parse::tree(rest).map_err(|error| {
+					let (line, column) =
+						parse::position(source, rest);
+					error.offset(line, column)
+				})?;
+			trees.push(tree);
+			rest = after;
+		}
+		Ok(trees)
+	}
+
 	pub fn root(&self) -> Option<&NodeIndex> {
 		self.root.as_ref()
 	}
@@ -56,14 +117,27 @@ impl Tree {
 		self.root = Some(node);
 	}
 
+	/// Children of `node` in the order in which they were added.
 	pub fn children_of(&self, node: NodeIndex) -> Vec<NodeIndex> {
-		self.graph.neighbors(node).collect()
+		// `petgraph` lists the most recently added edge first
+		let mut children: Vec<_> = self.graph.neighbors(node).collect();
+		children.reverse();
+		children
 	}
 
 	pub fn get_node(&self, idx: NodeIndex) -> &Node {
 		&self.graph[idx]
 	}
 
+	pub fn get_node_mut(&mut self, idx: NodeIndex) -> &mut Node {
+		&mut self.graph[idx]
+	}
+
+	/// Indices of all nodes, in no particular order.
+	pub fn nodes(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+		self.graph.node_indices()
+	}
+
 	pub fn add_node(&mut self, node: Node) -> NodeIndex {
 		self.graph.add_node(node)
 	}
@@ -72,3 +146,11 @@ impl Tree {
 		self.graph.add_edge(from, to, ());
 	}
 }
+
+impl FromStr for Tree {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Tree, ParseError> {
+		Tree::parse(s)
+	}
+}
@@ -1,26 +1,248 @@
-#![allow(dead_code)]
-
 use nom::{
 	branch::alt,
-	bytes::{tag, take_till},
-	character::complete::{char, multispace0, none_of},
-	combinator::value,
-	multi::fold_many0,
-	number::complete::float,
-	sequence::delimited,
+	bytes::complete::{self, is_not},
+	character::complete::{anychar, char, multispace0, none_of, one_of},
+	combinator::{recognize, value},
+	multi::{fold_many0, many0},
+	number::complete::double,
+	sequence::{delimited, preceded},
 	IResult, Parser,
 };
 
-use super::Node;
+use std::fmt::{self, Display};
+
+use super::{attributes, Attributes, Node, NodeIndex, Tree};
+
+/// A syntax error, with the position where it was found.  Lines and columns
+/// start from one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	line: usize,
+	column: usize,
+	message: String,
+}
+
+impl ParseError {
+	/// `rest` is the unparsed tail of `source`.
+	pub(crate) fn new(
+		source: &str,
+		rest: &str,
+		message: impl Into<String>,
+	) -> Self {
+		let (line, column) = position(source, rest);
+
+		ParseError {
+			line,
+			column,
+			message: message.into(),
+		}
+	}
+
+	/// Moves the position by the one of the start of `source` in a larger
+	/// file.
+	pub(crate) fn offset(mut self, line: usize, column: usize) -> Self {
+		if self.line == 1 {
+			self.column += column - 1;
+		}
+		self.line += line - 1;
+		self
+	}
+
+	pub fn line(&self) -> usize {
+		self.line
+	}
+
+	pub fn column(&self) -> usize {
+		self.column
+	}
+
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} at line {}, column {}",
+			self.message, self.line, self.column
+		)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+/// Line and column of the start of `rest`, which is a tail of `source`.
+pub(crate) fn position(source: &str, rest: &str) -> (usize, usize) {
+	let consumed = &source[..source.len() - rest.len()];
+	let line = consumed.matches('\n').count() + 1;
+	let line_start = consumed.rfind('\n').map(|i| i + 1).unwrap_or(0);
+	let column = consumed[line_start..].chars().count() + 1;
+	(line, column)
+}
 
-macro_rules! ws {
-	($f:expr) => {
-		|s| delimited(multispace0, $f, multispace0).parse(s)
+/// Whether `input` only has whitespace and comments.
+pub(crate) fn is_blank(input: &str) -> bool {
+	skip_comments(input).is_empty()
+}
+
+/// Parses a single tree terminated by a semicolon.  Returns the rest of the
+/// input after the semicolon.
+pub(crate) fn tree(source: &str) -> Result<(&str, Tree), ParseError> {
+	let mut parser = TreeParser {
+		source,
+		tree: Tree::new(),
+	};
+
+	// Comments before the tree, like the `[&R]` rooting flag, carry no
+	// node information.
+	let rest = skip_comments(source);
+	if rest.is_empty() {
+		return Err(ParseError::new(source, rest, "Expected a tree"));
+	}
+	let (rest, root) = parser.subtree(rest)?;
+	parser.tree.set_root(root);
+
+	let rest = skip_comments(rest);
+	let Some(rest) = rest.strip_prefix(';') else {
+		return Err(parser.error(rest, "Expected ';'"));
 	};
+
+	Ok((rest, parser.tree))
+}
+
+struct TreeParser<'a> {
+	source: &'a str,
+	tree: Tree,
+}
+
+impl<'a> TreeParser<'a> {
+	fn error(&self, rest: &str, message: &str) -> ParseError {
+		ParseError::new(self.source, rest, message)
+	}
+
+	/// Parses with an explicit stack instead of recursion, so that deep
+	/// caterpillar trees can't overflow the call stack.
+	fn subtree(
+		&mut self,
+		input: &'a str,
+	) -> Result<(&'a str, NodeIndex), ParseError> {
+		// Children of the nodes whose parentheses are still open
+		let mut open: Vec<Vec<NodeIndex>> = Vec::new();
+		let mut rest = input;
+
+		loop {
+			rest = whitespace(rest);
+			while let Some(inner) = rest.strip_prefix('(') {
+				open.push(Vec::new());
+				rest = whitespace(inner);
+			}
+
+			let (after, node) = self.body(rest)?;
+			let mut index = self.tree.add_node(node);
+			rest = after;
+
+			// Close the parentheses which end after this node
+			loop {
+				let Some(siblings) = open.last_mut() else {
+					return Ok((rest, index));
+				};
+				siblings.push(index);
+
+				let after = whitespace(rest);
+				if let Some(after) = after.strip_prefix(',') {
+					rest = after;
+					break;
+				} else if let Some(after) =
+					after.strip_prefix(')')
+				{
+					let children =
+						open.pop().unwrap_or_default();
+					let (after, node) = self.body(after)?;
+					index = self.tree.add_node(node);
+					for child in children {
+						self.tree
+							.add_edge(index, child);
+					}
+					rest = after;
+				} else {
+					return Err(self.error(
+						after,
+						"Expected ',' or ')'",
+					));
+				}
+			}
+		}
+	}
+
+	/// Name, comments and the branch length of a node.  All of them are
+	/// optional.
+	fn body(&self, input: &'a str) -> Result<(&'a str, Node), ParseError> {
+		let mut attributes = Attributes::new();
+		let rest = self.comments(input, &mut attributes)?;
+		let (rest, name) = name(rest).map_err(|_| {
+			self.error(rest, "Unterminated quoted label")
+		})?;
+		let mut rest = self.comments(rest, &mut attributes)?;
+
+		let mut distance = None;
+		if let Some(after) = rest.strip_prefix(':') {
+			let after = self.comments(after, &mut attributes)?;
+			let (after, length) =
+				double::<_, ()>(after).map_err(|_| {
+					self.error(
+						after,
+						"Expected a branch length",
+					)
+				})?;
+			distance = Some(length);
+			rest = self.comments(after, &mut attributes)?;
+		}
+
+		Ok((rest, Node::new(name, distance, attributes)))
+	}
+
+	/// Skips comments and whitespace.  The contents of `[&...]` metadata
+	/// comments are parsed into `attributes`.
+	fn comments(
+		&self,
+		input: &'a str,
+		attributes: &mut Attributes,
+	) -> Result<&'a str, ParseError> {
+		let mut rest = whitespace(input);
+		while rest.starts_with('[') {
+			let (after, text) = comment(rest).map_err(|_| {
+				self.error(rest, "Unterminated comment")
+			})?;
+			if let Some(metadata) = text.strip_prefix('&') {
+				attributes::parse(metadata, attributes)
+					.ok_or_else(|| {
+						self.error(rest, "Invalid metadata comment")
+					})?;
+			}
+			rest = whitespace(after);
+		}
+		Ok(rest)
+	}
+}
+
+fn whitespace(input: &str) -> &str {
+	multispace0::<_, ()>(input)
+		.map(|(rest, _)| rest)
+		.unwrap_or(input)
+}
+
+fn skip_comments(input: &str) -> &str {
+	let mut rest = whitespace(input);
+	while let Ok((after, _)) = comment(rest) {
+		rest = whitespace(after);
+	}
+	rest
 }
 
 fn bare(input: &str) -> IResult<&str, String> {
-	let ch = none_of(" \t\n,;[\"");
+	let ch = none_of(" \t\r\n()[]':;,\"");
 
 	fold_many0(ch, String::new, |mut string, mut fragment| {
 		if fragment == '_' {
@@ -32,8 +254,11 @@ fn bare(input: &str) -> IResult<&str, String> {
 	.parse(input)
 }
 
-fn quoted(input: &str) -> IResult<&str, String> {
-	let ch = alt((none_of("\"\\"), value('"', tag("\\\""))));
+/// Double-quoted text, where a quote, a backslash or a `]` can be escaped
+/// with a backslash.
+pub(super) fn quoted(input: &str) -> IResult<&str, String> {
+	let escaped = preceded(char('\\'), one_of("\"\\]"));
+	let ch = alt((none_of("\"\\"), escaped));
 
 	let build = fold_many0(ch, String::new, |mut string, fragment| {
 		string.push(fragment);
@@ -43,28 +268,38 @@ fn quoted(input: &str) -> IResult<&str, String> {
 	delimited(char('"'), build, char('"')).parse(input)
 }
 
-fn name(input: &str) -> IResult<&str, String> {
-	// Try `quoted` first
-	alt((quoted, bare)).parse(input)
-}
+/// Standard Newick quoting, where a single quote is escaped by doubling it.
+fn single_quoted(input: &str) -> IResult<&str, String> {
+	// The closing quote is a prefix of an escaped one, so the streaming
+	// `tag` would ask for more input.
+	let ch = alt((none_of("'"), value('\'', complete::tag("''"))));
 
-fn comment(input: &str) -> IResult<&str, String> {
-	// XXX: can Newick contain nested brackets?
-	delimited(char('['), take_till(|c| c != ']'), char(']'))
-		.parse(input)
-		.map(|(rest, s)| (rest, s.to_owned()))
-}
+	let build = fold_many0(ch, String::new, |mut string, fragment| {
+		string.push(fragment);
+		string
+	});
 
-fn distance(input: &str) -> IResult<&str, f64> {
-	float(input).map(|(rest, f)| (rest, f as f64))
+	delimited(char('\''), build, char('\'')).parse(input)
 }
 
-fn body(input: &str) -> IResult<&str, Node> {
-	let (rest, name) = ws!(name)(input)?;
-	let (rest, attributes) = ws!(comment)(rest)?;
-	let (rest, distance) = ws!(distance)(rest)?;
+fn name(input: &str) -> IResult<&str, String> {
+	// Unterminated quotes must fail instead of falling back to an empty
+	// bare name.
+	match input.chars().next() {
+		Some('"') => quoted(input),
+		Some('\'') => single_quoted(input),
+		_ => bare(input),
+	}
+}
 
-	Ok((rest, Node::new(name, Some(distance), attributes)))
+/// Contents of a `[...]` comment, without the brackets.  A `]` escaped with
+/// a backslash doesn't end the comment.
+fn comment(input: &str) -> IResult<&str, &str> {
+	let text = recognize(many0(alt((
+		is_not("\\]"),
+		recognize((char('\\'), anychar)),
+	))));
+	delimited(char('['), text, char(']')).parse(input)
 }
 
 #[cfg(test)]
@@ -91,6 +326,7 @@ mod test {
 			("rest of it", (" of it", "rest")),
 			("under_score", ("", "under score")),
 			("__and_trailing___", ("", "  and trailing   ")),
+			("leaf:0.1", (":0.1", "leaf")),
 		];
 		check!(bare_tests, bare);
 
@@ -104,7 +340,103 @@ mod test {
 		];
 		check!(quoted_tests, quoted);
 
+		let single_quoted_tests = [
+			("'under_score'", ("", "under_score")),
+			("'it''s'", ("", "it's")),
+		];
+		check!(single_quoted_tests, single_quoted);
+
 		check!(bare_tests, name);
 		check!(quoted_tests, name);
+		check!(single_quoted_tests, name);
+	}
+
+	#[test]
+	fn test_comment() {
+		assert_eq!(comment("[&rate=1]:0.5"), Ok((":0.5", "&rate=1")));
+		assert_eq!(comment(r#"[&a="\]"]"#), Ok(("", r#"&a="\]""#)));
+		assert!(comment("[unterminated").is_err());
+	}
+
+	#[test]
+	fn trees() {
+		let source = "[&R] ((A:1,'B c':2.5e-1)AB:0.5, C)[&rate=2]root;";
+		let tree = Tree::parse(source).unwrap();
+		let root = *tree.root().unwrap();
+		assert_eq!(tree.get_node(root).name(), "root");
+		assert_eq!(tree.get_node(root).distance(), None);
+
+		let children = tree.children_of(root);
+		let ab = tree.get_node(children[0]);
+		assert_eq!((ab.name(), ab.distance()), ("AB", Some(0.5)));
+		let b = tree.get_node(tree.children_of(children[0])[1]);
+		assert_eq!((b.name(), b.distance()), ("B c", Some(0.25)));
+
+		assert_eq!(
+			tree.serialize(),
+			"((A:1,'B c':0.25)AB:0.5,C)root[&rate=2];"
+		);
+
+		// Branch comments, like in BEAST output
+		let tree = Tree::parse("(A:[&rate=1]1[x],B);").unwrap();
+		let a = tree.children_of(*tree.root().unwrap())[0];
+		assert_eq!(tree.get_node(a).distance(), Some(1.0));
+		assert_eq!(tree.serialize(), "(A[&rate=1]:1,B);");
+
+		// Metadata from several comments is merged
+		let tree = Tree::parse(
+			r#"(A[&rate=1][&range={0.5,2},state="x"]:1,B);"#,
+		)
+		.unwrap();
+		let a = tree
+			.get_node(tree.children_of(*tree.root().unwrap())[0]);
+		assert_eq!(a.attributes().len(), 3);
+		assert_eq!(
+			tree.serialize(),
+			r#"(A[&range={0.5,2},rate=1,state="x"]:1,B);"#
+		);
+
+		let trees = Tree::parse_all("(A,B);\n(C,D);\n").unwrap();
+		assert_eq!(trees.len(), 2);
+		assert!(Tree::parse("(A,B);(C,D);").is_err());
+		assert!("A;".parse::<Tree>().is_ok());
+	}
+
+	#[test]
+	fn deep_tree() {
+		// A caterpillar with a nesting level per leaf
+		let depth = 100_000;
+		let mut source = "(".repeat(depth);
+		source += "A0";
+		for i in 1..=depth {
+			source += &format!(",A{i}:1)");
+		}
+		source += ";";
+
+		let tree = Tree::parse(&source).unwrap();
+		assert_eq!(tree.leaves().count(), depth + 1);
+		let root = *tree.root().unwrap();
+		let last = format!("A{depth}");
+		assert_eq!(
+			tree.get_node(tree.children_of(root)[1]).name(),
+			last
+		);
+	}
+
+	#[test]
+	fn error_position() {
+		let error = tree("((A,B),\n  C;").unwrap_err();
+		assert_eq!((error.line(), error.column()), (2, 4));
+		assert_eq!(error.message(), "Expected ',' or ')'");
+
+		let error = tree("(A:x,B);").unwrap_err();
+		assert_eq!((error.line(), error.column()), (1, 4));
+
+		let error = tree("(A,B)").unwrap_err();
+		assert_eq!(error.message(), "Expected ';'");
+
+		let error = tree("(A[&rate={1],B);").unwrap_err();
+		assert_eq!((error.line(), error.column()), (1, 3));
+		assert_eq!(error.message(), "Invalid metadata comment");
 	}
 }
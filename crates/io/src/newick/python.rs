@@ -1,8 +1,11 @@
-use pyo3::prelude::*;
+use anyhow::{anyhow, ensure, Result};
+use pyo3::{exceptions::PyTypeError, prelude::*};
+use rng::PyRng;
 
 use std::sync::{Arc, Mutex, MutexGuard};
 
-use super::{Node, Tree};
+use super::{Attributes, NewickReader, Node, NodeIndex, Tree, Value};
+use crate::python::{open, Source};
 
 #[derive(Debug, Clone)]
 #[pyclass(name = "Node", module = "aspartik.io.newick", frozen)]
@@ -17,23 +20,24 @@ impl PyNode {
 	}
 }
 
+impl From<Node> for PyNode {
+	fn from(value: Node) -> Self {
+		PyNode {
+			inner: Arc::new(Mutex::new(value)),
+		}
+	}
+}
+
 #[pymethods]
 impl PyNode {
 	#[new]
 	#[pyo3(signature = (name, attributes = None, distance = None))]
 	fn new(
 		name: String,
-		attributes: Option<String>,
+		attributes: Option<Attributes>,
 		distance: Option<f64>,
 	) -> Self {
-		let node = Node::new(
-			name,
-			distance,
-			attributes.unwrap_or_default(),
-		);
-		PyNode {
-			inner: Arc::new(Mutex::new(node)),
-		}
+		Node::new(name, distance, attributes.unwrap_or_default()).into()
 	}
 
 	#[getter]
@@ -45,6 +49,11 @@ impl PyNode {
 	fn distance(&self) -> Option<f64> {
 		self.inner().distance
 	}
+
+	#[getter]
+	fn attributes(&self) -> Attributes {
+		self.inner().attributes.clone()
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -60,16 +69,232 @@ impl PyTree {
 	}
 }
 
+/// A node passed from Python, either by its index or by its name.
+#[derive(FromPyObject)]
+enum NodeRef {
+	Index(usize),
+	Name(String),
+}
+
+impl NodeRef {
+	fn resolve(self, tree: &Tree) -> Result<NodeIndex> {
+		match self {
+			NodeRef::Index(index) => {
+				let node = NodeIndex::new(index);
+				ensure!(
+					tree.contains(node),
+					"No node with index {index}"
+				);
+				Ok(node)
+			}
+			NodeRef::Name(name) => {
+				tree.find(&name).ok_or_else(|| {
+					anyhow!("No node named '{name}'")
+				})
+			}
+		}
+	}
+}
+
+fn resolve_all(tree: &Tree, nodes: Vec<NodeRef>) -> Result<Vec<NodeIndex>> {
+	nodes.into_iter().map(|node| node.resolve(tree)).collect()
+}
+
+fn indices(nodes: impl Iterator<Item = NodeIndex>) -> Vec<usize> {
+	nodes.map(|node| node.index()).collect()
+}
+
+impl From<Tree> for PyTree {
+	fn from(value: Tree) -> Self {
+		PyTree {
+			inner: Arc::new(Mutex::new(value)),
+		}
+	}
+}
+
 #[pymethods]
 impl PyTree {
+	/// Parses `newick` if it's passed, otherwise creates an empty tree.
 	#[new]
-	fn new() -> Self {
-		PyTree {
-			inner: Arc::new(Mutex::new(Tree::new())),
-		}
+	#[pyo3(signature = (newick = None))]
+	fn new(newick: Option<&str>) -> Result<Self> {
+		let tree = match newick {
+			Some(newick) => Tree::parse(newick)?,
+			None => Tree::new(),
+		};
+		Ok(tree.into())
+	}
+
+	/// Parses all of the trees in a string.
+	#[staticmethod]
+	fn parse_all(newick: &str) -> Result<Vec<Self>> {
+		let trees = Tree::parse_all(newick)?;
+		Ok(trees.into_iter().map(Into::into).collect())
 	}
 
 	fn __str__(&self) -> String {
 		self.inner().serialize()
 	}
+
+	#[getter]
+	fn root(&self) -> Option<usize> {
+		self.inner().root().map(|root| root.index())
+	}
+
+	/// Returns a copy of the node.
+	fn node(&self, node: NodeRef) -> Result<PyNode> {
+		let tree = self.inner();
+		let node = node.resolve(&tree)?;
+		Ok(tree.get_node(node).clone().into())
+	}
+
+	fn find(&self, name: &str) -> Option<usize> {
+		self.inner().find(name).map(|node| node.index())
+	}
+
+	fn parent_of(&self, node: NodeRef) -> Result<Option<usize>> {
+		let tree = self.inner();
+		let node = node.resolve(&tree)?;
+		Ok(tree.parent_of(node).map(|parent| parent.index()))
+	}
+
+	fn children_of(&self, node: NodeRef) -> Result<Vec<usize>> {
+		let tree = self.inner();
+		let node = node.resolve(&tree)?;
+		Ok(indices(tree.children_of(node).into_iter()))
+	}
+
+	fn leaves(&self) -> Vec<usize> {
+		indices(self.inner().leaves())
+	}
+
+	fn descendants(&self, node: NodeRef) -> Result<Vec<usize>> {
+		let tree = self.inner();
+		let node = node.resolve(&tree)?;
+		Ok(indices(tree.descendants(node)))
+	}
+
+	fn mrca(&self, nodes: Vec<NodeRef>) -> Result<usize> {
+		let tree = self.inner();
+		let nodes = resolve_all(&tree, nodes)?;
+		let mrca = tree.mrca(&nodes).ok_or_else(|| {
+			anyhow!("The nodes have no common ancestor")
+		})?;
+		Ok(mrca.index())
+	}
+
+	fn distance_between(&self, a: NodeRef, b: NodeRef) -> Result<f64> {
+		let tree = self.inner();
+		let (a, b) = (a.resolve(&tree)?, b.resolve(&tree)?);
+		tree.distance_between(a, b)
+			.ok_or_else(|| anyhow!("The nodes aren't connected"))
+	}
+
+	fn cophenetic(&self) -> Vec<Vec<f64>> {
+		self.inner().cophenetic()
+	}
+
+	fn reroot(&self, node: NodeRef) -> Result<()> {
+		let mut tree = self.inner();
+		let node = node.resolve(&tree)?;
+		tree.reroot(node);
+		Ok(())
+	}
+
+	fn reroot_outgroup(&self, outgroup: Vec<NodeRef>) -> Result<()> {
+		let mut tree = self.inner();
+		let outgroup = resolve_all(&tree, outgroup)?;
+		tree.reroot_outgroup(&outgroup)
+	}
+
+	fn reroot_midpoint(&self) -> Result<()> {
+		self.inner().reroot_midpoint()
+	}
+
+	#[pyo3(signature = (ascending = true))]
+	fn ladderize(&self, ascending: bool) {
+		self.inner().ladderize(ascending)
+	}
+
+	fn prune(&self, nodes: Vec<NodeRef>) -> Result<()> {
+		let mut tree = self.inner();
+		let nodes = resolve_all(&tree, nodes)?;
+		tree.prune(&nodes);
+		Ok(())
+	}
+
+	fn collapse(&self, threshold: f64) {
+		self.inner().collapse(threshold)
+	}
+
+	fn resolve_polytomies(&self, rng: Py<PyRng>) {
+		self.inner().resolve_polytomies(&mut *rng.get().inner())
+	}
+}
+
+#[pyclass(name = "NewickReader", module = "aspartik.io.newick", frozen)]
+pub(crate) struct PyNewickReader {
+	inner: parking_lot::Mutex<NewickReader<Source>>,
+}
+
+#[pymethods]
+impl PyNewickReader {
+	/// `source` is either a path or a file-like object.
+	#[new]
+	fn new(source: &Bound<PyAny>) -> Result<Self> {
+		Ok(Self {
+			inner: parking_lot::Mutex::new(NewickReader::new(
+				open(source)?,
+			)),
+		})
+	}
+
+	fn __iter__(this: PyRef<Self>) -> PyRef<Self> {
+		this
+	}
+
+	fn __next__(&self) -> Option<Result<PyTree>> {
+		self.inner.lock().next().map(|r| r.map(Into::into))
+	}
+}
+
+impl<'py> FromPyObject<'py> for Value {
+	fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+		if let Ok(text) = obj.extract::<String>() {
+			return Ok(Value::Text(text));
+		}
+		if let Ok(number) = obj.extract::<f64>() {
+			return Ok(Value::Number(number));
+		}
+		if let Ok(items) = obj.try_iter() {
+			let values = items
+				.map(|item| item?.extract())
+				.collect::<PyResult<_>>()?;
+			return Ok(Value::Set(values));
+		}
+
+		Err(PyTypeError::new_err(
+			"Attribute values must be numbers, strings, or collections of them",
+		))
+	}
+}
+
+impl<'py> IntoPyObject<'py> for Value {
+	type Target = PyAny;
+	type Output = Bound<'py, PyAny>;
+	type Error = PyErr;
+
+	fn into_pyobject(self, py: Python<'py>) -> PyResult<Self::Output> {
+		Ok(match self {
+			Value::Number(value) => {
+				value.into_pyobject(py)?.into_any()
+			}
+			Value::Text(value) => {
+				value.into_pyobject(py)?.into_any()
+			}
+			Value::Set(values) => {
+				values.into_pyobject(py)?.into_any()
+			}
+		})
+	}
 }
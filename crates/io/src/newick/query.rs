@@ -0,0 +1,294 @@
+use petgraph::Direction;
+
+use std::{
+	collections::{HashMap, HashSet},
+	iter,
+};
+
+use super::{NodeIndex, Tree};
+
+impl Tree {
+	pub fn contains(&self, node: NodeIndex) -> bool {
+		self.graph.contains_node(node)
+	}
+
+	pub fn parent_of(&self, node: NodeIndex) -> Option<NodeIndex> {
+		self.graph
+			.neighbors_directed(node, Direction::Incoming)
+			.next()
+	}
+
+	pub fn is_leaf(&self, node: NodeIndex) -> bool {
+		self.graph.neighbors(node).next().is_none()
+	}
+
+	/// `node` and all of its descendants, parents before children and
+	/// siblings in order.
+	pub fn preorder(
+		&self,
+		node: NodeIndex,
+	) -> impl Iterator<Item = NodeIndex> + '_ {
+		let mut stack = vec![node];
+		iter::from_fn(move || {
+			let node = stack.pop()?;
+			stack.extend(self.children_of(node).into_iter().rev());
+			Some(node)
+		})
+	}
+
+	/// Descendants of `node` in preorder, without `node` itself.
+	pub fn descendants(
+		&self,
+		node: NodeIndex,
+	) -> impl Iterator<Item = NodeIndex> + '_ {
+		self.preorder(node).skip(1)
+	}
+
+	/// Leaves under `node` from left to right.
+	pub fn leaves_of(
+		&self,
+		node: NodeIndex,
+	) -> impl Iterator<Item = NodeIndex> + '_ {
+		self.preorder(node).filter(|node| self.is_leaf(*node))
+	}
+
+	/// All leaves from left to right.  Empty if the root isn't set.
+	pub fn leaves(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+		self.root.into_iter().flat_map(|root| self.leaves_of(root))
+	}
+
+	/// Parent of `node`, its parent and so on up to the root.
+	pub fn ancestors(
+		&self,
+		node: NodeIndex,
+	) -> impl Iterator<Item = NodeIndex> + '_ {
+		iter::successors(self.parent_of(node), |node| {
+			self.parent_of(*node)
+		})
+	}
+
+	/// The first node called `name` in preorder.
+	pub fn find(&self, name: &str) -> Option<NodeIndex> {
+		let root = self.root?;
+		self.preorder(root)
+			.find(|node| self.graph[*node].name == name)
+	}
+
+	/// Most recent common ancestor: the deepest node which has all of
+	/// `nodes` in its subtree, including itself.  `None` if `nodes` is empty
+	/// or they aren't connected.
+	pub fn mrca(&self, nodes: &[NodeIndex]) -> Option<NodeIndex> {
+		let (first, rest) = nodes.split_first()?;
+
+		// Lineage of the first node, from the deepest one
+		let mut candidates: Vec<_> = iter::once(*first)
+			.chain(self.ancestors(*first))
+			.collect();
+		for node in rest {
+			let lineage: HashSet<_> = iter::once(*node)
+				.chain(self.ancestors(*node))
+				.collect();
+			let deepest =
+				candidates.iter().position(|candidate| {
+					lineage.contains(candidate)
+				})?;
+			candidates.drain(..deepest);
+		}
+
+		candidates.first().copied()
+	}
+
+	/// Sum of the branch lengths on the path between `a` and `b`.  Missing
+	/// lengths count as zero.
+	pub fn distance_between(
+		&self,
+		a: NodeIndex,
+		b: NodeIndex,
+	) -> Option<f64> {
+		let mrca = self.mrca(&[a, b])?;
+		let up = |node| -> f64 {
+			iter::once(node)
+				.chain(self.ancestors(node))
+				.take_while(|node| *node != mrca)
+				.map(|node| self.length(node))
+				.sum()
+		};
+		Some(up(a) + up(b))
+	}
+
+	/// Matrix of path lengths between all pairs of leaves, in the order of
+	/// [`leaves`][Self::leaves].  Missing branch lengths count as zero.
+	pub fn cophenetic(&self) -> Vec<Vec<f64>> {
+		let leaves: Vec<_> = self.leaves().collect();
+		let mut out = vec![vec![0.0; leaves.len()]; leaves.len()];
+		let Some(root) = self.root else {
+			return out;
+		};
+
+		let indices: HashMap<_, _> = leaves
+			.iter()
+			.enumerate()
+			.map(|(i, leaf)| (*leaf, i))
+			.collect();
+		// Leaves of the visited subtrees and their distances to the
+		// subtree roots.  Pairs of leaves from different children meet at
+		// their MRCA, so every distance is set exactly once.
+		let mut subtrees: HashMap<NodeIndex, Vec<(usize, f64)>> =
+			HashMap::new();
+		let order: Vec<_> = self.preorder(root).collect();
+		for node in order.into_iter().rev() {
+			let mut below: Vec<(usize, f64)> = Vec::new();
+			if let Some(i) = indices.get(&node) {
+				below.push((*i, 0.0));
+			}
+
+			for child in self.children_of(node) {
+				let length = self.length(child);
+				let subtree = subtrees
+					.remove(&child)
+					.unwrap_or_default();
+				for &(i, distance) in &subtree {
+					for &(j, other) in &below {
+						let total = distance
+							+ length + other;
+						out[i][j] = total;
+						out[j][i] = total;
+					}
+				}
+				below.extend(subtree
+					.into_iter()
+					.map(|(i, d)| (i, d + length)));
+			}
+
+			subtrees.insert(node, below);
+		}
+
+		out
+	}
+
+	/// Length of the branch above `node`, zero if it's missing.
+	pub(crate) fn length(&self, node: NodeIndex) -> f64 {
+		self.graph[node].distance.unwrap_or(0.0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn traversal() {
+		let tree =
+			Tree::parse("((A:1,B:2)AB:1,(C:1,D:1,E:3)CDE:2)root;")
+				.unwrap();
+		let name =
+			|node: NodeIndex| tree.get_node(node).name().to_owned();
+		let names = |nodes: Vec<NodeIndex>| {
+			nodes.into_iter().map(name).collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			names(tree.leaves().collect()),
+			["A", "B", "C", "D", "E"]
+		);
+		let ab = tree.find("AB").unwrap();
+		assert_eq!(names(tree.descendants(ab).collect()), ["A", "B"]);
+		let b = tree.find("B").unwrap();
+		assert_eq!(names(tree.ancestors(b).collect()), ["AB", "root"]);
+
+		let c = tree.find("C").unwrap();
+		let e = tree.find("E").unwrap();
+		assert_eq!(name(tree.mrca(&[c, e]).unwrap()), "CDE");
+		assert_eq!(name(tree.mrca(&[c, e, b]).unwrap()), "root");
+		assert_eq!(tree.mrca(&[ab, b]), Some(ab));
+		assert_eq!(tree.mrca(&[]), None);
+
+		assert_eq!(tree.distance_between(b, e), Some(8.0));
+		let matrix = tree.cophenetic();
+		assert_eq!(matrix[1][4], 8.0);
+		assert_eq!(matrix[4][1], 8.0);
+		assert_eq!(matrix[0][1], 3.0);
+		assert_eq!(matrix[2][3], 2.0);
+		assert_eq!(matrix[3][3], 0.0);
+	}
+	#[test]
+	fn mrca_of_nested_nodes() {
+		let tree = Tree::parse("((A,(B,C)BC)ABC,D)root;").unwrap();
+		let node = |name| tree.find(name).unwrap();
+		let name = |node| tree.get_node(node).name().to_owned();
+
+		assert_eq!(tree.mrca(&[node("B")]), Some(node("B")));
+		assert_eq!(
+			name(tree.mrca(&[node("B"), node("C")]).unwrap()),
+			"BC"
+		);
+		assert_eq!(
+			name(tree.mrca(&[node("C"), node("A")]).unwrap()),
+			"ABC"
+		);
+		// An ancestor of the other nodes is their MRCA
+		assert_eq!(
+			name(tree
+				.mrca(&[node("B"), node("ABC"), node("C")])
+				.unwrap()),
+			"ABC"
+		);
+		assert_eq!(
+			name(tree.mrca(&[node("A"), node("D")]).unwrap()),
+			"root"
+		);
+	}
+
+	#[test]
+	fn distance_between_nodes() {
+		let tree = Tree::parse("((A:1,B)AB:2,C:4)root;").unwrap();
+		let node = |name| tree.find(name).unwrap();
+
+		assert_eq!(
+			tree.distance_between(node("A"), node("A")),
+			Some(0.0)
+		);
+		// Between a node and its ancestor
+		assert_eq!(
+			tree.distance_between(node("A"), node("root")),
+			Some(3.0)
+		);
+		assert_eq!(
+			tree.distance_between(node("root"), node("A")),
+			Some(3.0)
+		);
+		// The missing length of B counts as zero
+		assert_eq!(
+			tree.distance_between(node("B"), node("C")),
+			Some(6.0)
+		);
+		assert_eq!(
+			tree.distance_between(node("A"), node("B")),
+			Some(1.0)
+		);
+	}
+
+	#[test]
+	fn cophenetic_symmetry() {
+		let tree = Tree::parse(
+			"(((A:1,B:2):0.5,C:3):1,(D:0.25,E:1,F:2):4,G:7);",
+		)
+		.unwrap();
+		let leaves: Vec<_> = tree.leaves().collect();
+		let matrix = tree.cophenetic();
+		assert_eq!(matrix.len(), leaves.len());
+
+		for (i, a) in leaves.iter().enumerate() {
+			assert_eq!(matrix[i][i], 0.0);
+			for (j, b) in leaves.iter().enumerate() {
+				assert_eq!(matrix[i][j], matrix[j][i]);
+				assert_eq!(
+					Some(matrix[i][j]),
+					tree.distance_between(*a, *b)
+				);
+			}
+		}
+
+		assert!(Tree::new().cophenetic().is_empty());
+	}
+}
@@ -0,0 +1,158 @@
+use anyhow::Result;
+
+use std::io::{BufRead, BufReader, Read};
+
+use super::{parse, Tree};
+
+/// Reads semicolon-terminated trees one by one, so that large tree files
+/// don't have to be loaded into memory at once.
+pub struct NewickReader<R: Read> {
+	reader: BufReader<R>,
+	/// Text which has been read, but doesn't form a full tree yet.
+	pending: String,
+	/// Line and column of the start of `pending` in the input.
+	line: usize,
+	column: usize,
+}
+
+impl<R: Read> NewickReader<R> {
+	/// Creates a Newick reader from a byte reader.  The reader is wrapped
+	/// in `BufReader` internally, so there's no need for the caller to
+	/// buffer it manually.
+	pub fn new(reader: R) -> Self {
+		NewickReader {
+			reader: BufReader::new(reader),
+			pending: String::new(),
+			line: 1,
+			column: 1,
+		}
+	}
+
+	/// Parses the first `length` bytes of `pending`.
+	fn parse_pending(&mut self, length: usize) -> Result<Tree> {
+		let chunk: String = self.pending.drain(..length).collect();
+
+		let result = parse::tree(&chunk)
+			.map(|(_, tree)| tree)
+			.map_err(|error| error.offset(self.line, self.column));
+
+		let (line, column) = parse::position(&chunk, "");
+		if line == 1 {
+			self.column += column - 1;
+		} else {
+			self.column = column;
+		}
+		self.line += line - 1;
+
+		Ok(result?)
+	}
+}
+
+impl<R: Read> Iterator for NewickReader<R> {
+	type Item = Result<Tree>;
+
+	fn next(&mut self) -> Option<Result<Tree>> {
+		loop {
+			if let Some(end) = tree_end(&self.pending) {
+				return Some(self.parse_pending(end + 1));
+			}
+
+			match self.reader.read_line(&mut self.pending) {
+				Ok(0) if parse::is_blank(&self.pending) => {
+					return None;
+				}
+				// An unterminated tree at the end of the input
+				Ok(0) => {
+					let length = self.pending.len();
+					return Some(self.parse_pending(length));
+				}
+				Ok(_) => continue,
+				Err(err) => return Some(Err(err.into())),
+			}
+		}
+	}
+}
+
+/// Byte index of the semicolon which ends the first tree in `text`, skipping
+/// the ones in quotes and comments.
+fn tree_end(text: &str) -> Option<usize> {
+	let mut chars = text.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			';' => return Some(i),
+			// A backslash escapes the next character, like in the parser
+			'[' => loop {
+				match chars.next()?.1 {
+					'\\' => {
+						chars.next()?;
+					}
+					']' => break,
+					_ => (),
+				}
+			},
+			'\'' => {
+				chars.find(|(_, c)| *c == '\'')?;
+			}
+			'"' => loop {
+				match chars.next()?.1 {
+					'\\' => {
+						chars.next()?;
+					}
+					'"' => break,
+					_ => (),
+				}
+			},
+			_ => (),
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn multiple_trees() {
+		let source = "(A,B);\n[a comment; with a semicolon]\n('C;',D)\n;((E,F),\nG:x);\n";
+		let mut reader = NewickReader::new(source.as_bytes());
+
+		assert_eq!(
+			reader.next().unwrap().unwrap().serialize(),
+			"(A,B);"
+		);
+		assert_eq!(
+			reader.next().unwrap().unwrap().serialize(),
+			"('C;',D);"
+		);
+
+		let error = reader.next().unwrap().unwrap_err();
+		let error = error.downcast::<parse::ParseError>().unwrap();
+		assert_eq!((error.line(), error.column()), (5, 3));
+
+		assert!(reader.next().is_none());
+	}
+
+	#[test]
+	fn escaped_comment_bracket() {
+		let source = "(A[&a=\"x\\];y\"],B);\n(C,D);\n";
+		let mut reader = NewickReader::new(source.as_bytes());
+
+		let tree = reader.next().unwrap().unwrap();
+		assert_eq!(tree.serialize(), "(A[&a=\"x\\];y\"],B);");
+		assert_eq!(
+			reader.next().unwrap().unwrap().serialize(),
+			"(C,D);"
+		);
+		assert!(reader.next().is_none());
+	}
+
+	#[test]
+	fn unterminated() {
+		let mut reader =
+			NewickReader::new("(A,B);\n(C,D)\n".as_bytes());
+		assert!(reader.next().unwrap().is_ok());
+		assert!(reader.next().unwrap().is_err());
+		assert!(reader.next().is_none());
+	}
+}
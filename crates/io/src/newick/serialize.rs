@@ -1,17 +1,31 @@
 use std::fmt::{Result, Write};
 
-use super::{Node, NodeIndex, Tree};
+use super::{attributes, Node, NodeIndex, Tree};
 
 impl Node {
 	pub fn serialize_to<W: Write>(&self, writer: &mut W) -> Result {
-		if self.name.contains(['"', ',', ';', '[', ' ', '\t']) {
-			writer.write_char('"')?;
-			writer.write_str(&self.name.replace("\"", "\\\""))?;
-			writer.write_char('"')?;
+		// Bare names can't have punctuation, and their underscores are
+		// read as spaces.
+		let special = [
+			' ', '\t', '\r', '\n', '(', ')', '[', ']', '\'', '"',
+			':', ';', ',', '_',
+		];
+		if self.name.contains(special) {
+			writer.write_char('\'')?;
+			writer.write_str(&self.name.replace('\'', "''"))?;
+			writer.write_char('\'')?;
 		} else {
 			writer.write_str(&self.name)?;
 		}
 
+		if !self.attributes.is_empty() {
+			writer.write_str("[&")?;
+			writer.write_str(&attributes::serialize(
+				&self.attributes,
+			))?;
+			writer.write_char(']')?;
+		}
+
 		if let Some(distance) = self.distance {
 			writer.write_char(':')?;
 			writer.write_str(&distance.to_string())?;
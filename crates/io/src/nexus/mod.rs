@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+
+use std::{borrow::Cow, io::Read, str::FromStr};
+
+use crate::newick::{ParseError, Tree};
+use data::seq::{Character, Seq};
+
+mod parse;
+#[cfg(feature = "python")]
+pub(crate) mod python;
+mod write;
+
+pub use write::NexusTreesWriter;
+
+/// Contents of a NEXUS file.  Only the `TAXA`, `DATA`/`CHARACTERS` and `TREES`
+/// blocks are read, the rest are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Nexus {
+	taxa: Vec<String>,
+	characters: Vec<Characters>,
+	trees: Vec<(String, Tree)>,
+}
+
+impl Nexus {
+	pub fn parse(source: &str) -> Result<Nexus, ParseError> {
+		parse::nexus(source)
+	}
+
+	/// Reads the whole file from `reader` and parses it.
+	pub fn read<R: Read>(mut reader: R) -> Result<Nexus> {
+		let mut source = String::new();
+		reader.read_to_string(&mut source)?;
+		Ok(Nexus::parse(&source)?)
+	}
+
+	/// Taxa from the `TAXA` block.  If the file doesn't have one, these are
+	/// the taxa of the first character matrix.
+	pub fn taxa(&self) -> &[String] {
+		&self.taxa
+	}
+
+	/// Character matrices, one per `DATA` or `CHARACTERS` block.
+	pub fn characters(&self) -> &[Characters] {
+		&self.characters
+	}
+
+	/// Trees with their names.  Taxon numbers from `TRANSLATE` tables are
+	/// replaced with the full names.
+	pub fn trees(&self) -> &[(String, Tree)] {
+		&self.trees
+	}
+
+	pub fn into_trees(self) -> Vec<(String, Tree)> {
+		self.trees
+	}
+}
+
+impl FromStr for Nexus {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Nexus, ParseError> {
+		Nexus::parse(s)
+	}
+}
+
+/// A `MATRIX` from a `DATA` or `CHARACTERS` block.
+#[derive(Debug, Clone)]
+pub struct Characters {
+	datatype: String,
+	missing: char,
+	gap: Option<char>,
+	matrix: Vec<(String, String)>,
+}
+
+impl Default for Characters {
+	fn default() -> Self {
+		Characters {
+			datatype: "STANDARD".to_owned(),
+			missing: '?',
+			gap: None,
+			matrix: Vec::new(),
+		}
+	}
+}
+
+impl Characters {
+	/// Upper-cased value of `FORMAT DATATYPE`, `STANDARD` by default.
+	pub fn datatype(&self) -> &str {
+		&self.datatype
+	}
+
+	pub fn missing(&self) -> char {
+		self.missing
+	}
+
+	pub fn gap(&self) -> Option<char> {
+		self.gap
+	}
+
+	/// Taxon names and their character data, exactly as they are in the
+	/// file, but with interleaved parts joined together.
+	pub fn matrix(&self) -> &[(String, String)] {
+		&self.matrix
+	}
+
+	/// Converts the rows of the matrix into sequences.  Characters are
+	/// upper-cased, the gap symbol is replaced by `-` and the missing one
+	/// by `N`, following the IUPAC codes.
+	pub fn sequences<C: Character>(&self) -> Result<Vec<(String, Seq<C>)>> {
+		self.matrix
+			.iter()
+			.map(|(name, row)| {
+				let row: String = row
+					.chars()
+					.map(|c| match c {
+						c if Some(c) == self.gap => '-',
+						c if c == self.missing => 'N',
+						c => c.to_ascii_uppercase(),
+					})
+					.collect();
+				let seq = Seq::try_from(row.as_str())
+					.with_context(|| {
+						format!("Invalid characters for the taxon '{name}'")
+					})?;
+				Ok((name.clone(), seq))
+			})
+			.collect()
+	}
+}
+
+/// Quotes `word` if it can't be written as a bare NEXUS word.
+pub(crate) fn quote(word: &str) -> Cow<'_, str> {
+	let special = [
+		' ', '\t', '\r', '\n', '(', ')', '[', ']', '{', '}', '\'', '"',
+		':', ';', ',', '=',
+	];
+	if word.is_empty() || word.contains(special) {
+		Cow::Owned(format!("'{}'", word.replace('\'', "''")))
+	} else {
+		Cow::Borrowed(word)
+	}
+}
+
+/// Like [`quote`], but also quotes underscores, which are read as spaces in
+/// bare taxon names.
+pub(crate) fn quote_label(label: &str) -> Cow<'_, str> {
+	if label.contains('_') {
+		Cow::Owned(format!("'{}'", label.replace('\'', "''")))
+	} else {
+		quote(label)
+	}
+}
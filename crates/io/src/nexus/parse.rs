@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+
+use super::{Characters, Nexus};
+use crate::newick::{parse as newick, ParseError, Tree};
+
+type Options = Vec<(String, Option<String>)>;
+
+/// Characters which always form a token of their own.
+const PUNCTUATION: [char; 5] = [';', '=', ',', '(', ')'];
+
+pub(super) fn nexus(source: &str) -> Result<Nexus, ParseError> {
+	let mut parser = Parser {
+		source,
+		rest: source,
+		nexus: Nexus::default(),
+	};
+	parser.file()?;
+	Ok(parser.nexus)
+}
+
+struct Parser<'a> {
+	source: &'a str,
+	/// Unparsed tail of `source`.
+	rest: &'a str,
+	nexus: Nexus,
+}
+
+impl<'a> Parser<'a> {
+	fn error(&self, message: impl Into<String>) -> ParseError {
+		ParseError::new(self.source, self.rest, message)
+	}
+
+	fn file(&mut self) -> Result<(), ParseError> {
+		self.skip()?;
+		let header = self.rest.get(..6);
+		if !header.is_some_and(|h| h.eq_ignore_ascii_case("#NEXUS")) {
+			return Err(self.error("Expected '#NEXUS'"));
+		}
+		self.rest = &self.rest[6..];
+
+		loop {
+			self.skip()?;
+			if self.rest.is_empty() {
+				return Ok(());
+			}
+
+			let begin = self.word()?;
+			if !begin.is_some_and(|w| {
+				w.eq_ignore_ascii_case("BEGIN")
+			}) {
+				return Err(self.error("Expected 'BEGIN'"));
+			}
+			let name = self.expect_word("a block name")?;
+			self.expect(';')?;
+
+			match name.to_ascii_uppercase().as_str() {
+				"TAXA" => self.taxa()?,
+				"DATA" | "CHARACTERS" => self.characters()?,
+				"TREES" => self.trees()?,
+				_ => {
+					while self.command()?.is_some() {
+						self.skip_command()?;
+					}
+				}
+			}
+		}
+	}
+
+	/// Reads the name of the next command, or returns `None` if the block
+	/// has ended.
+	fn command(&mut self) -> Result<Option<String>, ParseError> {
+		let command = self.expect_word("a command")?;
+		let command = command.to_ascii_uppercase();
+		if command == "END" || command == "ENDBLOCK" {
+			self.expect(';')?;
+			Ok(None)
+		} else {
+			Ok(Some(command))
+		}
+	}
+
+	fn taxa(&mut self) -> Result<(), ParseError> {
+		let mut ntax = None;
+		let mut labels = Vec::new();
+
+		while let Some(command) = self.command()? {
+			match command.as_str() {
+				"DIMENSIONS" => {
+					let options = self.options()?;
+					ntax = self
+						.number(&options, "NTAX")?
+						.or(ntax);
+				}
+				"TAXLABELS" => {
+					while let Some(label) = self.label()? {
+						labels.push(label);
+					}
+					self.expect(';')?;
+				}
+				_ => self.skip_command()?,
+			}
+		}
+
+		if ntax.is_some_and(|n| n != labels.len()) {
+			return Err(self.error(format!(
+				"Expected {} taxa, found {}",
+				ntax.unwrap_or_default(),
+				labels.len()
+			)));
+		}
+		self.nexus.taxa = labels;
+
+		Ok(())
+	}
+
+	fn characters(&mut self) -> Result<(), ParseError> {
+		let mut characters = Characters::default();
+		let mut ntax = None;
+		let mut nchar = None;
+		let mut interleave = false;
+
+		while let Some(command) = self.command()? {
+			match command.as_str() {
+				"DIMENSIONS" => {
+					let options = self.options()?;
+					ntax = self
+						.number(&options, "NTAX")?
+						.or(ntax);
+					nchar = self
+						.number(&options, "NCHAR")?
+						.or(nchar);
+				}
+				"FORMAT" => {
+					let options = self.options()?;
+					self.format(
+						&options,
+						&mut characters,
+						&mut interleave,
+					)?;
+				}
+				"MATRIX" => {
+					characters.matrix = if interleave {
+						self.interleaved()?
+					} else if let Some(nchar) = nchar {
+						self.sequential(nchar)?
+					} else {
+						return Err(self.error(
+							"NCHAR must be set for a sequential matrix",
+						));
+					};
+					self.check_matrix(
+						&characters,
+						ntax,
+						nchar,
+					)?;
+				}
+				_ => self.skip_command()?,
+			}
+		}
+
+		if self.nexus.taxa.is_empty() {
+			self.nexus.taxa = characters
+				.matrix
+				.iter()
+				.map(|(name, _)| name.clone())
+				.collect();
+		}
+		self.nexus.characters.push(characters);
+
+		Ok(())
+	}
+
+	fn format(
+		&self,
+		options: &Options,
+		characters: &mut Characters,
+		interleave: &mut bool,
+	) -> Result<(), ParseError> {
+		for (key, value) in options {
+			match key.as_str() {
+				"DATATYPE" => {
+					let value = self.value(value)?;
+					characters.datatype =
+						value.to_ascii_uppercase();
+				}
+				"MISSING" => {
+					characters.missing =
+						self.symbol(value)?
+				}
+				"GAP" => {
+					characters.gap =
+						Some(self.symbol(value)?)
+				}
+				"INTERLEAVE" => {
+					*interleave =
+						value.as_ref().is_none_or(
+							|v| {
+								!v.eq_ignore_ascii_case("NO")
+							},
+						);
+				}
+				_ => (),
+			}
+		}
+		Ok(())
+	}
+
+	fn check_matrix(
+		&self,
+		characters: &Characters,
+		ntax: Option<usize>,
+		nchar: Option<usize>,
+	) -> Result<(), ParseError> {
+		let found = characters.matrix.len();
+		if let Some(ntax) = ntax.filter(|n| *n != found) {
+			return Err(self.error(format!(
+				"Expected {ntax} taxa in the matrix, found {found}"
+			)));
+		}
+
+		for (name, row) in &characters.matrix {
+			let length = row.chars().count();
+			if let Some(nchar) = nchar.filter(|n| *n != length) {
+				return Err(self.error(format!(
+					"Expected {nchar} characters for '{name}', found {length}"
+				)));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Each taxon name is followed by all of its characters, which may span
+	/// several lines.
+	fn sequential(
+		&mut self,
+		nchar: usize,
+	) -> Result<Vec<(String, String)>, ParseError> {
+		let mut matrix = Vec::new();
+
+		while let Some(name) = self.label()? {
+			let mut row = String::new();
+			while row.chars().count() < nchar {
+				self.skip()?;
+				let end = self
+					.rest
+					.find(|c: char| {
+						c.is_whitespace()
+							|| c == ';' || c == '['
+					})
+					.unwrap_or(self.rest.len());
+				if end == 0 {
+					return Err(self.error(format!(
+						"Expected {nchar} characters for '{name}', found {}",
+						row.chars().count()
+					)));
+				}
+				row.push_str(&self.rest[..end]);
+				self.rest = &self.rest[end..];
+			}
+			matrix.push((name, row));
+		}
+		self.expect(';')?;
+
+		Ok(matrix)
+	}
+
+	/// Lines start with a taxon name, followed by the next part of its
+	/// characters.
+	fn interleaved(&mut self) -> Result<Vec<(String, String)>, ParseError> {
+		let mut matrix: Vec<(String, String)> = Vec::new();
+		let mut rows = HashMap::new();
+
+		while let Some(name) = self.label()? {
+			let index = match rows.get(&name) {
+				Some(index) => *index,
+				None => {
+					rows.insert(name.clone(), matrix.len());
+					matrix.push((name, String::new()));
+					matrix.len() - 1
+				}
+			};
+
+			let line_end = self
+				.rest
+				.find(['\n', ';'])
+				.unwrap_or(self.rest.len());
+			let mut chars = self.rest[..line_end].chars();
+			while let Some(c) = chars.next() {
+				match c {
+					// Comments can't span lines here
+					'[' => {
+						chars.find(|c| *c == ']');
+					}
+					c if c.is_whitespace() => (),
+					c => matrix[index].1.push(c),
+				}
+			}
+			self.rest = &self.rest[line_end..];
+		}
+		self.expect(';')?;
+
+		Ok(matrix)
+	}
+
+	fn trees(&mut self) -> Result<(), ParseError> {
+		let mut translate = HashMap::new();
+
+		while let Some(command) = self.command()? {
+			match command.as_str() {
+				"TRANSLATE" => loop {
+					// Keys are matched against
+					// Newick leaf names, which are
+					// normalized the same way
+					let key = self.expect_label(
+						"a taxon number",
+					)?;
+					let name = self
+						.expect_label("a taxon name")?;
+					translate.insert(key, name);
+					if !self.eat(',')? {
+						self.expect(';')?;
+						break;
+					}
+				},
+				"TREE" | "UTREE" => {
+					let (name, tree) =
+						self.tree(&translate)?;
+					self.nexus.trees.push((name, tree));
+				}
+				_ => self.skip_command()?,
+			}
+		}
+
+		Ok(())
+	}
+
+	fn tree(
+		&mut self,
+		translate: &HashMap<String, String>,
+	) -> Result<(String, Tree), ParseError> {
+		let mut name = self.expect_word("a tree name")?;
+		// The default tree marker
+		if name == "*" {
+			name = self.expect_word("a tree name")?;
+		}
+		self.expect('=')?;
+
+		let (line, column) = newick::position(self.source, self.rest);
+		let (rest, mut tree) = newick::tree(self.rest)
+			.map_err(|error| error.offset(line, column))?;
+		self.rest = rest;
+
+		// Internal labels, like support values, are never taxa
+		let leaves: Vec<_> = tree.leaves().collect();
+		for node in leaves {
+			let node = tree.get_node_mut(node);
+			if let Some(name) = translate.get(node.name()) {
+				node.set_name(name.clone());
+			}
+		}
+
+		Ok((name, tree))
+	}
+
+	/// `KEY=value` pairs until the end of the command.  Keys are
+	/// upper-cased and values are optional.
+	fn options(&mut self) -> Result<Options, ParseError> {
+		let mut options = Vec::new();
+		while let Some(key) = self.word()? {
+			let value = if self.eat('=')? {
+				Some(self.expect_word("a value")?)
+			} else {
+				None
+			};
+			options.push((key.to_ascii_uppercase(), value));
+		}
+		self.expect(';')?;
+		Ok(options)
+	}
+
+	fn value<'v>(
+		&self,
+		value: &'v Option<String>,
+	) -> Result<&'v str, ParseError> {
+		value.as_deref().ok_or_else(|| self.error("Expected '='"))
+	}
+
+	/// Value of the option `key`, if it's set.
+	fn number(
+		&self,
+		options: &Options,
+		key: &str,
+	) -> Result<Option<usize>, ParseError> {
+		let Some((_, value)) = options.iter().find(|(k, _)| k == key)
+		else {
+			return Ok(None);
+		};
+		self.value(value)?
+			.parse()
+			.map(Some)
+			.map_err(|_| self.error("Expected a number"))
+	}
+
+	fn symbol(&self, value: &Option<String>) -> Result<char, ParseError> {
+		let mut chars = self.value(value)?.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => Ok(c),
+			_ => Err(self.error("Expected a single character")),
+		}
+	}
+
+	/// Skips whitespace and comments.
+	fn skip(&mut self) -> Result<(), ParseError> {
+		loop {
+			self.rest = self.rest.trim_start();
+			if !self.rest.starts_with('[') {
+				return Ok(());
+			}
+			let Some(end) = self.rest.find(']') else {
+				return Err(self.error("Unterminated comment"));
+			};
+			self.rest = &self.rest[end + 1..];
+		}
+	}
+
+	/// Consumes `c` if it's the next token.
+	fn eat(&mut self, c: char) -> Result<bool, ParseError> {
+		self.skip()?;
+		if let Some(rest) = self.rest.strip_prefix(c) {
+			self.rest = rest;
+			Ok(true)
+		} else {
+			Ok(false)
+		}
+	}
+
+	fn expect(&mut self, c: char) -> Result<(), ParseError> {
+		if self.eat(c)? {
+			Ok(())
+		} else {
+			Err(self.error(format!("Expected '{c}'")))
+		}
+	}
+
+	fn expect_word(&mut self, what: &str) -> Result<String, ParseError> {
+		self.word()?
+			.ok_or_else(|| self.error(format!("Expected {what}")))
+	}
+
+	fn expect_label(&mut self, what: &str) -> Result<String, ParseError> {
+		self.label()?
+			.ok_or_else(|| self.error(format!("Expected {what}")))
+	}
+
+	/// A taxon name.  Underscores in bare names stand for spaces, like in
+	/// Newick.
+	fn label(&mut self) -> Result<Option<String>, ParseError> {
+		self.skip()?;
+		let quoted = self.rest.starts_with(['\'', '"']);
+		let word = self.word()?;
+		if quoted {
+			Ok(word)
+		} else {
+			Ok(word.map(|word| word.replace('_', " ")))
+		}
+	}
+
+	/// Reads a quoted or a bare word.  Returns `None` if the next token is
+	/// punctuation or the input has ended.
+	fn word(&mut self) -> Result<Option<String>, ParseError> {
+		self.skip()?;
+
+		let Some(first) = self.rest.chars().next() else {
+			return Ok(None);
+		};
+		if PUNCTUATION.contains(&first) {
+			return Ok(None);
+		}
+
+		if first == '\'' || first == '"' {
+			let mut word = String::new();
+			let mut chars =
+				self.rest.char_indices().skip(1).peekable();
+			while let Some((i, c)) = chars.next() {
+				if c != first {
+					word.push(c);
+				} else if first == '\''
+					&& chars.next_if(|(_, c)| *c == '\'')
+						.is_some()
+				{
+					// An escaped single quote
+					word.push(c);
+				} else {
+					self.rest = &self.rest[i + 1..];
+					return Ok(Some(word));
+				}
+			}
+			return Err(self.error("Unterminated quoted word"));
+		}
+
+		let end = self
+			.rest
+			.find(|c: char| {
+				c.is_whitespace()
+					|| c == '[' || PUNCTUATION.contains(&c)
+			})
+			.unwrap_or(self.rest.len());
+		let word = self.rest[..end].to_owned();
+		self.rest = &self.rest[end..];
+		Ok(Some(word))
+	}
+
+	/// Skips the rest of a command, including the semicolon.
+	fn skip_command(&mut self) -> Result<(), ParseError> {
+		loop {
+			if self.word()?.is_some() {
+				continue;
+			}
+			let Some(c) = self.rest.chars().next() else {
+				return Err(self.error("Expected ';'"));
+			};
+			self.rest = &self.rest[c.len_utf8()..];
+			if c == ';' {
+				return Ok(());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::{seq::DnaSeq, DnaNucleotide};
+
+	#[test]
+	fn taxa_and_characters() {
+		let source = "#NEXUS
+[A comment]
+begin taxa;
+	dimensions ntax=3;
+	taxlabels A 'B c' D_e;
+end;
+
+BEGIN DATA;
+	DIMENSIONS NTAX=3 NCHAR=6;
+	FORMAT DATATYPE=dna MISSING=? GAP=- INTERLEAVE;
+	MATRIX
+	A     ACG
+	'B c' AC-
+	D_e   ?CG [comment]
+
+	A     TTA
+	'B c' TT?
+	D_e   tta
+	;
+END;
+
+BEGIN ASSUMPTIONS;
+	OPTIONS DEFTYPE=unord;
+END;
+";
+		let nexus = Nexus::parse(source).unwrap();
+		assert_eq!(nexus.taxa(), ["A", "B c", "D e"]);
+
+		let characters = &nexus.characters()[0];
+		assert_eq!(characters.datatype(), "DNA");
+		assert_eq!(characters.gap(), Some('-'));
+		assert_eq!(
+			characters.matrix()[2],
+			("D e".to_owned(), "?CGtta".to_owned())
+		);
+
+		let sequences =
+			characters.sequences::<DnaNucleotide>().unwrap();
+		let expected = DnaSeq::try_from("AC-TTN").unwrap();
+		assert_eq!(sequences[1].1, expected);
+	}
+
+	#[test]
+	fn sequential() {
+		let source = "#NEXUS
+BEGIN CHARACTERS;
+	DIMENSIONS NCHAR=8;
+	FORMAT MISSING=N;
+	MATRIX
+	first ACGT
+	      ACGT
+	second ACGTACGT;
+END;
+";
+		let nexus = Nexus::parse(source).unwrap();
+		assert_eq!(nexus.taxa(), ["first", "second"]);
+		assert_eq!(nexus.characters()[0].missing(), 'N');
+		assert_eq!(nexus.characters()[0].matrix()[0].1, "ACGTACGT");
+
+		let error = Nexus::parse(
+			"#NEXUS\nBEGIN DATA; DIMENSIONS NCHAR=4;\nMATRIX a AC;\nEND;",
+		)
+		.unwrap_err();
+		assert_eq!((error.line(), error.column()), (3, 12));
+	}
+
+	#[test]
+	fn trees() {
+		let source = "#NEXUS
+Begin trees;
+	Translate
+		1 A,
+		2 'B c',
+		3 C
+		;
+tree STATE_0 = [&R] ((1:1,2:1):0.5,3:1.5);
+tree * STATE_1 = [&R] ((1[&rate=1]:1,3:1):0.5,2:1.5);
+End;
+";
+		let nexus = Nexus::parse(source).unwrap();
+		let trees = nexus.trees();
+		assert_eq!(trees.len(), 2);
+		assert_eq!(trees[0].0, "STATE_0");
+		assert_eq!(
+			trees[0].1.serialize(),
+			"((A:1,'B c':1):0.5,C:1.5);"
+		);
+		assert_eq!(trees[1].0, "STATE_1");
+		assert_eq!(
+			trees[1].1.serialize(),
+			"((A[&rate=1]:1,C:1):0.5,'B c':1.5);"
+		);
+
+		// Keys are normalized like leaf names, and internal labels
+		// are left alone
+		let nexus = Nexus::parse(
+			"#NEXUS
+Begin trees;
+	Translate 1 Homo_sapiens, Pan_troglodytes 'Pan t', 3 C;
+	tree a = ((1,Pan_troglodytes)1,3)3;
+End;
+",
+		)
+		.unwrap();
+		assert_eq!(
+			nexus.trees()[0].1.serialize(),
+			"(('Homo sapiens','Pan t')1,C)3;"
+		);
+
+		let error = Nexus::parse(
+			"#NEXUS\nBegin trees;\ntree a = (A,B;\nEnd;",
+		)
+		.unwrap_err();
+		assert_eq!((error.line(), error.column()), (3, 14));
+	}
+
+	#[test]
+	fn errors() {
+		assert!(Nexus::parse("BEGIN TAXA; END;").is_err());
+		assert!(Nexus::parse("#NEXUS BEGIN TAXA; TAXLABELS A").is_err());
+		let error = Nexus::parse(
+			"#NEXUS BEGIN TAXA; DIMENSIONS NTAX=2; TAXLABELS A; END;",
+		)
+		.unwrap_err();
+		assert_eq!(error.message(), "Expected 2 taxa, found 1");
+	}
+}
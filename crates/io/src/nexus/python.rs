@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use pyo3::prelude::*;
+
+use std::{fs::File, io::BufWriter};
+
+use super::{Characters, Nexus, NexusTreesWriter};
+use crate::newick::{python::PyTree, Tree};
+use data::{seq::python::PyDnaSeq, DnaNucleotide};
+
+#[pyclass(name = "Nexus", module = "aspartik.io.nexus", frozen)]
+pub(crate) struct PyNexus(Nexus);
+
+#[pymethods]
+impl PyNexus {
+	#[new]
+	fn new(path: &str) -> Result<Self> {
+		let file = File::open(path)?;
+		Ok(PyNexus(Nexus::read(file)?))
+	}
+
+	#[staticmethod]
+	fn parse(source: &str) -> Result<Self> {
+		Ok(PyNexus(Nexus::parse(source)?))
+	}
+
+	#[getter]
+	fn taxa(&self) -> Vec<String> {
+		self.0.taxa().to_vec()
+	}
+
+	#[getter]
+	fn characters(&self) -> Vec<PyCharacters> {
+		self.0.characters()
+			.iter()
+			.cloned()
+			.map(PyCharacters)
+			.collect()
+	}
+
+	#[getter]
+	fn trees(&self) -> Vec<(String, PyTree)> {
+		self.0.trees()
+			.iter()
+			.map(|(name, tree)| (name.clone(), tree.clone().into()))
+			.collect()
+	}
+}
+
+#[pyclass(name = "Characters", module = "aspartik.io.nexus", frozen)]
+pub(crate) struct PyCharacters(Characters);
+
+#[pymethods]
+impl PyCharacters {
+	#[getter]
+	fn datatype(&self) -> String {
+		self.0.datatype().to_owned()
+	}
+
+	#[getter]
+	fn missing(&self) -> char {
+		self.0.missing()
+	}
+
+	#[getter]
+	fn gap(&self) -> Option<char> {
+		self.0.gap()
+	}
+
+	#[getter]
+	fn matrix(&self) -> Vec<(String, String)> {
+		self.0.matrix().to_vec()
+	}
+
+	fn dna(&self) -> Result<Vec<(String, PyDnaSeq)>> {
+		let sequences = self.0.sequences::<DnaNucleotide>()?;
+		Ok(sequences
+			.into_iter()
+			.map(|(name, seq)| (name, seq.into()))
+			.collect())
+	}
+}
+
+#[pyclass(name = "NexusTreesWriter", module = "aspartik.io.nexus", frozen)]
+pub(crate) struct PyNexusTreesWriter {
+	inner: Mutex<Option<NexusTreesWriter<BufWriter<File>>>>,
+}
+
+#[pymethods]
+impl PyNexusTreesWriter {
+	#[new]
+	fn new(path: &str, taxa: Vec<String>) -> Result<Self> {
+		let file = BufWriter::new(File::create(path)?);
+		let writer = NexusTreesWriter::new(file, &taxa)?;
+		Ok(Self {
+			inner: Mutex::new(Some(writer)),
+		})
+	}
+
+	/// Parses `newick` and appends it to the file.
+	fn write(&self, name: &str, newick: &str) -> Result<()> {
+		let tree = Tree::parse(newick)?;
+		self.inner
+			.lock()
+			.as_mut()
+			.ok_or_else(|| anyhow!("The writer is closed"))?
+			.write_tree(name, &tree)
+	}
+
+	fn close(&self) -> Result<()> {
+		if let Some(mut writer) = self.inner.lock().take() {
+			writer.finish()?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, ensure, Result};
+
+use std::{
+	collections::HashMap,
+	io::{self, Write},
+};
+
+use super::{quote, quote_label};
+use crate::newick::Tree;
+
+/// Writes trees into a NEXUS file in the format of BEAST's `.trees` files,
+/// which TreeAnnotator and FigTree open directly.  Leaves are replaced by their
+/// numbers in the `TRANSLATE` table to keep the file small.
+///
+/// The file is completed by [`finish`][Self::finish] or when the writer is
+/// dropped.
+pub struct NexusTreesWriter<W: Write> {
+	writer: W,
+	/// Taxon names and their numbers in the translate table.
+	numbers: HashMap<String, usize>,
+	finished: bool,
+}
+
+impl<W: Write> NexusTreesWriter<W> {
+	/// Writes the header with the `TAXA` block and the `TRANSLATE` table.
+	pub fn new(mut writer: W, taxa: &[String]) -> Result<Self> {
+		let mut numbers = HashMap::new();
+		for (i, taxon) in taxa.iter().enumerate() {
+			let duplicate = numbers.insert(taxon.clone(), i + 1);
+			ensure!(
+				duplicate.is_none(),
+				"Duplicate taxon '{taxon}'"
+			);
+		}
+
+		writeln!(writer, "#NEXUS")?;
+		writeln!(writer)?;
+		writeln!(writer, "Begin taxa;")?;
+		writeln!(writer, "\tDimensions ntax={};", taxa.len())?;
+		writeln!(writer, "\tTaxlabels")?;
+		for taxon in taxa {
+			writeln!(writer, "\t\t{}", quote_label(taxon))?;
+		}
+		writeln!(writer, "\t\t;")?;
+		writeln!(writer, "End;")?;
+		writeln!(writer)?;
+		writeln!(writer, "Begin trees;")?;
+		writeln!(writer, "\tTranslate")?;
+		for (i, taxon) in taxa.iter().enumerate() {
+			let separator =
+				if i + 1 == taxa.len() { "" } else { "," };
+			writeln!(
+				writer,
+				"\t\t{} {}{separator}",
+				i + 1,
+				quote_label(taxon)
+			)?;
+		}
+		writeln!(writer, "\t\t;")?;
+
+		Ok(NexusTreesWriter {
+			writer,
+			numbers,
+			finished: false,
+		})
+	}
+
+	/// Appends a rooted tree.  All of its leaves must be named after the
+	/// taxa passed to [`new`][Self::new].
+	pub fn write_tree(&mut self, name: &str, tree: &Tree) -> Result<()> {
+		ensure!(!self.finished, "The trees block is already finished");
+
+		let mut tree = tree.clone();
+		let leaves: Vec<_> = tree
+			.nodes()
+			.filter(|node| tree.children_of(*node).is_empty())
+			.collect();
+		for leaf in leaves {
+			let node = tree.get_node_mut(leaf);
+			let number = self.numbers.get(node.name()).ok_or_else(
+				|| anyhow!("Unknown taxon '{}'", node.name()),
+			)?;
+			node.set_name(number.to_string());
+		}
+
+		writeln!(
+			self.writer,
+			"tree {} = [&R] {}",
+			quote(name),
+			tree.serialize()
+		)?;
+		Ok(())
+	}
+
+	/// Ends the trees block and flushes the writer.  Calling it again does
+	/// nothing.
+	pub fn finish(&mut self) -> io::Result<()> {
+		if !self.finished {
+			writeln!(self.writer, "End;")?;
+			self.finished = true;
+		}
+		self.writer.flush()
+	}
+}
+
+impl<W: Write> Drop for NexusTreesWriter<W> {
+	fn drop(&mut self) {
+		// Errors can't be reported from `drop`, callers which care
+		// should use `finish`.
+		let _ = self.finish();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nexus::Nexus;
+
+	#[test]
+	fn round_trip() {
+		let taxa = ["A".to_owned(), "B c".to_owned(), "D".to_owned()];
+		let mut out = Vec::new();
+		{
+			let mut writer =
+				NexusTreesWriter::new(&mut out, &taxa).unwrap();
+			let tree = Tree::parse("((A:1,'B c':1):0.5,D:1.5):0;")
+				.unwrap();
+			writer.write_tree("STATE_0", &tree).unwrap();
+
+			let unknown = Tree::parse("(A,E);").unwrap();
+			assert!(writer
+				.write_tree("STATE_1", &unknown)
+				.is_err());
+		}
+
+		let text = String::from_utf8(out).unwrap();
+		assert!(text.contains(
+			"tree STATE_0 = [&R] ((1:1,2:1):0.5,3:1.5):0;"
+		));
+		assert!(text.ends_with("End;\n"));
+
+		let nexus = Nexus::parse(&text).unwrap();
+		assert_eq!(nexus.taxa(), taxa);
+		assert_eq!(
+			nexus.trees()[0].1.serialize(),
+			"((A:1,'B c':1):0.5,D:1.5):0;"
+		);
+
+		assert!(NexusTreesWriter::new(
+			Vec::new(),
+			&["A".to_owned(), "A".to_owned()]
+		)
+		.is_err());
+	}
+}
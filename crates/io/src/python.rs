@@ -0,0 +1,76 @@
+use anyhow::Result;
+use pyo3::{
+	prelude::*,
+	types::{PyBytes, PyString},
+};
+
+use std::{
+	fs::File,
+	io::{self, Read},
+	path::PathBuf,
+};
+
+/// Input accepted by the readers: a path or a file-like object opened in
+/// either text or binary mode.
+pub(crate) type Source = Box<dyn Read + Send>;
+
+/// Opens `source`, which is either a path (a string or an `os.PathLike`) or
+/// an object with a `read` method.
+pub(crate) fn open(source: &Bound<PyAny>) -> Result<Source> {
+	if source.hasattr("read")? {
+		return Ok(Box::new(PyReader::new(source.clone().unbind())));
+	}
+
+	let path: PathBuf = source.extract()?;
+	Ok(Box::new(File::open(path)?))
+}
+
+/// Reads from a Python file-like object.  Text files return strings, which
+/// are encoded as UTF-8.
+struct PyReader {
+	file: Py<PyAny>,
+	/// Leftovers of the last chunk which didn't fit into the caller's
+	/// buffer.
+	pending: Vec<u8>,
+	offset: usize,
+}
+
+impl PyReader {
+	fn new(file: Py<PyAny>) -> Self {
+		PyReader {
+			file,
+			pending: Vec::new(),
+			offset: 0,
+		}
+	}
+}
+
+impl Read for PyReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.offset == self.pending.len() {
+			self.pending = Python::with_gil(|py| -> PyResult<_> {
+				let chunk = self.file.call_method1(
+					py,
+					"read",
+					(buf.len(),),
+				)?;
+				let chunk = chunk.bind(py);
+				if let Ok(text) = chunk.downcast::<PyString>() {
+					Ok(text.to_str()?.as_bytes().to_vec())
+				} else {
+					Ok(chunk.downcast::<PyBytes>()?
+						.as_bytes()
+						.to_vec())
+				}
+			})
+			.map_err(io::Error::other)?;
+			self.offset = 0;
+		}
+
+		let pending = &self.pending[self.offset..];
+		let len = pending.len().min(buf.len());
+		buf[..len].copy_from_slice(&pending[..len]);
+		self.offset += len;
+		Ok(len)
+	}
+}
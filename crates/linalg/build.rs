@@ -1,3 +1,5 @@
 fn main() {
-	println!("cargo::rustc-link-lib=dylib=lapack");
+	if std::env::var_os("CARGO_FEATURE_LAPACK").is_some() {
+		println!("cargo::rustc-link-lib=dylib=lapack");
+	}
 }
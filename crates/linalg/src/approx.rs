@@ -1,6 +1,6 @@
 use approx::{AbsDiffEq, RelativeEq, UlpsEq};
 
-use crate::{RowMatrix, Vector};
+use crate::{DMatrix, DVector, RowMatrix, Vector};
 
 impl<T: AbsDiffEq, const N: usize> AbsDiffEq for Vector<T, N>
 where
@@ -170,3 +170,122 @@ where
 		true
 	}
 }
+
+// Dynamically sized types with different dimensions are never equal.
+impl<T: AbsDiffEq> AbsDiffEq for DVector<T>
+where
+	T::Epsilon: Copy,
+{
+	type Epsilon = T::Epsilon;
+
+	fn default_epsilon() -> T::Epsilon {
+		T::default_epsilon()
+	}
+
+	fn abs_diff_eq(&self, other: &Self, epsilon: T::Epsilon) -> bool {
+		self.as_slice().abs_diff_eq(other.as_slice(), epsilon)
+	}
+}
+
+impl<T: RelativeEq> RelativeEq for DVector<T>
+where
+	T::Epsilon: Copy,
+{
+	fn default_max_relative() -> T::Epsilon {
+		T::default_max_relative()
+	}
+
+	fn relative_eq(
+		&self,
+		other: &Self,
+		epsilon: Self::Epsilon,
+		max_relative: T::Epsilon,
+	) -> bool {
+		self.as_slice().relative_eq(
+			other.as_slice(),
+			epsilon,
+			max_relative,
+		)
+	}
+}
+
+impl<T: UlpsEq> UlpsEq for DVector<T>
+where
+	T::Epsilon: Copy,
+{
+	fn default_max_ulps() -> u32 {
+		T::default_max_ulps()
+	}
+
+	fn ulps_eq(
+		&self,
+		other: &Self,
+		epsilon: Self::Epsilon,
+		max_ulps: u32,
+	) -> bool {
+		self.as_slice().ulps_eq(other.as_slice(), epsilon, max_ulps)
+	}
+}
+
+impl<T: AbsDiffEq> AbsDiffEq for DMatrix<T>
+where
+	T::Epsilon: Copy,
+{
+	type Epsilon = T::Epsilon;
+
+	fn default_epsilon() -> T::Epsilon {
+		T::default_epsilon()
+	}
+
+	fn abs_diff_eq(&self, other: &Self, epsilon: T::Epsilon) -> bool {
+		self.shape() == other.shape()
+			&& self.as_slice()
+				.abs_diff_eq(other.as_slice(), epsilon)
+	}
+}
+
+impl<T: RelativeEq> RelativeEq for DMatrix<T>
+where
+	T::Epsilon: Copy,
+{
+	fn default_max_relative() -> T::Epsilon {
+		T::default_max_relative()
+	}
+
+	fn relative_eq(
+		&self,
+		other: &Self,
+		epsilon: Self::Epsilon,
+		max_relative: T::Epsilon,
+	) -> bool {
+		self.shape() == other.shape()
+			&& self.as_slice().relative_eq(
+				other.as_slice(),
+				epsilon,
+				max_relative,
+			)
+	}
+}
+
+impl<T: UlpsEq> UlpsEq for DMatrix<T>
+where
+	T::Epsilon: Copy,
+{
+	fn default_max_ulps() -> u32 {
+		T::default_max_ulps()
+	}
+
+	fn ulps_eq(
+		&self,
+		other: &Self,
+		epsilon: Self::Epsilon,
+		max_ulps: u32,
+	) -> bool {
+		self.shape() == other.shape()
+			&& self.as_slice().ulps_eq(
+				other.as_slice(),
+				epsilon,
+				max_ulps,
+			)
+	}
+}
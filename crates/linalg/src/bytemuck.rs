@@ -1,4 +1,4 @@
-use super::{RowMatrix, Vector};
+use super::{DMatrix, DVector, RowMatrix, Vector};
 use bytemuck::{Pod, Zeroable};
 
 unsafe impl<T, const N: usize> Zeroable for Vector<T, N> where T: Copy + Zeroable
@@ -13,3 +13,17 @@ unsafe impl<T, const N: usize, const M: usize> Pod for RowMatrix<T, N, M> where
 	T: Copy + Pod
 {
 }
+
+// Dynamically sized types can't be `Pod` themselves, but their elements can
+// still be viewed as bytes, for example to upload them to a GPU.
+impl<T: Pod> DVector<T> {
+	pub fn as_bytes(&self) -> &[u8] {
+		bytemuck::cast_slice(self.as_slice())
+	}
+}
+
+impl<T: Pod> DMatrix<T> {
+	pub fn as_bytes(&self) -> &[u8] {
+		bytemuck::cast_slice(self.as_slice())
+	}
+}
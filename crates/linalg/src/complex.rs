@@ -0,0 +1,100 @@
+//! Eigen-decompositions with complex eigenvalues.
+
+use num_complex::Complex64;
+
+use crate::{decomposition::gauss_jordan, Error, RowMatrix, Vector};
+
+/// Diagonalization `A = R^T diag(values) L` of a real matrix, where the rows of
+/// `R` are the right eigenvectors and `L` is the inverse of `R^T`, so its rows
+/// are the matching left eigenvectors.
+///
+/// Non-symmetric matrices, like the rate matrices of irreversible substitution
+/// models, can have complex conjugate pairs of eigenvalues.  Functions of the
+/// matrix calculated through the decomposition are still real, as the
+/// imaginary parts of the conjugate terms cancel out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexEigen<const N: usize> {
+	values: Vector<Complex64, N>,
+	right: RowMatrix<Complex64, N, N>,
+	left: RowMatrix<Complex64, N, N>,
+}
+
+impl<const N: usize> ComplexEigen<N> {
+	/// Creates a decomposition from the eigenvalues and the right
+	/// eigenvectors (as rows) of a matrix.
+	///
+	/// Returns [`Error::Singular`] if the eigenvectors are linearly
+	/// dependent, which means that the matrix isn't diagonalizable.
+	pub fn new(
+		values: Vector<Complex64, N>,
+		right: RowMatrix<Complex64, N, N>,
+	) -> Result<Self, Error> {
+		let left = inverse(&right.transpose())?;
+
+		Ok(Self {
+			values,
+			right,
+			left,
+		})
+	}
+
+	pub fn values(&self) -> Vector<Complex64, N> {
+		self.values
+	}
+
+	/// Right eigenvectors as rows, `A r = lambda r`.
+	pub fn right(&self) -> RowMatrix<Complex64, N, N> {
+		self.right
+	}
+
+	/// Left eigenvectors as rows, `l^T A = lambda l^T`.  They are scaled so
+	/// that `l_i^T r_i = 1`.
+	pub fn left(&self) -> RowMatrix<Complex64, N, N> {
+		self.left
+	}
+
+	/// Calculates `f(A)` as `R^T diag(f(values)) L`.
+	///
+	/// The imaginary part of the result is discarded, so `f` must map
+	/// conjugate values to conjugate values, as all real analytic functions
+	/// do.
+	pub fn map<F>(&self, f: F) -> RowMatrix<f64, N, N>
+	where
+		F: Fn(Complex64) -> Complex64,
+	{
+		let values = self.values.map(f);
+
+		let mut out = RowMatrix::default();
+		for i in 0..N {
+			for j in 0..N {
+				let mut sum = Complex64::default();
+				for k in 0..N {
+					sum += self.right[(k, i)]
+						* values[k] * self.left[(k, j)];
+				}
+				out[(i, j)] = sum.re;
+			}
+		}
+		out
+	}
+
+	/// The original matrix.
+	pub fn reconstruct(&self) -> RowMatrix<f64, N, N> {
+		self.map(|v| v)
+	}
+
+	/// Calculates `e^(A t)`.
+	pub fn exp(&self, t: f64) -> RowMatrix<f64, N, N> {
+		self.map(|v| (v * t).exp())
+	}
+}
+
+/// Inverse of a complex matrix.
+fn inverse<const N: usize>(
+	matrix: &RowMatrix<Complex64, N, N>,
+) -> Result<RowMatrix<Complex64, N, N>, Error> {
+	let mut m = *matrix;
+	let mut out = RowMatrix::<Complex64, N, N>::default();
+	gauss_jordan(m.as_mut_slice(), out.as_mut_slice(), N, Complex64::norm)?;
+	Ok(out)
+}
@@ -0,0 +1,274 @@
+//! Pure-Rust LU and Cholesky decompositions of small matrices.
+
+use num_traits::NumAssign;
+
+use crate::{Error, RowMatrix, Vector};
+
+/// LU decomposition with partial pivoting, `P A = L U`.
+#[derive(Debug, Clone, Copy)]
+pub struct Lu<const N: usize> {
+	/// `L` below the diagonal (with an implicit unit diagonal) and `U` on
+	/// and above it.
+	lu: RowMatrix<f64, N, N>,
+	/// Row of `A` which ended up in each row of `LU`.
+	pivots: [usize; N],
+	/// Sign of the permutation, `-1` for an odd number of swaps.
+	sign: f64,
+}
+
+impl<const N: usize> Lu<N> {
+	/// Solves `A x = b`.
+	pub fn solve(&self, b: Vector<f64, N>) -> Vector<f64, N> {
+		let mut x = Vector::default();
+		for i in 0..N {
+			x[i] = b[self.pivots[i]];
+		}
+
+		// Forward substitution with `L`
+		for i in 0..N {
+			for k in 0..i {
+				x[i] -= self.lu[(i, k)] * x[k];
+			}
+		}
+		// Back substitution with `U`
+		for i in (0..N).rev() {
+			for k in (i + 1)..N {
+				x[i] -= self.lu[(i, k)] * x[k];
+			}
+			x[i] /= self.lu[(i, i)];
+		}
+
+		x
+	}
+
+	/// Solves `A X = B`.
+	pub fn solve_matrix(
+		&self,
+		b: &RowMatrix<f64, N, N>,
+	) -> RowMatrix<f64, N, N> {
+		let columns = b.transpose();
+		let mut out = RowMatrix::default();
+		for j in 0..N {
+			out[j] = self.solve(columns[j]);
+		}
+		out.transpose()
+	}
+
+	pub fn determinant(&self) -> f64 {
+		(0..N).fold(self.sign, |d, i| d * self.lu[(i, i)])
+	}
+
+	pub fn inverse(&self) -> RowMatrix<f64, N, N> {
+		self.solve_matrix(&RowMatrix::identity())
+	}
+}
+
+/// Writes the inverse of the `n` by `n` row-major `matrix` into `inverse` with
+/// Gauss-Jordan elimination and partial pivoting, using `magnitude` to choose
+/// the pivots.  `matrix` is overwritten in the process.
+pub(crate) fn gauss_jordan<T: Copy + NumAssign>(
+	matrix: &mut [T],
+	inverse: &mut [T],
+	n: usize,
+	magnitude: impl Fn(T) -> f64,
+) -> Result<(), Error> {
+	let (m, out) = (matrix, inverse);
+	out.fill(T::zero());
+	for i in 0..n {
+		out[i * n + i] = T::one();
+	}
+
+	for k in 0..n {
+		// The row with the largest element in the column
+		let p = (k..n)
+			.max_by(|a, b| {
+				magnitude(m[*a * n + k])
+					.total_cmp(&magnitude(m[*b * n + k]))
+			})
+			.unwrap();
+		let pivot = m[p * n + k];
+		if magnitude(pivot) == 0.0 || !magnitude(pivot).is_finite() {
+			return Err(Error::Singular);
+		}
+		if p != k {
+			for j in 0..n {
+				m.swap(p * n + j, k * n + j);
+				out.swap(p * n + j, k * n + j);
+			}
+		}
+
+		for j in 0..n {
+			m[k * n + j] /= pivot;
+			out[k * n + j] /= pivot;
+		}
+		for i in (0..n).filter(|i| *i != k) {
+			let factor = m[i * n + k];
+			for j in 0..n {
+				let (above, inverse) =
+					(m[k * n + j], out[k * n + j]);
+				m[i * n + j] -= factor * above;
+				out[i * n + j] -= factor * inverse;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes the lower Cholesky factor of the `n` by `n` row-major `matrix` into
+/// `lower`, which must be zeroed.  Only the lower triangle of `matrix` is read.
+/// Returns `false` if the matrix isn't positive definite.
+pub(crate) fn cholesky_lower(
+	matrix: &[f64],
+	n: usize,
+	lower: &mut [f64],
+) -> bool {
+	for i in 0..n {
+		for j in 0..=i {
+			let mut sum = matrix[i * n + j];
+			for k in 0..j {
+				sum -= lower[i * n + k] * lower[j * n + k];
+			}
+
+			if i == j {
+				if sum <= 0.0 || !sum.is_finite() {
+					return false;
+				}
+				lower[i * n + i] = sum.sqrt();
+			} else {
+				lower[i * n + j] = sum / lower[j * n + j];
+			}
+		}
+	}
+	true
+}
+
+/// Cholesky decomposition of a symmetric positive definite matrix, `A = L L^T`.
+#[derive(Debug, Clone, Copy)]
+pub struct Cholesky<const N: usize> {
+	lower: RowMatrix<f64, N, N>,
+}
+
+impl<const N: usize> Cholesky<N> {
+	/// The lower triangular factor `L`.
+	pub fn lower(&self) -> RowMatrix<f64, N, N> {
+		self.lower
+	}
+
+	/// Solves `A x = b`.
+	pub fn solve(&self, b: Vector<f64, N>) -> Vector<f64, N> {
+		let l = &self.lower;
+		let mut x = b;
+		for i in 0..N {
+			for k in 0..i {
+				x[i] -= l[(i, k)] * x[k];
+			}
+			x[i] /= l[(i, i)];
+		}
+		for i in (0..N).rev() {
+			for k in (i + 1)..N {
+				x[i] -= l[(k, i)] * x[k];
+			}
+			x[i] /= l[(i, i)];
+		}
+		x
+	}
+
+	pub fn determinant(&self) -> f64 {
+		(0..N).map(|i| self.lower[(i, i)].powi(2)).product()
+	}
+
+	/// Natural logarithm of the determinant, which doesn't overflow for
+	/// large matrices.
+	pub fn ln_determinant(&self) -> f64 {
+		(0..N).map(|i| 2.0 * self.lower[(i, i)].ln()).sum()
+	}
+}
+
+impl<const N: usize> RowMatrix<f64, N, N> {
+	/// LU decomposition with partial pivoting, or `None` if the matrix is
+	/// singular.
+	pub fn lu(&self) -> Option<Lu<N>> {
+		let mut lu = *self;
+		let mut pivots = [0; N];
+		for (i, pivot) in pivots.iter_mut().enumerate() {
+			*pivot = i;
+		}
+		let mut sign = 1.0;
+
+		for k in 0..N {
+			// The row with the largest element in the column
+			let p = (k..N)
+				.max_by(|a, b| {
+					lu[(*a, k)]
+						.abs()
+						.total_cmp(&lu[(*b, k)].abs())
+				})
+				.unwrap();
+			if lu[(p, k)] == 0.0 || !lu[(p, k)].is_finite() {
+				return None;
+			}
+			if p != k {
+				let row = lu[p];
+				lu[p] = lu[k];
+				lu[k] = row;
+				pivots.swap(p, k);
+				sign = -sign;
+			}
+
+			for i in (k + 1)..N {
+				let factor = lu[(i, k)] / lu[(k, k)];
+				lu[(i, k)] = factor;
+				for j in (k + 1)..N {
+					lu[(i, j)] -= factor * lu[(k, j)];
+				}
+			}
+		}
+
+		Some(Lu { lu, pivots, sign })
+	}
+
+	/// Cholesky decomposition, or `None` if the matrix isn't symmetric
+	/// positive definite.  Only the lower triangle is read.
+	pub fn cholesky(&self) -> Option<Cholesky<N>> {
+		let mut lower = RowMatrix::default();
+		cholesky_lower(self.as_slice(), N, lower.as_mut_slice())
+			.then_some(Cholesky { lower })
+	}
+
+	/// Inverse calculated from the LU decomposition.  With the `lapack`
+	/// feature LAPACK is used instead.
+	///
+	/// # Panics
+	///
+	/// If the matrix is singular, see [`try_inverse`][Self::try_inverse].
+	#[cfg(not(feature = "lapack"))]
+	pub fn inverse(&self) -> Self {
+		self.try_inverse().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`inverse`][Self::inverse], but returns an error if the matrix
+	/// is singular.
+	#[cfg(not(feature = "lapack"))]
+	pub fn try_inverse(&self) -> Result<Self, Error> {
+		self.checked_lu().map(|lu| lu.inverse())
+	}
+
+	/// Like [`lu`][Self::lu], but tells apart singular matrices and ones
+	/// with non-finite elements.
+	pub fn checked_lu(&self) -> Result<Lu<N>, Error> {
+		let is_finite = (0..N).all(|i| {
+			self[i].as_array().iter().all(|v| v.is_finite())
+		});
+		if !is_finite {
+			return Err(Error::NotFinite);
+		}
+		self.lu().ok_or(Error::Singular)
+	}
+
+	/// Determinant calculated from the LU decomposition, zero for singular
+	/// matrices.
+	pub fn determinant(&self) -> f64 {
+		self.lu().map_or(0.0, |lu| lu.determinant())
+	}
+}
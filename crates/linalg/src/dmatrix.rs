@@ -0,0 +1,465 @@
+use num_traits::{Float, Num, NumAssign};
+
+use std::{
+	fmt::{self, Display},
+	ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign},
+};
+
+use crate::{decomposition::cholesky_lower, DVector, Error, RowMatrix};
+
+/// A heap-allocated row-major matrix, for when the dimensions are only known
+/// at runtime.  Operations on matrices with incompatible dimensions panic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DMatrix<T> {
+	rows: usize,
+	columns: usize,
+	data: Vec<T>,
+}
+
+// Constructors
+impl<T> DMatrix<T> {
+	/// Creates a matrix from its elements, row by row.
+	///
+	/// # Panics
+	///
+	/// If the length of `data` isn't `rows * columns`.
+	pub fn from_vec(rows: usize, columns: usize, data: Vec<T>) -> Self {
+		assert_eq!(
+			data.len(),
+			rows * columns,
+			"Data length must be rows * columns"
+		);
+
+		Self {
+			rows,
+			columns,
+			data,
+		}
+	}
+}
+
+impl<T: Copy> DMatrix<T> {
+	/// Creates a new matrix with all of it's elements set to `value`.
+	pub fn from_element(rows: usize, columns: usize, value: T) -> Self {
+		Self::from_vec(rows, columns, vec![value; rows * columns])
+	}
+}
+
+impl<T: Copy + Num> DMatrix<T> {
+	/// A matrix with all of it's elements set to zero.
+	pub fn zeros(rows: usize, columns: usize) -> Self {
+		Self::from_element(rows, columns, T::zero())
+	}
+
+	/// A matrix with all of it's elements set to one.
+	pub fn ones(rows: usize, columns: usize) -> Self {
+		Self::from_element(rows, columns, T::one())
+	}
+
+	/// Identity matrix.
+	pub fn identity(size: usize) -> Self {
+		let mut out = Self::zeros(size, size);
+		for i in 0..size {
+			out[(i, i)] = T::one();
+		}
+		out
+	}
+
+	pub fn from_diagonal(diag: &DVector<T>) -> Self {
+		let mut out = Self::zeros(diag.len(), diag.len());
+		for i in 0..diag.len() {
+			out[(i, i)] = diag[i];
+		}
+		out
+	}
+}
+
+/// Fails with [`Error::Dimensions`] if the rows have different lengths.
+impl<T> TryFrom<Vec<Vec<T>>> for DMatrix<T> {
+	type Error = Error;
+
+	fn try_from(value: Vec<Vec<T>>) -> Result<Self, Error> {
+		let rows = value.len();
+		let columns = value.first().map(Vec::len).unwrap_or(0);
+
+		let mut data = Vec::with_capacity(rows * columns);
+		for row in value {
+			if row.len() != columns {
+				return Err(Error::Dimensions {
+					expected: (rows, columns),
+					found: (rows, row.len()),
+				});
+			}
+			data.extend(row);
+		}
+
+		Ok(Self::from_vec(rows, columns, data))
+	}
+}
+
+impl<T: Copy, const N: usize> From<RowMatrix<T, N, N>> for DMatrix<T> {
+	fn from(value: RowMatrix<T, N, N>) -> Self {
+		Self::from_vec(N, N, value.as_slice().to_vec())
+	}
+}
+
+impl<T: Copy, const N: usize> TryFrom<DMatrix<T>> for RowMatrix<T, N, N> {
+	type Error = Error;
+
+	/// Fails with [`Error::Dimensions`] unless the matrix is `N` by `N`.
+	fn try_from(value: DMatrix<T>) -> Result<Self, Error> {
+		if value.shape() != (N, N) {
+			return Err(Error::Dimensions {
+				expected: (N, N),
+				found: value.shape(),
+			});
+		}
+
+		Ok(RowMatrix::from_slice(&value.data))
+	}
+}
+
+impl<T: Display> Display for DMatrix<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("[")?;
+
+		for i in 0..self.rows {
+			for j in 0..self.columns {
+				self[(i, j)].fmt(f)?;
+
+				if !(i == self.rows - 1
+					&& j == self.columns - 1)
+				{
+					f.write_str(",")?;
+				}
+				if j != self.columns - 1 {
+					f.write_str(" ")?;
+				}
+			}
+
+			if i != self.rows - 1 {
+				f.write_str("\n ")?;
+			}
+		}
+
+		f.write_str("]")?;
+
+		Ok(())
+	}
+}
+
+// Operators
+
+impl<T> Index<usize> for DMatrix<T> {
+	type Output = [T];
+
+	fn index(&self, i: usize) -> &[T] {
+		&self.data[i * self.columns..(i + 1) * self.columns]
+	}
+}
+
+impl<T> IndexMut<usize> for DMatrix<T> {
+	fn index_mut(&mut self, i: usize) -> &mut [T] {
+		&mut self.data[i * self.columns..(i + 1) * self.columns]
+	}
+}
+
+impl<T> Index<(usize, usize)> for DMatrix<T> {
+	type Output = T;
+
+	fn index(&self, (i, j): (usize, usize)) -> &T {
+		assert!(j < self.columns, "Column index out of bounds");
+		&self.data[i * self.columns + j]
+	}
+}
+
+impl<T> IndexMut<(usize, usize)> for DMatrix<T> {
+	fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut T {
+		assert!(j < self.columns, "Column index out of bounds");
+		&mut self.data[i * self.columns + j]
+	}
+}
+
+impl<T: Copy + AddAssign> AddAssign<&DMatrix<T>> for DMatrix<T> {
+	fn add_assign(&mut self, rhs: &Self) {
+		assert_eq!(self.shape(), rhs.shape(), "Dimensions must match");
+		for (a, b) in self.data.iter_mut().zip(&rhs.data) {
+			*a += *b;
+		}
+	}
+}
+
+impl<T: Copy + AddAssign> Add<&DMatrix<T>> for DMatrix<T> {
+	type Output = Self;
+
+	fn add(mut self, rhs: &Self) -> Self {
+		self += rhs;
+		self
+	}
+}
+
+impl<T: Copy + MulAssign> DMatrix<T> {
+	pub fn component_mul_assign(&mut self, rhs: &Self) {
+		assert_eq!(self.shape(), rhs.shape(), "Dimensions must match");
+		for (a, b) in self.data.iter_mut().zip(&rhs.data) {
+			*a *= *b;
+		}
+	}
+
+	pub fn component_mul(mut self, rhs: &Self) -> Self {
+		self.component_mul_assign(rhs);
+		self
+	}
+}
+
+impl<T: Copy + Num + AddAssign> DMatrix<T> {
+	/// Sum of the diagonal elements, zero for an empty matrix.
+	pub fn trace(&self) -> T {
+		assert!(self.is_square(), "The matrix must be square");
+
+		let mut out = T::zero();
+		for i in 0..self.rows {
+			out += self[(i, i)];
+		}
+		out
+	}
+}
+
+impl<T: Copy + MulAssign> MulAssign<T> for DMatrix<T> {
+	fn mul_assign(&mut self, rhs: T) {
+		for item in &mut self.data {
+			*item *= rhs;
+		}
+	}
+}
+
+impl<T: Copy + MulAssign> Mul<T> for DMatrix<T> {
+	type Output = Self;
+
+	fn mul(mut self, rhs: T) -> Self {
+		self *= rhs;
+		self
+	}
+}
+
+impl<T: Copy + NumAssign> Mul<&DVector<T>> for &DMatrix<T> {
+	type Output = DVector<T>;
+
+	fn mul(self, rhs: &DVector<T>) -> DVector<T> {
+		assert_eq!(self.columns, rhs.len(), "Dimensions must match");
+
+		let mut out = DVector::zeros(self.rows);
+		for i in 0..self.rows {
+			for (a, b) in self[i].iter().zip(rhs.iter()) {
+				out[i] += *a * *b;
+			}
+		}
+		out
+	}
+}
+
+impl<T: Copy + NumAssign> Mul<&DMatrix<T>> for &DMatrix<T> {
+	type Output = DMatrix<T>;
+
+	fn mul(self, rhs: &DMatrix<T>) -> DMatrix<T> {
+		assert_eq!(self.columns, rhs.rows, "Dimensions must match");
+
+		let mut out = DMatrix::zeros(self.rows, rhs.columns);
+		for i in 0..self.rows {
+			for k in 0..self.columns {
+				let a = self[(i, k)];
+				for j in 0..rhs.columns {
+					out[(i, j)] += a * rhs[(k, j)];
+				}
+			}
+		}
+		out
+	}
+}
+
+// Type-agnostic implementations.
+impl<T> DMatrix<T> {
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+
+	pub fn columns(&self) -> usize {
+		self.columns
+	}
+
+	/// Number of rows and columns.
+	pub fn shape(&self) -> (usize, usize) {
+		(self.rows, self.columns)
+	}
+
+	pub fn is_square(&self) -> bool {
+		self.rows == self.columns
+	}
+
+	pub fn as_ptr(&self) -> *const T {
+		self.data.as_ptr()
+	}
+
+	pub fn as_mut_ptr(&mut self) -> *mut T {
+		self.data.as_mut_ptr()
+	}
+
+	/// All of the elements, row by row.
+	pub fn as_slice(&self) -> &[T] {
+		&self.data
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		&mut self.data
+	}
+
+	pub fn into_vec(self) -> Vec<T> {
+		self.data
+	}
+
+	pub fn apply<F>(&mut self, f: F)
+	where
+		F: Fn(&mut T),
+	{
+		self.data.iter_mut().for_each(f);
+	}
+}
+
+impl<T: Copy> DMatrix<T> {
+	pub fn map<F, U>(&self, f: F) -> DMatrix<U>
+	where
+		F: Fn(T) -> U,
+	{
+		let data = self.data.iter().copied().map(f).collect();
+		DMatrix::from_vec(self.rows, self.columns, data)
+	}
+
+	pub fn map_diagonal<F>(&self, f: F) -> DMatrix<T>
+	where
+		F: Fn(T) -> T,
+	{
+		let mut out = self.clone();
+
+		for i in 0..self.rows.min(self.columns) {
+			out[(i, i)] = f(out[(i, i)]);
+		}
+
+		out
+	}
+
+	pub fn transpose(&self) -> DMatrix<T> {
+		let mut data = Vec::with_capacity(self.data.len());
+		for j in 0..self.columns {
+			for i in 0..self.rows {
+				data.push(self[(i, j)]);
+			}
+		}
+		DMatrix::from_vec(self.columns, self.rows, data)
+	}
+
+	pub fn is_symmetric(&self) -> bool
+	where
+		T: PartialEq,
+	{
+		if !self.is_square() {
+			return false;
+		}
+
+		for i in 0..self.rows {
+			for j in (i + 1)..self.columns {
+				if self[(i, j)] != self[(j, i)] {
+					return false;
+				}
+			}
+		}
+
+		true
+	}
+}
+
+// Matrix norms
+impl<F: Float> DMatrix<F> {
+	/// The largest absolute column sum.
+	pub fn one_norm(&self) -> F {
+		(0..self.columns)
+			.map(|j| {
+				(0..self.rows).fold(F::zero(), |s, i| {
+					s + self[(i, j)].abs()
+				})
+			})
+			.fold(F::zero(), F::max)
+	}
+
+	/// The largest absolute row sum.
+	pub fn infinity_norm(&self) -> F {
+		(0..self.rows)
+			.map(|i| {
+				self[i].iter()
+					.fold(F::zero(), |s, v| s + v.abs())
+			})
+			.fold(F::zero(), F::max)
+	}
+
+	/// Square root of the sum of the squares of all elements.
+	pub fn frobenius_norm(&self) -> F {
+		self.data.iter().fold(F::zero(), |s, v| s + *v * *v).sqrt()
+	}
+
+	/// The largest absolute element.
+	pub fn max_norm(&self) -> F {
+		self.data.iter().fold(F::zero(), |s, v| s.max(v.abs()))
+	}
+}
+
+impl DMatrix<f64> {
+	/// Lower Cholesky factor `L` of `A = L L^T`, or `None` if the matrix
+	/// isn't square or symmetric positive definite.  Only the lower triangle
+	/// is read.
+	pub fn cholesky(&self) -> Option<Self> {
+		if !self.is_square() {
+			return None;
+		}
+
+		let mut lower = Self::zeros(self.rows, self.rows);
+		cholesky_lower(&self.data, self.rows, &mut lower.data)
+			.then_some(lower)
+	}
+
+	/// Inverse calculated with Gauss-Jordan elimination.  With the `lapack`
+	/// feature LAPACK is used instead.
+	///
+	/// # Panics
+	///
+	/// If the matrix is singular or not square, see
+	/// [`try_inverse`][Self::try_inverse].
+	#[cfg(not(feature = "lapack"))]
+	pub fn inverse(&self) -> Self {
+		self.try_inverse().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`inverse`][Self::inverse], but returns an error if the matrix
+	/// is singular or not square.
+	#[cfg(not(feature = "lapack"))]
+	pub fn try_inverse(&self) -> Result<Self, Error> {
+		if !self.is_square() {
+			return Err(Error::Dimensions {
+				expected: (self.rows, self.rows),
+				found: self.shape(),
+			});
+		}
+		if self.data.iter().any(|v| !v.is_finite()) {
+			return Err(Error::NotFinite);
+		}
+
+		let n = self.rows;
+		let mut m = self.data.clone();
+		let mut out = Self::zeros(n, n);
+		crate::decomposition::gauss_jordan(
+			&mut m,
+			&mut out.data,
+			n,
+			f64::abs,
+		)?;
+		Ok(out)
+	}
+}
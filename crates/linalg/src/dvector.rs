@@ -0,0 +1,295 @@
+use num_traits::{Float, Num, NumAssign};
+
+use std::{
+	fmt::{self, Display},
+	ops::{
+		Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign,
+	},
+};
+
+use crate::{Error, Vector};
+
+/// A heap-allocated vector, for when the length is only known at runtime.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DVector<T> {
+	v: Vec<T>,
+}
+
+// `From` conversions
+impl<T> From<Vec<T>> for DVector<T> {
+	fn from(value: Vec<T>) -> Self {
+		Self { v: value }
+	}
+}
+
+impl<T> From<DVector<T>> for Vec<T> {
+	fn from(value: DVector<T>) -> Self {
+		value.v
+	}
+}
+
+impl<T: Copy> From<&[T]> for DVector<T> {
+	fn from(value: &[T]) -> Self {
+		value.to_vec().into()
+	}
+}
+
+impl<T, const N: usize> From<Vector<T, N>> for DVector<T> {
+	fn from(value: Vector<T, N>) -> Self {
+		Vec::from(value.to_array()).into()
+	}
+}
+
+impl<T, const N: usize> TryFrom<DVector<T>> for Vector<T, N> {
+	type Error = Error;
+
+	/// Fails with [`Error::Dimensions`] unless the length is `N`.  Vectors
+	/// are treated as columns.
+	fn try_from(value: DVector<T>) -> Result<Self, Error> {
+		let found = (value.len(), 1);
+		let array: [T; N] =
+			value.v.try_into().map_err(|_| Error::Dimensions {
+				expected: (N, 1),
+				found,
+			})?;
+		Ok(array.into())
+	}
+}
+
+impl<T: Display> Display for DVector<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("[")?;
+
+		for (i, item) in self.v.iter().enumerate() {
+			item.fmt(f)?;
+			if i != self.len() - 1 {
+				f.write_str(", ")?;
+			}
+		}
+
+		f.write_str("]")?;
+
+		Ok(())
+	}
+}
+
+// Constructors
+impl<T: Copy> DVector<T> {
+	pub fn from_element(length: usize, value: T) -> Self {
+		vec![value; length].into()
+	}
+}
+
+impl<T: Copy + Num> DVector<T> {
+	pub fn zeros(length: usize) -> Self {
+		Self::from_element(length, T::zero())
+	}
+
+	pub fn ones(length: usize) -> Self {
+		Self::from_element(length, T::one())
+	}
+
+	/// A standard basis vector: all elements are zero, except the one at
+	/// index `i`, which is set to one.
+	pub fn sbv(length: usize, i: usize) -> Self {
+		let mut out = Self::zeros(length);
+		out[i] = T::one();
+		out
+	}
+}
+
+// Operators.  Element-wise operations panic if the lengths differ.
+impl<T> Index<usize> for DVector<T> {
+	type Output = T;
+
+	fn index(&self, index: usize) -> &Self::Output {
+		&self.v[index]
+	}
+}
+
+impl<T> IndexMut<usize> for DVector<T> {
+	fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+		&mut self.v[index]
+	}
+}
+
+impl<T: Copy + AddAssign> AddAssign<&DVector<T>> for DVector<T> {
+	fn add_assign(&mut self, rhs: &Self) {
+		assert_eq!(self.len(), rhs.len(), "Vector lengths must match");
+		for i in 0..self.len() {
+			self[i] += rhs[i];
+		}
+	}
+}
+
+impl<T: Copy + AddAssign> Add<&DVector<T>> for DVector<T> {
+	type Output = Self;
+
+	fn add(mut self, rhs: &Self) -> Self::Output {
+		self += rhs;
+		self
+	}
+}
+
+impl<T: Copy + MulAssign> MulAssign<&DVector<T>> for DVector<T> {
+	fn mul_assign(&mut self, rhs: &Self) {
+		assert_eq!(self.len(), rhs.len(), "Vector lengths must match");
+		for i in 0..self.len() {
+			self[i] *= rhs[i];
+		}
+	}
+}
+
+impl<T: Copy + MulAssign> Mul<&DVector<T>> for DVector<T> {
+	type Output = Self;
+
+	fn mul(mut self, rhs: &Self) -> Self::Output {
+		self *= rhs;
+		self
+	}
+}
+
+impl<T: Copy + MulAssign> MulAssign<T> for DVector<T> {
+	fn mul_assign(&mut self, rhs: T) {
+		for item in &mut self.v {
+			*item *= rhs;
+		}
+	}
+}
+
+impl<T: Copy + MulAssign> Mul<T> for DVector<T> {
+	type Output = Self;
+
+	fn mul(mut self, rhs: T) -> Self::Output {
+		self *= rhs;
+		self
+	}
+}
+
+impl<T: Copy + DivAssign> DivAssign<&DVector<T>> for DVector<T> {
+	fn div_assign(&mut self, rhs: &Self) {
+		assert_eq!(self.len(), rhs.len(), "Vector lengths must match");
+		for i in 0..self.len() {
+			self[i] /= rhs[i];
+		}
+	}
+}
+
+impl<T: Copy + DivAssign> Div<&DVector<T>> for DVector<T> {
+	type Output = Self;
+
+	fn div(mut self, rhs: &Self) -> Self::Output {
+		self /= rhs;
+		self
+	}
+}
+
+impl<T: Copy + DivAssign> DivAssign<T> for DVector<T> {
+	fn div_assign(&mut self, rhs: T) {
+		for item in &mut self.v {
+			*item /= rhs;
+		}
+	}
+}
+
+impl<T: Copy + DivAssign> Div<T> for DVector<T> {
+	type Output = Self;
+
+	fn div(mut self, rhs: T) -> Self::Output {
+		self /= rhs;
+		self
+	}
+}
+
+// Type-agnostic implementations.
+impl<T> DVector<T> {
+	pub fn len(&self) -> usize {
+		self.v.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.v.is_empty()
+	}
+
+	pub fn as_ptr(&self) -> *const T {
+		self.v.as_ptr()
+	}
+
+	pub fn as_mut_ptr(&mut self) -> *mut T {
+		self.v.as_mut_ptr()
+	}
+
+	pub fn as_slice(&self) -> &[T] {
+		&self.v
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		&mut self.v
+	}
+
+	pub fn into_vec(self) -> Vec<T> {
+		self.v
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, T> {
+		self.v.iter()
+	}
+
+	pub fn apply<F>(&mut self, f: F)
+	where
+		F: Fn(&mut T),
+	{
+		self.v.iter_mut().for_each(f);
+	}
+
+	pub fn map<F, U>(&self, f: F) -> DVector<U>
+	where
+		F: Fn(T) -> U,
+		T: Copy,
+	{
+		self.v.iter().copied().map(f).collect::<Vec<_>>().into()
+	}
+}
+
+// Numeric methods.
+impl<T: Copy + NumAssign> DVector<T> {
+	pub fn sum(&self) -> T {
+		let mut out = T::zero();
+		for item in &self.v {
+			out += *item;
+		}
+		out
+	}
+
+	pub fn product(&self) -> T {
+		let mut out = T::one();
+		for item in &self.v {
+			out *= *item;
+		}
+		out
+	}
+
+	pub fn dot_product(&self, other: &DVector<T>) -> T {
+		assert_eq!(
+			self.len(),
+			other.len(),
+			"Vector lengths must match"
+		);
+
+		let mut out = T::zero();
+		for i in 0..self.len() {
+			out += self[i] * other[i];
+		}
+		out
+	}
+}
+
+impl<T: Float + NumAssign> DVector<T> {
+	pub fn norm(&self) -> T {
+		self.dot_product(self).sqrt()
+	}
+
+	pub fn normalize(&self) -> Self {
+		self.clone() / self.norm()
+	}
+}
@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+/// Failure of a decomposition, an inverse or a conversion.
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+	/// A LAPACK routine reported an illegal argument, the index of which
+	/// is `-info`.
+	#[error("argument {} of {routine} has an illegal value", -info)]
+	IllegalArgument { routine: &'static str, info: i32 },
+	/// A LAPACK routine failed with a positive `info`, the number of
+	/// unconverged eigenvalues for `dgeev` and `dsyev`.  Zero pivots in
+	/// `dgetrf` and `dgetri` are reported as [`Error::Singular`].
+	#[error("{routine} failed with info {info}")]
+	Lapack { routine: &'static str, info: i32 },
+	/// A real eigen-decomposition was requested for a matrix with complex
+	/// eigenvalues.
+	#[error("the matrix has complex eigenvalues")]
+	ComplexEigenvalues,
+	/// The matrix is singular.
+	#[error("the matrix is singular")]
+	Singular,
+	/// The matrix has infinite or NaN elements.
+	#[error("the matrix has non-finite elements")]
+	NotFinite,
+	/// A dynamically sized matrix or vector doesn't have the expected
+	/// dimensions.
+	#[error("expected dimensions {expected:?}, found {found:?}")]
+	Dimensions {
+		expected: (usize, usize),
+		found: (usize, usize),
+	},
+}
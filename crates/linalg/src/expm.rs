@@ -0,0 +1,65 @@
+//! Matrix exponential.
+
+use crate::{Error, RowMatrix};
+
+/// Degree of the Padé approximant.
+const DEGREE: usize = 6;
+
+impl<const N: usize> RowMatrix<f64, N, N> {
+	/// Calculates `e^A` with the scaling and squaring method: the matrix is
+	/// scaled down by a power of two until its norm is at most 1/2, the
+	/// exponential of the scaled matrix is approximated with a diagonal Padé
+	/// approximant, and the result is squared back up.
+	///
+	/// Unlike the eigen-decomposition, this works for any matrix, including
+	/// ones with complex eigenvalues or defective ones.
+	///
+	/// # Panics
+	///
+	/// If the matrix has non-finite elements, see
+	/// [`try_expm`][Self::try_expm].
+	pub fn expm(&self) -> Self {
+		self.try_expm().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`expm`][Self::expm], but returns an error if the matrix has
+	/// non-finite elements.
+	pub fn try_expm(&self) -> Result<Self, Error> {
+		// Infinity norm: the largest absolute row sum
+		let norm = (0..N)
+			.map(|i| {
+				(0..N).map(|j| self[(i, j)].abs()).sum::<f64>()
+			})
+			.fold(0.0, f64::max);
+		let squarings = if norm > 0.5 {
+			(norm / 0.5).log2().ceil() as i32
+		} else {
+			0
+		};
+		let a = *self * 0.5f64.powi(squarings);
+
+		let identity = Self::identity();
+		let mut power = a;
+		let mut coefficient = 0.5;
+		let mut numerator = identity + a * coefficient;
+		let mut denominator = identity + a * -coefficient;
+		for k in 2..=DEGREE {
+			coefficient *= (DEGREE - k + 1) as f64
+				/ (k * (2 * DEGREE - k + 1)) as f64;
+			power = power * a;
+
+			numerator += power * coefficient;
+			let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+			denominator += power * (sign * coefficient);
+		}
+
+		// The denominator is close to the identity for a scaled matrix,
+		// so it's only singular if something has overflowed.
+		let mut out =
+			denominator.checked_lu()?.solve_matrix(&numerator);
+		for _ in 0..squarings {
+			out = out * out;
+		}
+		Ok(out)
+	}
+}
@@ -13,3 +13,37 @@ impl<F: Float, const N: usize, const M: usize> RowMatrix<F, N, M> {
 		self.map(|e| e.abs())
 	}
 }
+
+// Matrix norms
+impl<F: Float, const N: usize, const M: usize> RowMatrix<F, N, M> {
+	/// The largest absolute column sum.
+	pub fn one_norm(&self) -> F {
+		(0..N).map(|j| {
+			(0..M).fold(F::zero(), |s, i| s + self[i][j].abs())
+		})
+		.fold(F::zero(), F::max)
+	}
+
+	/// The largest absolute row sum.
+	pub fn infinity_norm(&self) -> F {
+		(0..M).map(|i| {
+			(0..N).fold(F::zero(), |s, j| s + self[i][j].abs())
+		})
+		.fold(F::zero(), F::max)
+	}
+
+	/// Square root of the sum of the squares of all elements.
+	pub fn frobenius_norm(&self) -> F {
+		(0..M).flat_map(|i| (0..N).map(move |j| (i, j)))
+			.fold(F::zero(), |s, (i, j)| {
+				s + self[i][j] * self[i][j]
+			})
+			.sqrt()
+	}
+
+	/// The largest absolute element.
+	pub fn max_norm(&self) -> F {
+		(0..M).flat_map(|i| (0..N).map(move |j| (i, j)))
+			.fold(F::zero(), |s, (i, j)| s.max(self[i][j].abs()))
+	}
+}
@@ -3,17 +3,24 @@
 use std::cmp::min;
 use std::ffi::{c_char, c_int};
 
-use crate::{RowMatrix, Vector};
-
-macro_rules! assert_info {
-	($info:ident, $($rest:expr),+ $(,)?) => {
-		if $info < 0 {
-			panic!("Argument {} has an illegal value", -$info);
-		}
-		if $info > 0 {
-			panic!($($rest),+)
-		}
-	};
+use crate::Error;
+
+/// Turns a non-zero `info` into an error.
+fn check_info(routine: &'static str, info: i32) -> Result<(), Error> {
+	match info {
+		0 => Ok(()),
+		..0 => Err(Error::IllegalArgument { routine, info }),
+		_ => Err(Error::Lapack { routine, info }),
+	}
+}
+
+/// Like [`check_info`], but a positive `info` is the index of a zero pivot,
+/// which means that the matrix is singular.
+fn check_pivot(routine: &'static str, info: i32) -> Result<(), Error> {
+	match info {
+		1.. => Err(Error::Singular),
+		_ => check_info(routine, info),
+	}
 }
 
 /// Returns `V` for true and `N` for false.
@@ -26,31 +33,38 @@ fn job_char(do_job: bool) -> c_char {
 }
 
 /// Calculates eigenvalues and optionally left and/or right eigenvectors if
-/// `left` and `right` are set.  Note that it discards the imaginary parts of
-/// eigenvalues.  If `left` or `right` aren't set, the values of the returning
+/// `left` and `right` are set.  The real and the imaginary parts of the
+/// eigenvalues are written to `wr` and `wi`, and the eigenvectors to `vl` and
+/// `vr`.  If `left` or `right` aren't set, the values of the matching
 /// eigenvector matrices are undefined.
-pub fn dgeev<const N: usize>(
-	matrix: &RowMatrix<f64, N, N>,
+///
+/// All matrices are `n` by `n` and stored contiguously.  `matrix` is
+/// overwritten.  The outputs are passed in, so that callers with a fixed size
+/// can keep them on the stack.
+#[allow(clippy::too_many_arguments)]
+pub fn dgeev(
+	matrix: &mut [f64],
+	n: usize,
 	left: bool,
 	right: bool,
-) -> (Vector<f64, N>, RowMatrix<f64, N, N>, RowMatrix<f64, N, N>) {
+	wr: &mut [f64],
+	wi: &mut [f64],
+	vl: &mut [f64],
+	vr: &mut [f64],
+) -> Result<(), Error> {
+	assert_eq!(matrix.len(), n * n);
+	assert_eq!((wr.len(), wi.len()), (n, n));
+	assert_eq!((vl.len(), vr.len()), (n * n, n * n));
+
 	let jobvl = job_char(left);
 	let jobvr = job_char(right);
-	let n = N as c_int;
-
-	let mut a = *matrix;
+	let size = n;
+	let n = size as c_int;
 	let lda = n;
-
-	let mut wr: Vector<f64, N> = Default::default();
-	let mut wi: Vector<f64, N> = Default::default();
-
-	let mut vl: RowMatrix<f64, N, N> = Default::default();
 	let ldvl = n;
-
-	let mut vr: RowMatrix<f64, N, N> = Default::default();
 	let ldvr = n;
 
-	let mut work = vec![0f64; 4 * N];
+	let mut work = vec![0f64; 4 * size];
 	let lwork = work.len() as c_int;
 
 	let mut info = 0i32;
@@ -60,7 +74,7 @@ pub fn dgeev<const N: usize>(
 			&jobvl,
 			&jobvr,
 			&n,
-			a.as_mut_ptr(),
+			matrix.as_mut_ptr(),
 			&lda,
 			wr.as_mut_ptr(),
 			wi.as_mut_ptr(),
@@ -74,35 +88,37 @@ pub fn dgeev<const N: usize>(
 		)
 	}
 
-	assert_info!(
-		info,
-		"the QR algorithm failed to compute all the eigenvalues"
-	);
-
-	(wr, vl, vr)
+	// A positive info means that the QR algorithm failed to compute all
+	// of the eigenvalues
+	check_info("dgeev", info)
 }
 
-/// Calculates eigenvalues and optionally eigenvectors for a symmetric matrix.
-/// Note that it's the callers responsibility to verify that `matrix` is
-/// symmetric.  If it's not, it'll be treated as if it was a matrix symmetric
-/// against the upper triangle of `matrix`.
-pub fn dsyev<const N: usize>(
-	matrix: &RowMatrix<f64, N, N>,
+/// Calculates eigenvalues and optionally eigenvectors for a symmetric `n` by
+/// `n` matrix.  Note that it's the callers responsibility to verify that
+/// `matrix` is symmetric.  If it's not, it'll be treated as if it was a matrix
+/// symmetric against the upper triangle of `matrix`.
+///
+/// The eigenvalues are written to `w`, and the eigenvectors replace
+/// `matrix`.
+pub fn dsyev(
+	matrix: &mut [f64],
+	n: usize,
 	compute_eigenvectors: bool,
-) -> (Vector<f64, N>, RowMatrix<f64, N, N>) {
+	w: &mut [f64],
+) -> Result<(), Error> {
+	assert_eq!(matrix.len(), n * n);
+	assert_eq!(w.len(), n);
+
 	let jobz = job_char(compute_eigenvectors);
 	// doesn't matter, as the input must be symmetric
 	let uplo = b'U' as c_char;
 
-	let n = N as c_int;
-
-	let mut a = *matrix;
+	let size = n;
+	let n = size as c_int;
 	let lda = n;
 
-	let mut w: Vector<f64, N> = Default::default();
-
-	let mut work = vec![0.0; 4 * N];
-	let lwork = 4 * N as c_int;
+	let mut work = vec![0.0; 4 * size];
+	let lwork = work.len() as c_int;
 
 	let mut info: i32 = 0;
 
@@ -111,7 +127,7 @@ pub fn dsyev<const N: usize>(
 			&jobz,
 			&uplo,
 			&n,
-			a.as_mut_ptr(),
+			matrix.as_mut_ptr(),
 			&lda,
 			w.as_mut_ptr(),
 			work.as_mut_ptr(),
@@ -120,53 +136,55 @@ pub fn dsyev<const N: usize>(
 		)
 	}
 
-	assert_info!(info, "the algorithm failed to converge, {info} off-diagonal elements of an intermediate tridiagonal form did not converge to zero");
-
-	(w, a)
+	// A positive info is the number of off-diagonal elements of an
+	// intermediate tridiagonal form which did not converge to zero
+	check_info("dsyev", info)
 }
 
-pub fn dgetrf<const N: usize, const M: usize>(
-	matrix: &RowMatrix<f64, N, M>,
-) -> (RowMatrix<f64, N, M>, Vec<i32>) {
-	let m = M as c_int;
-	let n = N as c_int;
-
-	let mut a = *matrix;
+/// LU factorization of an `m` by `n` matrix, which replaces `matrix`.  The
+/// pivot indices are written to `ipiv`.
+pub fn dgetrf(
+	matrix: &mut [f64],
+	n: usize,
+	m: usize,
+	ipiv: &mut [i32],
+) -> Result<(), Error> {
+	assert_eq!(matrix.len(), n * m);
+	assert_eq!(ipiv.len(), min(n, m));
+
+	let m = m as c_int;
+	let n = n as c_int;
 	let lda = m;
 
-	let mut ipiv = vec![0; min(N, M)];
-
 	let mut info: i32 = 0;
 
 	unsafe {
 		lapack_sys::dgetrf_(
 			&m,
 			&n,
-			a.as_mut_ptr(),
+			matrix.as_mut_ptr(),
 			&lda,
 			ipiv.as_mut_ptr(),
 			&mut info,
 		)
 	}
 
-	assert_info!(info, "U({info}, {info}) is exactly zero.  Division by zero will occur if U is used");
-
-	(a, ipiv)
+	// A positive info means that `U(info, info)` is exactly zero
+	check_pivot("dgetrf", info)
 }
 
-pub fn dgetri<const N: usize>(
-	matrix: &RowMatrix<f64, N, N>,
-	ipiv: &[i32],
-) -> RowMatrix<f64, N, N> {
-	assert_eq!(ipiv.len(), N);
+/// Inverse of an `n` by `n` matrix from its LU factorization in `matrix`,
+/// which is replaced by the inverse.
+pub fn dgetri(matrix: &mut [f64], n: usize, ipiv: &[i32]) -> Result<(), Error> {
+	assert_eq!(matrix.len(), n * n);
+	assert_eq!(ipiv.len(), n);
 
-	let n = N as c_int;
-
-	let mut a = *matrix;
+	let size = n;
+	let n = size as c_int;
 	let lda = n;
 
 	// TODO: blocksize
-	let len = N * 4;
+	let len = size * 4;
 	let mut work = vec![0.0; len];
 	let lwork = len as c_int;
 
@@ -175,7 +193,7 @@ pub fn dgetri<const N: usize>(
 	unsafe {
 		lapack_sys::dgetri_(
 			&n,
-			a.as_mut_ptr(),
+			matrix.as_mut_ptr(),
 			&lda,
 			ipiv.as_ptr(),
 			work.as_mut_ptr(),
@@ -184,7 +202,7 @@ pub fn dgetri<const N: usize>(
 		)
 	}
 
-	assert_info!(info, "U({info}, {info}) is exactly zero, the input matrix is singular");
-
-	a
+	// A positive info means that `U(info, info)` is exactly zero, so the
+	// matrix is singular
+	check_pivot("dgetri", info)
 }
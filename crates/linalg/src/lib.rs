@@ -2,13 +2,27 @@
 mod approx;
 #[cfg(feature = "bytemuck")]
 mod bytemuck;
+mod complex;
+mod decomposition;
+mod dmatrix;
+mod dvector;
+mod error;
+mod expm;
 mod float;
+#[cfg(feature = "lapack")]
 mod lapack;
+#[cfg(feature = "lapack")]
 mod math;
 #[cfg(feature = "proptest")]
 pub mod proptest;
 mod row_matrix;
 mod vector;
 
+pub use complex::ComplexEigen;
+pub use decomposition::{Cholesky, Lu};
+pub use dmatrix::DMatrix;
+pub use dvector::DVector;
+pub use error::Error;
+pub use num_complex::Complex64;
 pub use row_matrix::RowMatrix;
 pub use vector::Vector;
@@ -1,44 +1,369 @@
-use crate::{lapack, RowMatrix, Vector};
+use num_complex::Complex64;
+
+use crate::{lapack, ComplexEigen, DMatrix, DVector, Error, RowMatrix, Vector};
+
+type Eigen<const N: usize> =
+	(Vector<f64, N>, RowMatrix<f64, N, N>, RowMatrix<f64, N, N>);
 
 impl<const N: usize> RowMatrix<f64, N, N> {
 	/// Returns eigenvalues and matrices whose rows are right and left
-	/// eigenvectors, in that order.
-	pub fn eigen(
-		&self,
-	) -> (Vector<f64, N>, RowMatrix<f64, N, N>, RowMatrix<f64, N, N>) {
-		eigen(self, true, true)
+	/// eigenvectors, in that order.  Complex eigenvalues are reduced to
+	/// their real parts, use [`try_eigen`][Self::try_eigen] to detect them.
+	///
+	/// # Panics
+	///
+	/// If LAPACK fails, see [`try_eigen`][Self::try_eigen].
+	pub fn eigen(&self) -> Eigen<N> {
+		let (eigen, _) = eigen(self, true, true)
+			.unwrap_or_else(|e| panic!("{e}"));
+		eigen
+	}
+
+	/// Like [`eigen`][Self::eigen], but returns an error if LAPACK fails or
+	/// [`Error::ComplexEigenvalues`] if some of the eigenvalues are complex.
+	/// See [`complex_eigen`][Self::complex_eigen] for those matrices.
+	pub fn try_eigen(&self) -> Result<Eigen<N>, Error> {
+		let (eigen, imaginary) = eigen(self, true, true)?;
+		check_real(imaginary.as_array())?;
+		Ok(eigen)
 	}
 
 	pub fn eigenvectors(&self) -> RowMatrix<f64, N, N> {
-		let (_, right, _) = eigen(self, true, false);
+		let ((_, right, _), _) = eigen(self, true, false)
+			.unwrap_or_else(|e| panic!("{e}"));
 		right
 	}
 
 	pub fn left_eigenvectors(&self) -> RowMatrix<f64, N, N> {
-		let (_, _, left) = eigen(self, false, true);
+		let ((_, _, left), _) = eigen(self, false, true)
+			.unwrap_or_else(|e| panic!("{e}"));
 		left
 	}
 
 	pub fn eigenvalues(&self) -> Vector<f64, N> {
-		let (values, _, _) = eigen(self, false, false);
+		let ((values, _, _), _) = eigen(self, false, false)
+			.unwrap_or_else(|e| panic!("{e}"));
 		values
 	}
 
+	/// Like [`try_eigen`][Self::try_eigen], but returns `None` if some of
+	/// the eigenvalues are complex.
+	pub fn real_eigen(&self) -> Result<Option<Eigen<N>>, Error> {
+		check_finite(self.as_slice())?;
+		if self.is_symmetric() {
+			return self.try_eigen().map(Some);
+		}
+
+		let (values, imaginary, left, right) = dgeev(self, true, true)?;
+		if imaginary.as_array().iter().any(|v| *v != 0.0) {
+			return Ok(None);
+		}
+		// LAPACK sees the transpose of the row-major matrix, so its left
+		// eigenvectors are the right ones of `self`.
+		Ok(Some((values, left, right)))
+	}
+
+	/// Eigenvalues, including complex ones.  Complex eigenvalues come in
+	/// adjacent conjugate pairs, the one with the positive imaginary part
+	/// first.
+	pub fn complex_eigenvalues(&self) -> Vector<Complex64, N> {
+		let (real, imaginary, _, _) = dgeev(self, false, false)
+			.unwrap_or_else(|e| panic!("{e}"));
+		to_complex(real, imaginary)
+	}
+
+	/// Diagonalization of a general matrix, with complex eigenvalues and
+	/// eigenvectors.
+	///
+	/// # Panics
+	///
+	/// If LAPACK fails or the matrix isn't diagonalizable, see
+	/// [`try_complex_eigen`][Self::try_complex_eigen].
+	pub fn complex_eigen(&self) -> ComplexEigen<N> {
+		self.try_complex_eigen().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`complex_eigen`][Self::complex_eigen], but returns an error if
+	/// LAPACK fails or [`Error::Singular`] if the matrix isn't
+	/// diagonalizable.
+	pub fn try_complex_eigen(&self) -> Result<ComplexEigen<N>, Error> {
+		check_finite(self.as_slice())?;
+		let (real, imaginary, vectors, _) = dgeev(self, true, false)?;
+		let values = to_complex(real, imaginary);
+
+		// LAPACK sees the transpose, so its left eigenvectors `u` are the
+		// right ones of `self`, but conjugated, as they satisfy `u^H A^T
+		// = lambda u^H`.  The vectors of a conjugate pair are stored as
+		// the real and the imaginary parts in two adjacent rows.
+		let mut right = RowMatrix::<Complex64, N, N>::default();
+		let mut i = 0;
+		while i < N {
+			if imaginary[i] == 0.0 {
+				right[i] = vectors[i].map(Complex64::from);
+				i += 1;
+			} else {
+				for j in 0..N {
+					let re = vectors[(i, j)];
+					let im = vectors[(i + 1, j)];
+					right[(i, j)] = Complex64::new(re, -im);
+					right[(i + 1, j)] =
+						Complex64::new(re, im);
+				}
+				i += 2;
+			}
+		}
+
+		ComplexEigen::new(values, right)
+	}
+
+	/// Eigenvalues and eigenvectors (as rows) of a symmetric matrix.  Only
+	/// the upper triangle of `self` is used, so small asymmetries from
+	/// rounding errors are ignored.
+	pub fn symmetric_eigen(
+		&self,
+	) -> Result<(Vector<f64, N>, RowMatrix<f64, N, N>), Error> {
+		check_finite(self.as_slice())?;
+		dsyev(self, true)
+	}
+
+	/// # Panics
+	///
+	/// If the matrix is singular, see [`try_inverse`][Self::try_inverse].
 	pub fn inverse(&self) -> Self {
-		let (lu, ipiv) = lapack::dgetrf(self);
-		lapack::dgetri(&lu, &ipiv)
+		self.try_inverse().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`inverse`][Self::inverse], but returns an error if the matrix
+	/// is singular.
+	pub fn try_inverse(&self) -> Result<Self, Error> {
+		check_finite(self.as_slice())?;
+		let mut out = *self;
+		let mut ipiv = [0; N];
+		lapack::dgetrf(out.as_mut_slice(), N, N, &mut ipiv)?;
+		lapack::dgetri(out.as_mut_slice(), N, &ipiv)?;
+		Ok(out)
+	}
+}
+
+fn to_complex<const N: usize>(
+	real: Vector<f64, N>,
+	imaginary: Vector<f64, N>,
+) -> Vector<Complex64, N> {
+	let mut out = Vector::default();
+	for i in 0..N {
+		out[i] = Complex64::new(real[i], imaginary[i]);
+	}
+	out
+}
+
+type DEigen = (DVector<f64>, DMatrix<f64>, DMatrix<f64>);
+
+impl DMatrix<f64> {
+	/// Returns eigenvalues and matrices whose rows are right and left
+	/// eigenvectors, in that order.  Complex eigenvalues are reduced to
+	/// their real parts, use [`try_eigen`][Self::try_eigen] to detect them.
+	///
+	/// # Panics
+	///
+	/// If the matrix isn't square or LAPACK fails, see
+	/// [`try_eigen`][Self::try_eigen].
+	pub fn eigen(&self) -> DEigen {
+		let (eigen, _) =
+			self.decompose().unwrap_or_else(|e| panic!("{e}"));
+		eigen
+	}
+
+	/// Like [`eigen`][Self::eigen], but returns an error if the matrix isn't
+	/// square or LAPACK fails, and [`Error::ComplexEigenvalues`] if some of
+	/// the eigenvalues are complex.
+	pub fn try_eigen(&self) -> Result<DEigen, Error> {
+		let (eigen, imaginary) = self.decompose()?;
+		check_real(imaginary.as_slice())?;
+		Ok(eigen)
+	}
+
+	/// The decomposition of [`eigen`][Self::eigen] and the imaginary parts
+	/// of the eigenvalues.
+	fn decompose(&self) -> Result<(DEigen, DVector<f64>), Error> {
+		check_square(self)?;
+		check_finite(self.as_slice())?;
+
+		let n = self.rows();
+		let mut a = self.clone();
+		let mut values = DVector::zeros(n);
+		if self.is_symmetric() {
+			lapack::dsyev(
+				a.as_mut_slice(),
+				n,
+				true,
+				values.as_mut_slice(),
+			)?;
+			Ok(((values, a.clone(), a), DVector::zeros(n)))
+		} else {
+			let mut imaginary = DVector::zeros(n);
+			let mut left = DMatrix::zeros(n, n);
+			let mut right = DMatrix::zeros(n, n);
+			lapack::dgeev(
+				a.as_mut_slice(),
+				n,
+				true,
+				true,
+				values.as_mut_slice(),
+				imaginary.as_mut_slice(),
+				left.as_mut_slice(),
+				right.as_mut_slice(),
+			)?;
+			Ok(((values, left, right), imaginary))
+		}
+	}
+
+	pub fn eigenvectors(&self) -> DMatrix<f64> {
+		let (_, right, _) = self.eigen();
+		right
 	}
+
+	pub fn left_eigenvectors(&self) -> DMatrix<f64> {
+		let (_, _, left) = self.eigen();
+		left
+	}
+
+	pub fn eigenvalues(&self) -> DVector<f64> {
+		let (values, _, _) = self.eigen();
+		values
+	}
+
+	/// Eigenvalues and eigenvectors (as rows) of a symmetric matrix.  Only
+	/// the upper triangle of `self` is used.
+	pub fn symmetric_eigen(
+		&self,
+	) -> Result<(DVector<f64>, DMatrix<f64>), Error> {
+		check_square(self)?;
+		check_finite(self.as_slice())?;
+
+		let n = self.rows();
+		let mut vectors = self.clone();
+		let mut values = DVector::zeros(n);
+		lapack::dsyev(
+			vectors.as_mut_slice(),
+			n,
+			true,
+			values.as_mut_slice(),
+		)?;
+		Ok((values, vectors))
+	}
+
+	/// # Panics
+	///
+	/// If the matrix is singular or not square, see
+	/// [`try_inverse`][Self::try_inverse].
+	pub fn inverse(&self) -> Self {
+		self.try_inverse().unwrap_or_else(|e| panic!("{e}"))
+	}
+
+	/// Like [`inverse`][Self::inverse], but returns an error if the matrix
+	/// is singular or not square.
+	pub fn try_inverse(&self) -> Result<Self, Error> {
+		check_square(self)?;
+		check_finite(self.as_slice())?;
+
+		let n = self.rows();
+		let mut out = self.clone();
+		let mut ipiv = vec![0; n];
+		lapack::dgetrf(out.as_mut_slice(), n, n, &mut ipiv)?;
+		lapack::dgetri(out.as_mut_slice(), n, &ipiv)?;
+		Ok(out)
+	}
+}
+
+fn check_square(matrix: &DMatrix<f64>) -> Result<(), Error> {
+	if matrix.is_square() {
+		Ok(())
+	} else {
+		Err(Error::Dimensions {
+			expected: (matrix.rows(), matrix.rows()),
+			found: matrix.shape(),
+		})
+	}
+}
+
+/// Fails if any of the imaginary parts of the eigenvalues aren't zero.
+fn check_real(imaginary: &[f64]) -> Result<(), Error> {
+	if imaginary.iter().all(|v| *v == 0.0) {
+		Ok(())
+	} else {
+		Err(Error::ComplexEigenvalues)
+	}
+}
+
+/// LAPACK routines may loop forever or return garbage on NaN inputs.
+fn check_finite(matrix: &[f64]) -> Result<(), Error> {
+	if matrix.iter().all(|v| v.is_finite()) {
+		Ok(())
+	} else {
+		Err(Error::NotFinite)
+	}
+}
+
+#[allow(clippy::type_complexity)]
+fn dgeev<const N: usize>(
+	matrix: &RowMatrix<f64, N, N>,
+	left: bool,
+	right: bool,
+) -> Result<
+	(
+		Vector<f64, N>,
+		Vector<f64, N>,
+		RowMatrix<f64, N, N>,
+		RowMatrix<f64, N, N>,
+	),
+	Error,
+> {
+	let mut a = *matrix;
+	let mut wr = Vector::default();
+	let mut wi = Vector::default();
+	let mut vl = RowMatrix::default();
+	let mut vr = RowMatrix::default();
+	lapack::dgeev(
+		a.as_mut_slice(),
+		N,
+		left,
+		right,
+		wr.as_mut_slice(),
+		wi.as_mut_slice(),
+		vl.as_mut_slice(),
+		vr.as_mut_slice(),
+	)?;
+	Ok((wr, wi, vl, vr))
+}
+
+fn dsyev<const N: usize>(
+	matrix: &RowMatrix<f64, N, N>,
+	compute_eigenvectors: bool,
+) -> Result<(Vector<f64, N>, RowMatrix<f64, N, N>), Error> {
+	let mut vectors = *matrix;
+	let mut values = Vector::default();
+	lapack::dsyev(
+		vectors.as_mut_slice(),
+		N,
+		compute_eigenvectors,
+		values.as_mut_slice(),
+	)?;
+	Ok((values, vectors))
 }
 
+/// Real eigen-decomposition and the imaginary parts of the eigenvalues.
 fn eigen<const N: usize>(
 	matrix: &RowMatrix<f64, N, N>,
 	left: bool,
 	right: bool,
-) -> (Vector<f64, N>, RowMatrix<f64, N, N>, RowMatrix<f64, N, N>) {
+) -> Result<(Eigen<N>, Vector<f64, N>), Error> {
+	check_finite(matrix.as_slice())?;
 	if matrix.is_symmetric() {
-		let (values, vectors) = lapack::dsyev(matrix, left || right);
-		(values, vectors, vectors)
+		let (values, vectors) = dsyev(matrix, left || right)?;
+		Ok(((values, vectors, vectors), Vector::default()))
 	} else {
-		lapack::dgeev(matrix, left, right)
+		let (values, imaginary, left, right) =
+			dgeev(matrix, left, right)?;
+		Ok(((values, left, right), imaginary))
 	}
 }
@@ -214,6 +214,34 @@ where
 	}
 }
 
+impl<T, const N: usize, const M: usize> RowMatrix<T, N, M>
+where
+	T: Copy + NumAssign + Default,
+{
+	/// Multiplies every vector in `rhs` by the matrix, storing the results in
+	/// `out`.
+	///
+	/// The products are accumulated as a sum of the matrix columns scaled by
+	/// the vector elements, so the inner loop works on whole vectors and can
+	/// be vectorized by the compiler.
+	pub fn mul_slice(
+		&self,
+		rhs: &[Vector<T, N>],
+		out: &mut [Vector<T, M>],
+	) {
+		assert_eq!(rhs.len(), out.len());
+
+		let columns = self.transpose();
+		for (vector, out) in rhs.iter().zip(out) {
+			let mut sum = Vector::default();
+			for k in 0..N {
+				sum += columns[k] * vector[k];
+			}
+			*out = sum;
+		}
+	}
+}
+
 impl<T, const N: usize, const M: usize> Mul<T> for RowMatrix<T, N, M>
 where
 	T: Copy + MulAssign,
@@ -277,6 +305,27 @@ impl<T: Copy, const N: usize, const M: usize> RowMatrix<T, N, M> {
 		self[0].as_ptr()
 	}
 
+	/// All of the elements, row by row.
+	pub fn as_slice(&self) -> &[T] {
+		// SAFETY: both the matrix and the rows are `repr(C)` arrays, so
+		// the elements are contiguous.
+		unsafe { std::slice::from_raw_parts(self.as_ptr(), N * M) }
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		// SAFETY: see `as_slice`
+		unsafe {
+			std::slice::from_raw_parts_mut(self.as_mut_ptr(), N * M)
+		}
+	}
+
+	/// Panics if the length of `slice` isn't `N * M`.
+	pub(crate) fn from_slice(slice: &[T]) -> Self {
+		assert_eq!(slice.len(), N * M, "Slice length must be N * M");
+		let mut rows = slice.chunks_exact(N).map(Vector::from_slice);
+		std::array::from_fn(|_| rows.next().unwrap()).into()
+	}
+
 	pub fn apply<F>(&mut self, f: F)
 	where
 		F: Fn(&mut T),
@@ -24,6 +24,13 @@ impl<T: Copy, const N: usize> Vector<T, N> {
 	fn from_element(value: T) -> Self {
 		[value; N].into()
 	}
+
+	/// Panics if the length of `slice` isn't `N`.
+	pub(crate) fn from_slice(slice: &[T]) -> Self {
+		let array: [T; N] =
+			slice.try_into().expect("Slice length must be N");
+		array.into()
+	}
 }
 
 impl<T: Debug, const N: usize> Debug for Vector<T, N> {
@@ -233,6 +240,14 @@ impl<T, const N: usize> Vector<T, N> {
 		self.v
 	}
 
+	pub fn as_slice(&self) -> &[T] {
+		&self.v
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		&mut self.v
+	}
+
 	pub fn apply<F>(&mut self, f: F)
 	where
 		F: Fn(&mut T),
@@ -0,0 +1,75 @@
+use linalg::{Complex64, ComplexEigen, Error, RowMatrix};
+
+fn assert_close<const N: usize>(
+	a: RowMatrix<f64, N, N>,
+	b: RowMatrix<f64, N, N>,
+) {
+	for i in 0..N {
+		for j in 0..N {
+			assert!(
+				(a[(i, j)] - b[(i, j)]).abs() < 1e-12,
+				"{a} != {b}"
+			);
+		}
+	}
+}
+
+/// The eigenvalues of a rotation generator are `±i theta`.
+fn rotation(theta: f64) -> ComplexEigen<2> {
+	let i = Complex64::i();
+	let one = Complex64::from(1.0);
+	ComplexEigen::new(
+		[i * theta, -i * theta].into(),
+		RowMatrix::from([[one, -i], [one, i]]),
+	)
+	.unwrap()
+}
+
+#[test]
+fn reconstruct() {
+	let theta = 0.7;
+	let m = RowMatrix::from([[0.0, -theta], [theta, 0.0]]);
+	assert_close(rotation(theta).reconstruct(), m);
+}
+
+#[test]
+fn exp() {
+	let theta = 0.7f64;
+	let t = 2.0;
+	let m = RowMatrix::from([[0.0, -theta], [theta, 0.0]]);
+	let angle = theta * t;
+	let expected = RowMatrix::from([
+		[angle.cos(), -angle.sin()],
+		[angle.sin(), angle.cos()],
+	]);
+
+	assert_close(rotation(theta).exp(t), expected);
+	assert_close(rotation(theta).exp(t), (m * t).expm());
+}
+
+#[test]
+fn biorthogonal() {
+	let eigen = rotation(1.3);
+	let (left, right) = (eigen.left(), eigen.right());
+	for i in 0..2 {
+		for j in 0..2 {
+			let product: Complex64 = (0..2)
+				.map(|k| left[(i, k)] * right[(j, k)])
+				.sum();
+			let expected = if i == j { 1.0 } else { 0.0 };
+			assert!((product - expected).norm() < 1e-12);
+		}
+	}
+}
+
+#[test]
+fn defective() {
+	// A Jordan block only has a single eigenvector
+	let one = Complex64::from(1.0);
+	let zero = Complex64::from(0.0);
+	let eigen = ComplexEigen::new(
+		[one, one].into(),
+		RowMatrix::from([[one, zero], [one, zero]]),
+	);
+	assert_eq!(eigen.unwrap_err(), Error::Singular);
+}
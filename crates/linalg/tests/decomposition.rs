@@ -0,0 +1,88 @@
+use linalg::{Error, RowMatrix, Vector};
+
+fn assert_close<const N: usize>(a: Vector<f64, N>, b: Vector<f64, N>) {
+	for i in 0..N {
+		assert!((a[i] - b[i]).abs() < 1e-12, "{a:?} != {b:?}");
+	}
+}
+
+#[test]
+fn lu_solve() {
+	// The first pivot is zero, so the rows have to be swapped
+	let m = RowMatrix::from([
+		[0.0, 2.0, 1.0],
+		[1.0, 1.0, 0.0],
+		[3.0, 0.0, 1.0],
+	]);
+	let lu = m.lu().unwrap();
+
+	let x: Vector<f64, 3> = [1.0, -2.0, 0.5].into();
+	assert_close(lu.solve(m * x), x);
+
+	let inverse = lu.inverse();
+	let product = m * inverse;
+	for i in 0..3 {
+		assert_close(product[i], RowMatrix::<f64, 3, 3>::identity()[i]);
+	}
+}
+
+#[test]
+fn determinant() {
+	let m = RowMatrix::from([
+		[0.0, 2.0, 1.0],
+		[1.0, 1.0, 0.0],
+		[3.0, 0.0, 1.0],
+	]);
+	// 0 * 1 - 2 * 1 + 1 * (0 - 3)
+	assert!((m.determinant() + 5.0).abs() < 1e-12);
+
+	let singular = RowMatrix::from([[1.0, 2.0], [2.0, 4.0]]);
+	assert!(singular.lu().is_none());
+	assert_eq!(singular.determinant(), 0.0);
+}
+
+#[test]
+fn cholesky() {
+	let m = RowMatrix::from([
+		[4.0, 2.0, 0.4],
+		[2.0, 5.0, 1.0],
+		[0.4, 1.0, 3.0],
+	]);
+	let cholesky = m.cholesky().unwrap();
+
+	let lower = cholesky.lower();
+	let product = lower * lower.transpose();
+	for i in 0..3 {
+		assert_close(product[i], m[i]);
+	}
+
+	let x: Vector<f64, 3> = [0.3, -1.0, 2.0].into();
+	assert_close(cholesky.solve(m * x), x);
+	assert!((cholesky.determinant() - m.determinant()).abs() < 1e-9);
+	assert!((cholesky.ln_determinant() - m.determinant().ln()).abs()
+		< 1e-12);
+
+	let indefinite = RowMatrix::from([[1.0, 2.0], [2.0, 1.0]]);
+	assert!(indefinite.cholesky().is_none());
+}
+
+#[test]
+fn norms() {
+	let m = RowMatrix::from([[1.0, -2.0], [-3.0, 4.0]]);
+	assert_eq!(m.one_norm(), 6.0);
+	assert_eq!(m.infinity_norm(), 7.0);
+	assert_eq!(m.max_norm(), 4.0);
+	assert!((m.frobenius_norm() - 30f64.sqrt()).abs() < 1e-15);
+}
+
+#[test]
+fn errors() {
+	let singular = RowMatrix::from([[1.0, 2.0], [2.0, 4.0]]);
+	assert_eq!(singular.checked_lu().unwrap_err(), Error::Singular);
+	// The same error with and without LAPACK
+	assert_eq!(singular.try_inverse().unwrap_err(), Error::Singular);
+
+	let nan = RowMatrix::from([[1.0, f64::NAN], [0.0, 1.0]]);
+	assert_eq!(nan.checked_lu().unwrap_err(), Error::NotFinite);
+	assert_eq!(nan.try_expm().unwrap_err(), Error::NotFinite);
+}
@@ -0,0 +1,105 @@
+use linalg::{DMatrix, DVector, Error, RowMatrix, Vector};
+
+#[test]
+fn conversions() {
+	let m = RowMatrix::from([[1.0, 2.0], [3.0, 4.0]]);
+	let d = DMatrix::from(m);
+	assert_eq!(d.shape(), (2, 2));
+	assert_eq!(d[(1, 0)], 3.0);
+	assert_eq!(RowMatrix::<f64, 2, 2>::try_from(d.clone()), Ok(m));
+	assert_eq!(
+		RowMatrix::<f64, 3, 3>::try_from(d),
+		Err(Error::Dimensions {
+			expected: (3, 3),
+			found: (2, 2)
+		})
+	);
+
+	let v = Vector::from([1.0, 2.0, 3.0]);
+	let d = DVector::from(v);
+	assert_eq!(Vector::<f64, 3>::try_from(d.clone()), Ok(v));
+	assert!(Vector::<f64, 2>::try_from(d).is_err());
+
+	let ragged = DMatrix::try_from(vec![vec![1.0, 2.0], vec![3.0]]);
+	assert!(ragged.is_err());
+}
+
+#[test]
+fn arithmetic() {
+	let a = DMatrix::try_from(vec![
+		vec![1.0, 2.0, 3.0],
+		vec![4.0, 5.0, 6.0],
+	])
+	.unwrap();
+	let b = a.transpose();
+	assert_eq!(b.shape(), (3, 2));
+
+	let product = &a * &b;
+	assert_eq!(product.as_slice(), &[14.0, 32.0, 32.0, 77.0]);
+	assert_eq!(product.trace(), 91.0);
+	assert_eq!(DMatrix::<f64>::zeros(0, 0).trace(), 0.0);
+	assert!(product.is_symmetric());
+
+	let v = DVector::from(vec![1.0, 0.0, -1.0]);
+	assert_eq!((&a * &v).as_slice(), &[-2.0, -2.0]);
+
+	let sum = a.clone() + &a;
+	assert_eq!(sum, a.clone() * 2.0);
+	assert_eq!(a.clone().component_mul(&a)[1], [16.0, 25.0, 36.0]);
+
+	assert_eq!(a.one_norm(), 9.0);
+	assert_eq!(a.infinity_norm(), 15.0);
+	assert_eq!(a.max_norm(), 6.0);
+
+	let v = DVector::from(vec![3.0, 4.0]);
+	assert_eq!(v.norm(), 5.0);
+	assert_eq!(v.dot_product(&v), 25.0);
+	assert_eq!((v.clone() + &v).sum(), 14.0);
+}
+
+#[test]
+fn cholesky() {
+	let m = DMatrix::try_from(vec![
+		vec![4.0, 2.0, 0.4],
+		vec![2.0, 5.0, 1.0],
+		vec![0.4, 1.0, 3.0],
+	])
+	.unwrap();
+	let lower = m.cholesky().unwrap();
+	let product = &lower * &lower.transpose();
+	for i in 0..3 {
+		for j in 0..3 {
+			assert!((product[(i, j)] - m[(i, j)]).abs() < 1e-12);
+		}
+	}
+	assert_eq!(lower[(0, 2)], 0.0);
+
+	let indefinite =
+		DMatrix::try_from(vec![vec![1.0, 2.0], vec![2.0, 1.0]])
+			.unwrap();
+	assert!(indefinite.cholesky().is_none());
+	assert!(DMatrix::<f64>::ones(2, 3).cholesky().is_none());
+}
+
+#[test]
+fn inverse() {
+	let m = DMatrix::try_from(vec![
+		vec![4.0, 7.0, 2.0],
+		vec![3.0, 6.0, 1.0],
+		vec![2.0, 5.0, 3.0],
+	])
+	.unwrap();
+	let product = &m * &m.inverse();
+	let identity = DMatrix::<f64>::identity(3);
+	for i in 0..3 {
+		for j in 0..3 {
+			assert!((product[(i, j)] - identity[(i, j)]).abs()
+				< 1e-12);
+		}
+	}
+
+	let singular = DMatrix::try_from(vec![vec![1.0, 2.0], vec![2.0, 4.0]])
+		.unwrap();
+	assert_eq!(singular.try_inverse().unwrap_err(), Error::Singular);
+	assert!(DMatrix::<f64>::zeros(2, 3).try_inverse().is_err());
+}
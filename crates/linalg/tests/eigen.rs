@@ -1,7 +1,7 @@
 use approx::assert_relative_eq;
 use proptest::prelude::*;
 
-use linalg::{proptest::symmetric, RowMatrix};
+use linalg::{proptest::symmetric, Complex64, DMatrix, Error, RowMatrix};
 
 #[test]
 fn roundtrip() {
@@ -20,6 +20,72 @@ fn roundtrip() {
 	assert_relative_eq!(jc * 0.1, inverse * (diag * 0.1) * eigenvectors);
 }
 
+#[test]
+fn dynamic() {
+	let q = RowMatrix::from([
+		[-1.0, 0.5, 0.5],
+		[0.25, -0.5, 0.25],
+		[0.5, 0.5, -1.0],
+	]);
+	let (values, right, left) = q.eigen();
+	let (d_values, d_right, d_left) = DMatrix::from(q).eigen();
+
+	assert_eq!(d_values.as_slice(), values.as_slice());
+	assert_eq!(d_right, DMatrix::from(right));
+	assert_eq!(d_left, DMatrix::from(left));
+	assert_relative_eq!(
+		DMatrix::from(q).inverse(),
+		DMatrix::from(q.inverse()),
+	);
+}
+
+#[test]
+fn complex() {
+	// A cyclic irreversible model, its eigenvalues are `0` and
+	// `-1.5 ± i sqrt(3) / 2`
+	let q = RowMatrix::from([
+		[-1.0, 1.0, 0.0],
+		[0.0, -1.0, 1.0],
+		[1.0, 0.0, -1.0],
+	]);
+
+	let eigen = q.complex_eigen();
+	let (values, right, left) =
+		(eigen.values(), eigen.right(), eigen.left());
+	for i in 0..3 {
+		for j in 0..3 {
+			let q_r: Complex64 =
+				(0..3).map(|k| q[(j, k)] * right[(i, k)]).sum();
+			let l_q: Complex64 =
+				(0..3).map(|k| left[(i, k)] * q[(k, j)]).sum();
+			assert!((q_r - values[i] * right[(i, j)]).norm()
+				< 1e-10);
+			assert!((l_q - values[i] * left[(i, j)]).norm() < 1e-10);
+		}
+	}
+
+	assert_relative_eq!(eigen.reconstruct(), q, epsilon = 1e-10);
+	assert_relative_eq!(eigen.exp(0.8), (q * 0.8).expm(), epsilon = 1e-10);
+
+	// The real decompositions can't represent it
+	assert_eq!(q.try_eigen().unwrap_err(), Error::ComplexEigenvalues);
+	assert_eq!(
+		DMatrix::from(q).try_eigen().unwrap_err(),
+		Error::ComplexEigenvalues
+	);
+
+	// While the infallible ones keep the real parts
+	let mut real = q.complex_eigenvalues().map(|v| v.re);
+	let mut values = q.eigen().0;
+	real.as_mut_slice().sort_by(f64::total_cmp);
+	values.as_mut_slice().sort_by(f64::total_cmp);
+	assert_relative_eq!(values, real, epsilon = 1e-10);
+	assert_eq!(
+		DMatrix::from(q).eigen().0.as_slice(),
+		q.eigen().0.as_slice()
+	);
+}
+
 proptest! {
 	#[test]
 	fn symmetric_eigen_2(m in symmetric::<2>()) {
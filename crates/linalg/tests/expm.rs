@@ -0,0 +1,45 @@
+use linalg::RowMatrix;
+
+fn assert_close<const N: usize>(
+	a: RowMatrix<f64, N, N>,
+	b: RowMatrix<f64, N, N>,
+) {
+	for i in 0..N {
+		for j in 0..N {
+			assert!(
+				(a[(i, j)] - b[(i, j)]).abs() < 1e-12,
+				"{a} != {b}"
+			);
+		}
+	}
+}
+
+#[test]
+fn diagonal() {
+	let m = RowMatrix::from_diagonal([0.0, -1.5, 3.0].into());
+	let expected = RowMatrix::from_diagonal(
+		[1.0, (-1.5f64).exp(), 3.0f64.exp()].into(),
+	);
+	assert_close(m.expm(), expected);
+}
+
+#[test]
+fn rotation() {
+	// The eigenvalues are `±i * theta`, so the eigen-decomposition can't be
+	// used.
+	let theta = 2.5f64;
+	let m = RowMatrix::from([[0.0, -theta], [theta, 0.0]]);
+	let expected = RowMatrix::from([
+		[theta.cos(), -theta.sin()],
+		[theta.sin(), theta.cos()],
+	]);
+	assert_close(m.expm(), expected);
+}
+
+#[test]
+fn nilpotent() {
+	// Defective matrix, `e^A = I + A`
+	let m = RowMatrix::from([[0.0, 4.0], [0.0, 0.0]]);
+	let expected = RowMatrix::from([[1.0, 4.0], [0.0, 1.0]]);
+	assert_close(m.expm(), expected);
+}
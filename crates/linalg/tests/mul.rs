@@ -0,0 +1,22 @@
+use linalg::{RowMatrix, Vector};
+
+#[test]
+fn mul_slice() {
+	let m = RowMatrix::from([
+		[1.0, 2.0, 3.0],
+		[4.0, 5.0, 6.0],
+		[7.0, 8.0, 9.0],
+	]);
+	let vectors: Vec<Vector<f64, 3>> = (0..5)
+		.map(|i| [i as f64, 1.0 - i as f64, 0.5 * i as f64].into())
+		.collect();
+
+	let mut out = vec![Vector::default(); vectors.len()];
+	m.mul_slice(&vectors, &mut out);
+
+	for (vector, out) in vectors.iter().zip(&out) {
+		// All of the values are exactly representable, so there's no
+		// rounding error
+		assert_eq!(m * *vector, *out);
+	}
+}
@@ -14,6 +14,18 @@ pub struct PyRng {
 }
 
 impl PyRng {
+	/// Creates a generator for one of the independent streams derived from
+	/// `seed`.  Generators with the same seed and different `stream` values
+	/// use different increments of PCG, so they are independent streams.
+	pub fn from_stream(seed: u64, stream: u64) -> Self {
+		let state: u128 = Pcg64::seed_from_u64(seed).random();
+		let inner = Pcg64::new(state, stream.into());
+
+		PyRng {
+			inner: Mutex::new(inner),
+		}
+	}
+
 	/// Returns the guard of the Rust rng provider
 	///
 	/// # Panics
@@ -39,6 +51,14 @@ impl PyRng {
 		})
 	}
 
+	/// Creates a generator for one of the independent streams derived from
+	/// `seed`.
+	#[staticmethod]
+	#[pyo3(name = "from_stream")]
+	fn py_from_stream(seed: u64, stream: u64) -> Self {
+		Self::from_stream(seed, stream)
+	}
+
 	#[pyo3(signature = (ratio = 0.5))]
 	fn random_bool(&self, ratio: f64) -> bool {
 		self.inner().random_bool(ratio)
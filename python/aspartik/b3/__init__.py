@@ -8,9 +8,23 @@ class Prior(Protocol):
     def probability(self) -> float: ...
 
 
+@runtime_checkable
+class State(Protocol):
+    def accept(self) -> None: ...
+    def reject(self) -> None: ...
+
+
 from .._aspartik_rust_impl import _b3_rust_impl
 
-for item in ["Likelihood", "Parameter", "Proposal", "MCMC", "Tree"]:
+for item in [
+    "Likelihood",
+    "Parameter",
+    "Proposal",
+    "MCMC",
+    "Tree",
+    "Compound",
+    "MarginalLikelihood",
+]:
     locals()[item] = getattr(_b3_rust_impl, item)
 
 from . import loggers, operators, priors, substitutions
@@ -24,6 +38,8 @@ __all__ = [
     "Proposal",
     "MCMC",
     "Tree",
+    "Compound",
+    "MarginalLikelihood",
     # Rust submodules
     "tree",
     # Python
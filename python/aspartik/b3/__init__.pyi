@@ -1,6 +1,16 @@
 from __future__ import annotations
-from typing import List, Any, Optional, Sequence, Tuple, Protocol, runtime_checkable
-from collections.abc import Iterator
+from typing import (
+    List,
+    Any,
+    Callable,
+    Literal,
+    Optional,
+    Sequence,
+    Tuple,
+    Protocol,
+    runtime_checkable,
+)
+from collections.abc import Iterable, Iterator, Mapping
 
 from ..rng import RNG
 from .tree import Node, Leaf, Internal
@@ -22,6 +32,9 @@ class Tree:
     def num_internals(self) -> int: ...
     @property
     def num_leaves(self) -> int: ...
+    @property
+    def names(self) -> list[str]:
+        """Names of the leaves, indexed by leaf"""
     def is_internal(self, node: Node) -> bool: ...
     def is_leaf(self, node: Node) -> bool: ...
     def as_internal(self, node: Node) -> Optional[Internal]: ...
@@ -39,7 +52,32 @@ class Tree:
     def nodes(self) -> Iterator[Node]: ...
     def internals(self) -> Iterator[Internal]: ...
     def verify(self) -> None: ...
-    def newick(self) -> str: ...
+    def newick(
+        self,
+        label_internals: bool = False,
+        annotations: Optional[
+            Sequence[Mapping[str, float | str | Iterable[float | str]]]
+        ] = None,
+    ) -> str:
+        """Serializes the tree into Newick
+
+        If `label_internals` is set, internal nodes are named by their indices.
+        `annotations` must have a mapping for every node, indexed by node.  The
+        values are written as `[&key=value,...]` comments after the node names,
+        with strings in double quotes and collections as `{a,b}` sets, like
+        the HPD ranges of BEAST.
+        """
+    @property
+    def version(self) -> int:
+        """Changes on every edit of the tree
+
+        Rejected proposals roll it back together with the tree, so values
+        derived from the tree can be cached along with the version.
+        """
+
+    @property
+    def is_dirty(self) -> bool:
+        """Whenever the tree was edited by the current proposal"""
 
 class Proposal:
     @staticmethod
@@ -49,6 +87,20 @@ class Proposal:
     @staticmethod
     def Accept() -> Proposal: ...
 
+class Compound:
+    """Applies several operators in a row as a single move
+
+    If any of the operators rejects the move, the whole move is rejected.
+    Otherwise the Hastings ratios are summed.  The posterior is only
+    calculated once, after all of the operators have been applied.  Compound
+    operators can be nested.
+    """
+
+    def __init__(self, operators: Sequence[Operator], weight: float = 1): ...
+    def propose(self) -> Proposal: ...
+    @property
+    def weight(self) -> float: ...
+
 class Parameter:
     """An arbitrary multi-dimensional parameter
 
@@ -86,6 +138,18 @@ class Parameter:
     def is_real(self) -> bool: ...
     def is_integer(self) -> bool: ...
     def is_boolean(self) -> bool: ...
+    @property
+    def version(self) -> int:
+        """Changes every time the parameter is written to
+
+        Rejected proposals roll it back together with the values, so a result
+        computed from the parameter can be cached along with the version and
+        reused for as long as they match.
+        """
+
+    @property
+    def is_dirty(self) -> bool:
+        """Whenever the parameter was changed by the current proposal"""
 
 class Likelihood:
     def __init__(
@@ -94,7 +158,144 @@ class Likelihood:
         substitution: Any,
         tree: Tree,
         use_gpu: bool = False,
-    ): ...
+        gaps: Literal["missing", "state"] = "missing",
+        threads: int = 1,
+        layout: Literal["pattern", "node"] = "pattern",
+    ):
+        """
+        Ambiguous nucleotides are treated as the set of bases they stand for,
+        so `R` is equally compatible with adenine and guanine.  By default gaps
+        are treated as missing data.  If `gaps` is `"state"`, they become a
+        fifth state, and `substitution` must have 5 dimensions.  The GPU
+        likelihood only supports 4 states.
+
+        With `threads` above 1 the site patterns are split between that many
+        threads of a dedicated pool.  The result is the same as with a single
+        thread.  It can't be combined with `use_gpu`.
+
+        `layout` selects how the partials are stored on the CPU.  The default
+        `"pattern"` keeps the states of all nodes for each site pattern
+        together, while `"node"` stores the patterns of each node in one
+        contiguous block.  Which one is faster depends on the data, and the
+        node-major layout only runs on a single thread.
+        """
+
+    @staticmethod
+    def discrete(
+        traits: Sequence[str],
+        states: Sequence[str],
+        substitution: Any,
+        tree: Tree,
+        threads: int = 1,
+        layout: Literal["pattern", "node"] = "pattern",
+    ) -> Likelihood:
+        """Likelihood of a discrete trait, such as the sampling location
+
+        `traits` has the value of the trait for every leaf, in the same order
+        as the sequences of the nucleotide likelihood.  Each value is one of
+        `states`, several of them separated by `|` if the observation is
+        ambiguous, or `?` if it's unknown.  `substitution` must have as many
+        dimensions as there are states, up to 64.  See
+        `substitutions.Discrete`.
+
+        `threads` and `layout` are the same as for the nucleotide likelihood.
+        """
+
+    @staticmethod
+    def continuous(
+        traits: Sequence[Sequence[float]],
+        precision: Parameter,
+        tree: Tree,
+        rates: Optional[Parameter] = None,
+        root_mean: Optional[Sequence[float]] = None,
+        root_sample_size: float = 1.0,
+    ) -> Likelihood:
+        """Likelihood of a continuous trait under Brownian diffusion
+
+        `traits` has the values of the trait for every leaf, for example the
+        latitude and the longitude.  The trait of a node is normally
+        distributed around the trait of its parent with the covariance equal to
+        the branch length times the inverse of `precision`, a real parameter
+        holding the `d` by `d` precision matrix in row-major order.  The
+        likelihood is negative infinity if the matrix isn't positive definite.
+
+        `rates` are the relaxed random walk scalars which multiply the branch
+        lengths, one for each edge.  The internal states are integrated out.
+        The root has a flat prior, unless `root_mean` is set, in which case
+        it's normally distributed around it with the covariance of the
+        diffusion divided by `root_sample_size`.
+        """
+
+    def pattern_likelihoods(self) -> List[float]:
+        """Log likelihoods of the unique site patterns in the accepted state
+
+        They are not multiplied by `pattern_weights`.  The values are only
+        calculated when requested, so they don't slow down the chain.
+        """
+
+    def site_likelihoods(self) -> List[float]:
+        """Log likelihoods of every alignment site in the accepted state"""
+
+    def ancestral_marginal(self) -> List[List[List[float]]]:
+        """Marginal state probabilities of the internal nodes in the accepted state
+
+        The result is indexed by the internal node (in the order of
+        `Tree.internals`), then by the alignment site, and then by the state.
+        The root states are weighted equally, like in the likelihood itself.
+        Not supported by the GPU likelihood.
+        """
+
+    def sample_ancestral(self, rng: RNG) -> List[DNASeq]:
+        """Jointly samples the sequences of the internal nodes
+
+        The sequences are in the order of `Tree.internals`.  Each site is
+        sampled independently from the accepted state.  Not supported by the
+        GPU likelihood.
+        """
+
+    def sample_ancestral_states(self, rng: RNG) -> List[List[int]]:
+        """Jointly samples the states of the internal nodes
+
+        The result is indexed by the internal node (in the order of
+        `Tree.internals`) and then by the site, and holds the indices of the
+        states.  A discrete trait has a single site.  Not supported by the GPU
+        likelihood.
+        """
+
+    def sample_positions(self, rng: RNG) -> List[List[float]]:
+        """Jointly samples the continuous traits of all nodes
+
+        The result is indexed by node.  The leaves keep their observed values.
+        Only supported by the continuous trait likelihood.
+        """
+
+    def markov_jumps(
+        self, register: Sequence[Sequence[float]], rng: RNG
+    ) -> Tuple[List[float], List[float]]:
+        """Counts substitutions on every branch in the accepted state
+
+        `register` is a square matrix with a weight for every pair of states,
+        its diagonal is ignored.  Substitution histories are sampled with
+        uniformization, conditioned on jointly sampled ancestral states.
+
+        Returns the counts realized in the sampled histories and the counts
+        expected under their posterior distribution, summed over all sites.
+        Both lists are indexed by node and hold the count on the edge above
+        it, so the root has 0.  Not supported by the GPU likelihood.
+        """
+
+    @property
+    def states(self) -> int:
+        """Number of states of the substitution model
+
+        It's 5 for nucleotides with `gaps="state"` and the number of trait
+        states for `Likelihood.discrete`.  Not supported by the continuous
+        trait likelihood.
+        """
+
+    @property
+    def pattern_weights(self) -> List[float]:
+        """Number of alignment sites with each pattern"""
 
 @runtime_checkable
 class Prior(Protocol):
@@ -128,6 +329,46 @@ class Operator(Protocol):
         still be used.
         """
 
+class State(Protocol):
+    def accept(self) -> None:
+        """Keeps the changes made during the current step"""
+
+    def reject(self) -> None:
+        """Rolls back the changes made during the current step
+
+        Trees and parameters passed to `MCMC` are rolled back automatically.
+        This protocol is for other stateful objects, such as clock models which
+        cache their rates.
+        """
+
+class MarginalLikelihood:
+    """Log marginal likelihood estimates returned by `MCMC.marginal_likelihood`
+
+    The standard errors assume that the samples are independent, so they
+    underestimate the error of autocorrelated chains.
+    """
+
+    @property
+    def powers(self) -> List[float]:
+        """Powers of the likelihood at which the chain was run"""
+
+    @property
+    def means(self) -> List[float]:
+        """Mean of the collected values at each power"""
+
+    @property
+    def generalized(self) -> bool:
+        """Whenever a working distribution was used"""
+
+    @property
+    def path_sampling(self) -> float: ...
+    @property
+    def path_sampling_error(self) -> float: ...
+    @property
+    def stepping_stone(self) -> float: ...
+    @property
+    def stepping_stone_error(self) -> float: ...
+
 class Logger(Protocol):
     @property
     def every(self) -> int:
@@ -156,15 +397,69 @@ class MCMC:
         likelihoods: Sequence[Likelihood],
         loggers: Sequence[Logger],
         rng: RNG,
-    ): ...
+        states: Sequence[State] = (),
+        sample_prior: bool = False,
+    ):
+        """
+        If `sample_prior` is set, the likelihoods are never calculated and are
+        treated as zero, so the chain samples from the prior.  This is useful
+        for checking that the priors and calibrations induce the intended
+        distribution of node heights.  Loggers are called as usual.
+        """
+
     def run(self) -> None: ...
+    @staticmethod
+    def run_chains(
+        factory: Callable[[int, RNG], MCMC],
+        chains: int,
+        seed: int,
+        threads: Optional[int] = None,
+    ) -> List[MCMC]:
+        """Runs several independent chains in parallel
+
+        `factory` is called with the index of a chain and its random number
+        generator, and must build a complete model (tree, parameters,
+        operators, loggers) which doesn't share any objects with the other
+        chains.  Give each chain its own log files, for example by putting the
+        index into the file names.  The generators are derived from `seed`
+        with `RNG.from_stream`, so the runs are reproducible.
+
+        The chains run on `threads` threads, one per chain by default.  Python
+        code (operators, priors, loggers) still runs one chain at a time, but
+        likelihood calculations run in parallel.
+
+        Returns the finished chains in the order of their indices.
+        """
+
+    def marginal_likelihood(
+        self,
+        steps: int = 100,
+        alpha: float = 0.3,
+        working: Sequence[Prior] = (),
+    ) -> MarginalLikelihood:
+        """Estimates the log marginal likelihood of the model
+
+        The chain is run with the likelihood raised to `steps + 1` powers from
+        0 to 1, spaced according to the quantiles of `Beta(alpha, 1)`.  At each
+        power the chain makes `length` steps, and the likelihoods of all
+        states after the first `burnin` are collected.  Loggers aren't called.
+
+        If `working` priors are passed, their sum is used as the working
+        distribution of the generalized stepping-stone method.  It should
+        approximate the posterior, for example by fitting distributions to the
+        samples of a previous run.
+        """
+
     @property
     def posterior(self) -> float:
         """Posterior probability for the last accepted step"""
 
     @property
     def likelihood(self) -> float:
-        """Total likelihood for the last accepted step"""
+        """Total likelihood for the last accepted step
+
+        Always 0 when sampling from the prior.
+        """
 
     @property
     def prior(self) -> float:
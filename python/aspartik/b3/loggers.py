@@ -5,32 +5,37 @@ function.
 """
 
 from dataclasses import dataclass
-from collections.abc import Mapping
+from typing import Optional
+from collections.abc import Mapping, Sequence
 import json
 
-from . import MCMC, Tree, Parameter, Prior
+from . import MCMC, Likelihood, Tree, Parameter, Prior
+from ..io.nexus import NexusTreesWriter
+from ..rng import RNG
 
 
 @dataclass
 class TreeLogger:
-    """Records the topology of the tree into a `.trees` file."""
+    """Records the tree into a NEXUS `.trees` file.
+
+    The file has the same layout as the ones written by BEAST, so it can be
+    opened by TreeAnnotator and FigTree directly.
+    """
 
     tree: Tree
     path: str
     """
-    Path to the file where the trees will be appended in Newick format, one per
-    line.  It's opened verbatim (the `.trees` extension won't be added).
+    Path to the file where the trees will be written, named `STATE_<index>`.
+    It's opened verbatim (the `.trees` extension won't be added).
     """
     every: int
     """How often the logger will be called"""
 
     def __post_init__(self):
-        self._file = open(self.path, "w")
+        self._writer = NexusTreesWriter(self.path, self.tree.names)
 
     def log(self, mcmc: MCMC, index: int):
-        line = self.tree.newick()
-        self._file.write(line)
-        self._file.write("\n")
+        self._writer.write(f"STATE_{index}", self.tree.newick())
 
 
 @dataclass
@@ -75,3 +80,230 @@ class ValueLogger:
         entry_json = json.dumps(entry)
         self._file.write(entry_json)
         self._file.write("\n")
+
+
+@dataclass
+class SiteLogger:
+    """Records the per-site log likelihoods of the accepted states.
+
+    Each logged state is written as a tab-separated line, with the step index
+    in the first column and a column per site after that.  The result can be
+    used to compute WAIC or PSIS-LOO, or to look for poorly fitting sites.
+    """
+
+    likelihood: Likelihood
+    path: str
+    every: int
+    patterns: bool = False
+    """
+    If set, the log likelihoods of the unique site patterns are written instead
+    of every alignment site.  The number of sites with each pattern is in
+    `Likelihood.pattern_weights`.
+    """
+
+    def __post_init__(self):
+        self._file = open(self.path, "w")
+
+        if self.patterns:
+            num_columns = len(self.likelihood.pattern_weights)
+            name = "pattern"
+        else:
+            num_columns = len(self.likelihood.site_likelihoods())
+            name = "site"
+        header = ["state"] + [f"{name}{i + 1}" for i in range(num_columns)]
+        self._file.write("\t".join(header))
+        self._file.write("\n")
+
+    def log(self, mcmc: MCMC, index: int):
+        if self.patterns:
+            values = self.likelihood.pattern_likelihoods()
+        else:
+            values = self.likelihood.site_likelihoods()
+
+        self._file.write("\t".join([str(index)] + [repr(v) for v in values]))
+        self._file.write("\n")
+
+
+@dataclass
+class AncestralLogger:
+    """Records jointly sampled ancestral sequences together with the tree.
+
+    Each logged state is a JSON object on its own line, with the keys `state`
+    (the step index), `tree` (Newick with internal nodes named by their
+    indices), and `sequences`, which maps the internal node indices to their
+    sampled sequences.
+    """
+
+    likelihood: Likelihood
+    tree: Tree
+    """The tree used by `likelihood`"""
+    rng: RNG
+    path: str
+    every: int
+
+    def __post_init__(self):
+        self._file = open(self.path, "w")
+
+    def log(self, mcmc: MCMC, index: int):
+        sequences = self.likelihood.sample_ancestral(self.rng)
+        # Internal nodes are numbered right after the leaves
+        first = self.tree.num_leaves()
+
+        entry = {
+            "state": index,
+            "tree": self.tree.newick(label_internals=True),
+            "sequences": {
+                str(first + i): str(seq) for i, seq in enumerate(sequences)
+            },
+        }
+        self._file.write(json.dumps(entry))
+        self._file.write("\n")
+
+
+TRANSITIONS = [
+    [0, 0, 1, 0],
+    [0, 0, 0, 1],
+    [1, 0, 0, 0],
+    [0, 1, 0, 0],
+]
+"""Register counting nucleotide transitions (A <-> G and C <-> T)"""
+
+TRANSVERSIONS = [
+    [0, 1, 0, 1],
+    [1, 0, 1, 0],
+    [0, 1, 0, 1],
+    [1, 0, 1, 0],
+]
+"""Register counting nucleotide transversions"""
+
+
+@dataclass
+class MarkovJumpLogger:
+    """Records substitution counts on every branch as an annotated tree.
+
+    Each logged state is written as a Newick line, where every node has the
+    annotation `[&jumps=...,expected=...]` with the realized and expected
+    number of registered substitutions on the branch above it.  See
+    `Likelihood.markov_jumps` for details.
+    """
+
+    likelihood: Likelihood
+    tree: Tree
+    """The tree used by `likelihood`"""
+    register: Sequence[Sequence[float]]
+    """
+    Weight of each substitution.  `TRANSITIONS` and `TRANSVERSIONS` are the
+    registers for the two classes of nucleotide substitutions.  A register
+    smaller than the number of states of `likelihood` is padded with zeros,
+    so the nucleotide registers ignore changes to and from the gap state of
+    `gaps="state"`.
+    """
+    rng: RNG
+    path: str
+    every: int
+
+    def __post_init__(self):
+        states = self.likelihood.states
+        size = len(self.register)
+        if size > states or any(len(row) != size for row in self.register):
+            raise ValueError(
+                f"The register must be a square matrix with at most {states} rows"
+            )
+        self._register = [
+            [
+                self.register[i][j] if i < size and j < size else 0.0
+                for j in range(states)
+            ]
+            for i in range(states)
+        ]
+        self._file = open(self.path, "w")
+
+    def log(self, mcmc: MCMC, index: int):
+        realized, expected = self.likelihood.markov_jumps(self._register, self.rng)
+        annotations = [
+            {"jumps": r, "expected": e} for r, e in zip(realized, expected)
+        ]
+
+        self._file.write(self.tree.newick(annotations=annotations))
+        self._file.write("\n")
+
+
+def all_changes(states: int) -> list[list[float]]:
+    """Register counting every change of a trait with `states` states"""
+    return [[float(i != j) for j in range(states)] for i in range(states)]
+
+
+@dataclass
+class TraitLogger:
+    """Records the ancestral states of a discrete trait as an annotated tree.
+
+    Each logged state is written as a Newick line.  Internal nodes are
+    annotated with a jointly sampled `state`, and every node has the realized
+    and expected number of changes of the trait on the branch above it as
+    `changes` and `expected`.  The states and the changes are sampled
+    independently of each other.  `likelihood` must be created with
+    `Likelihood.discrete`.
+    """
+
+    likelihood: Likelihood
+    tree: Tree
+    """The tree used by `likelihood`"""
+    states: Sequence[str]
+    """Names of the trait states, the same as passed to the likelihood"""
+    rng: RNG
+    path: str
+    every: int
+
+    def __post_init__(self):
+        self._file = open(self.path, "w")
+        self._register = all_changes(len(self.states))
+
+    def log(self, mcmc: MCMC, index: int):
+        realized, expected = self.likelihood.markov_jumps(self._register, self.rng)
+        annotations: list[dict[str, float | str]] = [
+            {"changes": r, "expected": e} for r, e in zip(realized, expected)
+        ]
+
+        num_leaves = self.tree.num_leaves()
+        sampled = self.likelihood.sample_ancestral_states(self.rng)
+        for i, sites in enumerate(sampled):
+            annotations[num_leaves + i]["state"] = self.states[sites[0]]
+
+        self._file.write(self.tree.newick(annotations=annotations))
+        self._file.write("\n")
+
+
+@dataclass
+class DiffusionLogger:
+    """Records the positions of a continuous trait as an annotated tree.
+
+    Each logged state is written as a Newick line, where every node is
+    annotated with its trait values.  The values of the internal nodes are
+    sampled jointly given the tips, see `Likelihood.sample_positions`.
+    `likelihood` must be created with `Likelihood.continuous`.
+    """
+
+    likelihood: Likelihood
+    tree: Tree
+    """The tree used by `likelihood`"""
+    rng: RNG
+    path: str
+    every: int
+    names: Optional[Sequence[str]] = None
+    """
+    Annotation keys of the trait dimensions, `trait1`, `trait2`, and so on by
+    default.
+    """
+
+    def __post_init__(self):
+        self._file = open(self.path, "w")
+
+    def log(self, mcmc: MCMC, index: int):
+        positions = self.likelihood.sample_positions(self.rng)
+        names = self.names
+        if names is None:
+            names = [f"trait{i + 1}" for i in range(len(positions[0]))]
+
+        annotations = [dict(zip(names, position)) for position in positions]
+        self._file.write(self.tree.newick(annotations=annotations))
+        self._file.write("\n")
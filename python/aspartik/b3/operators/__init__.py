@@ -1,3 +1,4 @@
+from .. import Compound as Compound
 from ._param_scale import ParamScale as ParamScale
 from ._tree_exchange import (
     NarrowExchange as NarrowExchange,
@@ -9,8 +10,10 @@ from ._root_scale import RootScale as RootScale
 from ._node_slide import NodeSlide as NodeSlide
 from ._delta_exchange import DeltaExchange as DeltaExchange
 from ._wilson_balding import WilsonBalding as WilsonBalding
+from ._bit_flip import BitFlip as BitFlip
 
 __all__ = [
+    "Compound",
     "ParamScale",
     "NarrowExchange",
     "WideExchange",
@@ -18,4 +21,5 @@ __all__ = [
     "RootScale",
     "NodeSlide",
     "DeltaExchange",
+    "BitFlip",
 ]
@@ -0,0 +1,26 @@
+from dataclasses import dataclass
+
+from .. import Proposal, Parameter
+from ...rng import RNG
+
+
+@dataclass
+class BitFlip:
+    """Operator which flips one dimension of a Boolean parameter.
+
+    This operator is analogous to BEAST2's `BitFlipOperator`.  It's used to
+    switch the rate indicators of the `Discrete` substitution model on and off
+    in Bayesian stochastic search variable selection.
+    """
+
+    param: Parameter
+    """A Boolean parameter."""
+    rng: RNG
+    weight: float = 1
+
+    def propose(self) -> Proposal:
+        index = self.rng.random_int(0, len(self.param))
+        self.param[index] = not self.param[index]
+
+        # Flipping is its own inverse, so the move is symmetrical
+        return Proposal.Hastings(0)
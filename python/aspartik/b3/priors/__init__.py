@@ -1,5 +1,6 @@
 from ._bound import Bound as Bound
 from ._distribution import Distribution as Distribution
 from ._yule import Yule
+from ._indicator_count import IndicatorCount as IndicatorCount
 
-__all__ = ["Bound", "Distribution", "Yule"]
+__all__ = ["Bound", "Distribution", "Yule", "IndicatorCount"]
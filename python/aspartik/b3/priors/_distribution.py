@@ -24,15 +24,22 @@ class Distribution:
         else:
             raise Exception("not a distribution")
 
+        self._cache = None
+
     def probability(self) -> float:
         """
         For multi-dimensional parameters the sum of log probabilities of all
         dimensions is returned.
         """
 
+        version = self.param.version
+        if self._cache is not None and self._cache[0] == version:
+            return self._cache[1]
+
         out = 0
 
         for i in range(len(self.param)):
             out += self.distr_prob(self.param[i])
 
+        self._cache = (version, out)
         return out
@@ -0,0 +1,33 @@
+from dataclasses import dataclass
+
+from .. import Parameter
+from ... import stats
+
+
+@dataclass
+class IndicatorCount:
+    """Prior on the number of `True` dimensions of a Boolean parameter.
+
+    In Bayesian stochastic search variable selection the number of switched on
+    rate indicators is usually given a Poisson prior, which keeps the number
+    of rates small unless the data supports them.
+    """
+
+    param: Parameter
+    """A Boolean parameter, such as the indicators of `Discrete`."""
+    distribution: stats.distributions.Discrete
+    """Discrete distribution of the number of `True` dimensions."""
+
+    def __post_init__(self):
+        self._cache = None
+
+    def probability(self) -> float:
+        version = self.param.version
+        if self._cache is not None and self._cache[0] == version:
+            return self._cache[1]
+
+        count = sum(1 for i in range(len(self.param)) if self.param[i])
+        out = self.distribution.ln_pmf(count)
+
+        self._cache = (version, out)
+        return out
@@ -11,9 +11,16 @@ class Yule:
 
     # TODO: check leaf dates
 
+    def __post_init__(self):
+        self._cache = None
+
     def probability(self) -> float:
         tree = self.tree
 
+        versions = (tree.version, self.birth_rate.version)
+        if self._cache is not None and self._cache[0] == versions:
+            return self._cache[1]
+
         rate = self.birth_rate[0]
         assert isinstance(rate, float)
 
@@ -27,4 +34,5 @@ class Yule:
             if node == root:
                 out += diff
 
+        self._cache = (versions, out)
         return out
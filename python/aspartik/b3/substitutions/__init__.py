@@ -1,4 +1,4 @@
-from typing import List, Tuple, ClassVar
+from typing import List, Tuple, ClassVar, Optional
 from dataclasses import dataclass
 from math import prod
 
@@ -22,6 +22,10 @@ class JC:
         3,
     )
 
+    @property
+    def parameters(self) -> List[Parameter]:
+        return []
+
     def get_matrix(self):
         return self.matrix
 
@@ -35,6 +39,10 @@ class K80:
         # TODO: check that kappa is a single-dimensional real
         pass
 
+    @property
+    def parameters(self) -> List[Parameter]:
+        return [self.kappa]
+
     def get_matrix(self):
         k = self.kappa[0]
         s = [
@@ -64,6 +72,10 @@ class F81:
         ]
         self.matrix = normalize(s, 1 - a**2 - c**2 - g**2 - t**2)
 
+    @property
+    def parameters(self) -> List[Parameter]:
+        return []
+
     def get_matrix(self):
         return self.matrix
 
@@ -86,6 +98,10 @@ class HKY:
         if not self.kappa.is_real():
             raise ValueError("Expected a real parameter")
 
+    @property
+    def parameters(self) -> List[Parameter]:
+        return [self.kappa]
+
     def get_matrix(self):
         k = self.kappa[0]
         a, c, g, t = self.frequencies
@@ -107,4 +123,81 @@ class HKY:
         return s
 
 
+@dataclass
+class Discrete:
+    """Substitution model of a discrete trait, such as the sampling location.
+
+    The rates between the states come from `rates`.  If `indicators` is set,
+    each rate is switched on or off by the corresponding dimension of that
+    Boolean parameter, which allows the rates to be selected with Bayesian
+    stochastic search variable selection (BSSVS).  The states have equal
+    frequencies, and the matrix is normalized to one expected change per unit
+    of time.
+    """
+
+    states: int
+    """Number of states of the trait"""
+    rates: Parameter
+    """
+    Rates between the states, one for each pair of states if `symmetric` and
+    one for each ordered pair otherwise.  They are in row-major order of the
+    off-diagonal elements of the matrix (only the upper triangle when
+    symmetric).
+    """
+    indicators: Optional[Parameter] = None
+    """A Boolean parameter with the same number of dimensions as `rates`"""
+    symmetric: bool = True
+
+    def __post_init__(self):
+        if self.states < 2:
+            raise ValueError(f"Expected at least 2 states, got {self.states}")
+
+        self._pairs = []
+        for i in range(self.states):
+            for j in range(self.states):
+                if i < j or (i != j and not self.symmetric):
+                    self._pairs.append((i, j))
+
+        if len(self.rates) != len(self._pairs):
+            raise ValueError(
+                f"Expected {len(self._pairs)} rates, got {len(self.rates)}"
+            )
+        if self.indicators is not None and len(self.indicators) != len(self.rates):
+            raise ValueError(
+                f"Expected {len(self.rates)} indicators, got {len(self.indicators)}"
+            )
+
+    @property
+    def dimensions(self) -> int:
+        return self.states
+
+    @property
+    def parameters(self) -> List[Parameter]:
+        if self.indicators is None:
+            return [self.rates]
+        return [self.rates, self.indicators]
+
+    def get_matrix(self):
+        k = self.states
+        s = [[0.0] * k for _ in range(k)]
+
+        for index, (i, j) in enumerate(self._pairs):
+            rate = self.rates[index]
+            if self.indicators is not None and not self.indicators[index]:
+                rate = 0.0
+            s[i][j] = rate
+            if self.symmetric:
+                s[j][i] = rate
+
+        for i in range(k):
+            s[i][i] = -sum(s[i])
+
+        # With all of the rates switched off the trait can't change
+        total = -sum(s[i][i] for i in range(k)) / k
+        if total == 0:
+            return s
+
+        return normalize(s, total)
+
+
 # TODO: GTR
@@ -1 +1,7 @@
-from . import newick as newick, fasta as fasta
+from . import (
+    newick as newick,
+    nexus as nexus,
+    fasta as fasta,
+    fastq as fastq,
+    alignment as alignment,
+)
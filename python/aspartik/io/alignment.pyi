@@ -0,0 +1,28 @@
+from __future__ import annotations
+
+from os import PathLike
+from typing import IO, Literal, Optional, Union
+
+from .fasta import FASTADNARecord
+
+Format = Literal["fasta", "phylip", "clustal", "stockholm"]
+
+class Alignment:
+    def __init__(
+        self,
+        source: Union[str, PathLike[str], IO[str], IO[bytes]],
+        format: Optional[Format] = None,
+    ):
+        """Reads a DNA alignment from a path or a file-like object.  PHYLIP
+        can be relaxed or strict, sequential or interleaved.  The format is
+        detected from the contents if it isn't passed, and gzipped input is
+        decompressed."""
+    @staticmethod
+    def parse(text: str, format: Optional[Format] = None) -> Alignment: ...
+    @staticmethod
+    def from_records(records: list[FASTADNARecord]) -> Alignment: ...
+    @property
+    def records(self) -> list[FASTADNARecord]: ...
+    def __len__(self) -> int: ...
+    def write(self, path: str, format: Literal["fasta", "phylip"] = "fasta") -> None:
+        """Writes the alignment as FASTA or sequential relaxed PHYLIP."""
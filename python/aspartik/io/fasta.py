@@ -1,6 +1,6 @@
 from .._aspartik_rust_impl import _io_rust_impl
 
-__all__ = ["FASTADNARecord", "FASTADNAReader"]  # noqa: F822
+__all__ = ["FASTADNARecord", "FASTADNAReader", "FASTADNAWriter"]  # noqa: F822
 
 for item in __all__:
     locals()[item] = getattr(_io_rust_impl, item)
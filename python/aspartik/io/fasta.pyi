@@ -1,14 +1,38 @@
 from __future__ import annotations
 
+from os import PathLike
+from typing import IO, Union
+
 from ..data import DNASeq
 
 class FASTADNARecord:
+    def __init__(self, description: str, sequence: DNASeq): ...
     @property
     def sequence(self) -> DNASeq: ...
     @property
+    def id(self) -> str:
+        """The first word of the description."""
+    @property
+    def raw_description(self) -> str: ...
+    @property
     def description(self) -> str: ...
 
 class FASTADNAReader:
-    def __init__(self, path: str): ...
+    def __init__(self, source: Union[str, PathLike[str], IO[str], IO[bytes]]):
+        """Reads records from a path or a file-like object.  Gzip and bgzip
+        input is decompressed automatically."""
+    @staticmethod
+    def from_string(text: str) -> FASTADNAReader: ...
     def __iter__(self) -> FASTADNAReader: ...
     def __next__(self) -> FASTADNARecord: ...
+
+class FASTADNAWriter:
+    def __init__(self, path: str, line_width: int = 60, ids_only: bool = False):
+        """Sequences are wrapped at `line_width` characters, or written on a
+        single line if it's zero.  With `ids_only` the descriptions are cut
+        at the first whitespace."""
+    def write(self, description: str, sequence: DNASeq) -> None: ...
+    def write_record(self, record: FASTADNARecord) -> None: ...
+    def close(self) -> None:
+        """Flushes the file.  It's also done when the writer is garbage
+        collected."""
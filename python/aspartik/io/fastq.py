@@ -0,0 +1,10 @@
+from .._aspartik_rust_impl import _io_rust_impl
+
+__all__ = ["FASTQRecord", "FASTQReader"]  # noqa: F822
+
+for item in __all__:
+    locals()[item] = getattr(_io_rust_impl, item)
+
+
+def __dir__():
+    return __all__
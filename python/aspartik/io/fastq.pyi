@@ -0,0 +1,48 @@
+from __future__ import annotations
+
+from os import PathLike
+from typing import IO, Literal, Optional, Union
+
+from ..data import DNASeq
+
+Encoding = Literal["phred33", "phred64"]
+
+class FASTQRecord:
+    def __init__(self, description: str, sequence: DNASeq, qualities: list[int]):
+        """`qualities` are Phred scores, one per character of `sequence`."""
+    @property
+    def id(self) -> str:
+        """The first word of the description."""
+    @property
+    def description(self) -> str: ...
+    @property
+    def sequence(self) -> DNASeq: ...
+    @property
+    def qualities(self) -> list[int]: ...
+    def __len__(self) -> int: ...
+    def mean_quality(self) -> Optional[float]: ...
+    def error_probabilities(self) -> list[float]: ...
+    def trim(self, start: int, end: int) -> FASTQRecord:
+        """Returns a copy with only the characters in `start..end`."""
+    def trim_ends(self, threshold: int) -> FASTQRecord:
+        """Returns a copy without the characters with qualities below
+        `threshold` at both ends."""
+    def trim_sliding_window(self, window: int, threshold: float) -> FASTQRecord:
+        """Returns a copy cut at the first window of characters with a mean
+        quality below `threshold`."""
+    def trim_bwa(self, threshold: int) -> FASTQRecord:
+        """Returns a copy with the 3' end trimmed by the BWA algorithm."""
+
+class FASTQReader:
+    def __init__(
+        self,
+        source: Union[str, PathLike[str], IO[str], IO[bytes]],
+        encoding: Encoding = "phred33",
+    ):
+        """Reads records from a path or a file-like object.  Phred+33 is used
+        by Sanger and Illumina 1.8+, Phred+64 by Illumina 1.3 to 1.7.  Gzip
+        and bgzip input is decompressed automatically."""
+    @staticmethod
+    def from_string(text: str, encoding: Encoding = "phred33") -> FASTQReader: ...
+    def __iter__(self) -> FASTQReader: ...
+    def __next__(self) -> FASTQRecord: ...
@@ -1,6 +1,6 @@
 from .._aspartik_rust_impl import _io_rust_impl
 
-__all__ = ["Node", "Tree"]  # noqa: F822
+__all__ = ["Node", "Tree", "NewickReader"]  # noqa: F822
 
 for item in __all__:
     locals()[item] = getattr(_io_rust_impl, item)
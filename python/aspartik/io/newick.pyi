@@ -0,0 +1,81 @@
+from __future__ import annotations
+
+from os import PathLike
+from typing import IO, Optional, Sequence, Union
+from collections.abc import Iterable, Mapping
+
+from ..rng import RNG
+
+Value = Union[float, str, Iterable["Value"]]
+"""Value of a `[&key=value,...]` metadata comment.  Collections are written
+as `{a,b}` sets and are read back as lists."""
+
+NodeRef = Union[int, str]
+"""A node index, or the name of a node."""
+
+class Node:
+    def __init__(
+        self,
+        name: str,
+        attributes: Optional[Mapping[str, Value]] = None,
+        distance: Optional[float] = None,
+    ): ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def distance(self) -> Optional[float]: ...
+    @property
+    def attributes(self) -> dict[str, Value]: ...
+
+class Tree:
+    def __init__(self, newick: Optional[str] = None):
+        """Parses `newick` if it's passed, otherwise creates an empty tree."""
+    @staticmethod
+    def parse_all(newick: str) -> list[Tree]:
+        """Parses all of the trees in a string."""
+    def __str__(self) -> str: ...
+
+    # Nodes are referred to by their indices, which stay valid until the node
+    # is removed.  Methods which take nodes also accept node names.
+    @property
+    def root(self) -> Optional[int]: ...
+    def node(self, node: NodeRef) -> Node:
+        """Returns a copy of the node."""
+    def find(self, name: str) -> Optional[int]:
+        """The first node called `name` in preorder."""
+    def parent_of(self, node: NodeRef) -> Optional[int]: ...
+    def children_of(self, node: NodeRef) -> list[int]: ...
+    def leaves(self) -> list[int]:
+        """All leaves from left to right."""
+    def descendants(self, node: NodeRef) -> list[int]:
+        """Descendants of `node` in preorder, without `node` itself."""
+    def mrca(self, nodes: Sequence[NodeRef]) -> int:
+        """The most recent common ancestor of `nodes`."""
+    def distance_between(self, a: NodeRef, b: NodeRef) -> float:
+        """Sum of the branch lengths between `a` and `b`."""
+    def cophenetic(self) -> list[list[float]]:
+        """Path lengths between all pairs of leaves, in the order of `leaves`.
+        Missing branch lengths count as zero."""
+    def reroot(self, node: NodeRef) -> None:
+        """Makes `node` the root.  An old root left with a single child is
+        removed."""
+    def reroot_outgroup(self, outgroup: Sequence[NodeRef]) -> None:
+        """Roots the tree in the middle of the branch above the outgroup."""
+    def reroot_midpoint(self) -> None:
+        """Roots the tree halfway between the two most distant leaves."""
+    def ladderize(self, ascending: bool = True) -> None:
+        """Sorts children by the sizes of their subtrees."""
+    def prune(self, nodes: Sequence[NodeRef]) -> None:
+        """Removes `nodes` with their subtrees, along with the internal nodes
+        left with a single child."""
+    def collapse(self, threshold: float) -> None:
+        """Turns internal branches shorter than `threshold` into
+        polytomies."""
+    def resolve_polytomies(self, rng: RNG) -> None:
+        """Randomly resolves polytomies with zero-length branches."""
+
+class NewickReader:
+    def __init__(self, source: Union[str, PathLike[str], IO[str], IO[bytes]]):
+        """Reads trees from a path or a file-like object."""
+    def __iter__(self) -> NewickReader: ...
+    def __next__(self) -> Tree: ...
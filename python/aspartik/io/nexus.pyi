@@ -0,0 +1,47 @@
+from __future__ import annotations
+
+from typing import Optional
+
+from ..data import DNASeq
+from .newick import Tree
+
+class Nexus:
+    def __init__(self, path: str):
+        """Reads the `TAXA`, `DATA`/`CHARACTERS` and `TREES` blocks of a
+        NEXUS file.  Other blocks are skipped."""
+    @staticmethod
+    def parse(source: str) -> Nexus: ...
+    @property
+    def taxa(self) -> list[str]:
+        """Taxa from the `TAXA` block, or from the first matrix if the file
+        doesn't have one."""
+    @property
+    def characters(self) -> list[Characters]:
+        """Character matrices, one per `DATA` or `CHARACTERS` block."""
+    @property
+    def trees(self) -> list[tuple[str, Tree]]:
+        """Named trees, with the `TRANSLATE` tables applied."""
+
+class Characters:
+    @property
+    def datatype(self) -> str: ...
+    @property
+    def missing(self) -> str: ...
+    @property
+    def gap(self) -> Optional[str]: ...
+    @property
+    def matrix(self) -> list[tuple[str, str]]:
+        """Taxon names and their characters, as written in the file."""
+    def dna(self) -> list[tuple[str, DNASeq]]:
+        """Converts the matrix into DNA sequences.  Gaps become `-` and missing
+        characters `N`."""
+
+class NexusTreesWriter:
+    def __init__(self, path: str, taxa: list[str]):
+        """Creates a `.trees` file in the format of BEAST, with a `TRANSLATE`
+        table for `taxa`."""
+    def write(self, name: str, newick: str) -> None:
+        """Appends a rooted tree, whose leaves must be named after the taxa."""
+    def close(self) -> None:
+        """Ends the trees block.  It's also done when the writer is garbage
+        collected."""
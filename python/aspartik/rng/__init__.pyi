@@ -19,6 +19,14 @@ class RNG:
         The seed is a positive integer less than $2^{64}$.  If no seed is
         passed the RNG will be seeded from the operating system data source.
         """
+    @staticmethod
+    def from_stream(seed: int, stream: int) -> RNG:
+        """Creates a generator for one of the streams derived from `seed`
+
+        Generators with the same seed and different streams are independent
+        streams of the same generator.  This is useful for giving
+        each of several parallel simulations its own reproducible generator.
+        """
     def random_bool(self, ratio: float = 0.5) -> bool:
         """
         Rerturns `True` with the probability of `ratio`, which must be in the